@@ -0,0 +1,51 @@
+use crate::connection_test::PROBE_PREFIX;
+use crate::types::{CorsPreset, CorsRuleConfig, LifecyclePreset, LifecycleRuleConfig};
+
+const CORS_MAX_AGE_SECS: i32 = 60 * 60;
+
+/// CORS rules for `preset`. `origins` defaults to any origin, which is fine
+/// for public images but worth narrowing when uploads are allowed.
+pub fn cors_preset(preset: CorsPreset, origins: Option<Vec<String>>) -> Vec<CorsRuleConfig> {
+    let allowed_origins = origins
+        .map(|origins| {
+            origins
+                .into_iter()
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|origins| !origins.is_empty())
+        .unwrap_or_else(|| vec!["*".to_string()]);
+
+    let (id, methods, expose_headers): (&str, &[&str], &[&str]) = match preset {
+        CorsPreset::PublicRead => ("moli-tutu-public-read", &["GET", "HEAD"], &[]),
+        CorsPreset::BrowserUpload => ("moli-tutu-browser-upload", &["GET", "HEAD", "PUT"], &["ETag"]),
+    };
+
+    vec![CorsRuleConfig {
+        id: Some(id.to_string()),
+        allowed_origins,
+        allowed_methods: methods.iter().map(|method| method.to_string()).collect(),
+        allowed_headers: vec!["*".to_string()],
+        expose_headers: expose_headers.iter().map(|header| header.to_string()).collect(),
+        max_age_seconds: Some(CORS_MAX_AGE_SECS),
+    }]
+}
+
+pub fn lifecycle_preset(preset: LifecyclePreset) -> LifecycleRuleConfig {
+    match preset {
+        LifecyclePreset::AbortIncompleteUploads => LifecycleRuleConfig {
+            id: "moli-tutu-abort-incomplete-uploads".to_string(),
+            enabled: true,
+            abort_incomplete_multipart_days: Some(1),
+            ..Default::default()
+        },
+        LifecyclePreset::CleanupProbeObjects => LifecycleRuleConfig {
+            id: "moli-tutu-cleanup-probes".to_string(),
+            enabled: true,
+            prefix: Some(PROBE_PREFIX.to_string()),
+            expiration_days: Some(1),
+            ..Default::default()
+        },
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::database::Database;
+use crate::r2_uploader::R2Uploader;
+use crate::types::{BucketStats, RemoteObject, UsageGroup};
+
+/// Storage included in the R2 free tier (10 GB-month).
+pub const FREE_TIER_STORAGE_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+const LARGEST_OBJECTS_LIMIT: usize = 20;
+
+/// Lists the whole bucket and aggregates it. This is one ListObjectsV2 call
+/// per 1000 objects, so callers should cache the result.
+pub async fn compute_stats(uploader: &R2Uploader) -> Result<BucketStats> {
+    println!("[BucketStats] Listing bucket objects...");
+    let objects = uploader.list_all_objects(None).await?;
+
+    let mut stats = BucketStats {
+        profile: uploader.config().profile_key(),
+        bucket_name: uploader.config().bucket_name.clone(),
+        free_tier_storage_bytes: FREE_TIER_STORAGE_BYTES,
+        refreshed_at: Database::get_current_timestamp(),
+        ..Default::default()
+    };
+
+    let mut by_prefix: HashMap<String, UsageGroup> = HashMap::new();
+    let mut by_month: HashMap<String, UsageGroup> = HashMap::new();
+    let mut by_content_type: HashMap<String, UsageGroup> = HashMap::new();
+
+    for object in &objects {
+        stats.object_count += 1;
+        stats.total_bytes += object.size;
+
+        let prefix = match object.key.split_once('/') {
+            Some((top, _)) => format!("{}/", top),
+            None => String::new(),
+        };
+        let month = object.last_modified.map(month_of).unwrap_or_default();
+
+        add_to_group(&mut by_prefix, prefix, object.size);
+        add_to_group(&mut by_month, month, object.size);
+        add_to_group(&mut by_content_type, object.content_type.clone(), object.size);
+    }
+
+    stats.by_prefix = sorted_by_bytes(by_prefix);
+    stats.by_content_type = sorted_by_bytes(by_content_type);
+    stats.by_month = by_month.into_values().collect();
+    stats.by_month.sort_by(|a, b| a.name.cmp(&b.name));
+    stats.largest_objects = largest(objects);
+
+    println!(
+        "[BucketStats] {} objects, {} bytes in {}",
+        stats.object_count, stats.total_bytes, stats.bucket_name
+    );
+    Ok(stats)
+}
+
+fn add_to_group(groups: &mut HashMap<String, UsageGroup>, name: String, size: u64) {
+    let group = groups.entry(name.clone()).or_insert_with(|| UsageGroup {
+        name,
+        ..Default::default()
+    });
+    group.object_count += 1;
+    group.total_bytes += size;
+}
+
+fn sorted_by_bytes(groups: HashMap<String, UsageGroup>) -> Vec<UsageGroup> {
+    let mut groups: Vec<UsageGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes).then_with(|| a.name.cmp(&b.name)));
+    groups
+}
+
+fn largest(mut objects: Vec<RemoteObject>) -> Vec<RemoteObject> {
+    objects.sort_by_key(|object| std::cmp::Reverse(object.size));
+    objects.truncate(LARGEST_OBJECTS_LIMIT);
+    objects
+}
+
+/// `YYYY-MM` of a Unix timestamp in UTC.
+pub fn month_of(timestamp: i64) -> String {
+    let (year, month, _) = civil_date(timestamp);
+    format!("{:04}-{:02}", year, month)
+}
+
+/// `YYYY-MM-DD` of a Unix timestamp in UTC.
+pub fn date_of(timestamp: i64) -> String {
+    let (year, month, day) = civil_date(timestamp);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn civil_date(timestamp: i64) -> (i64, i64, i64) {
+    // Howard Hinnant's days-to-civil algorithm.
+    let days = timestamp.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_timestamps_to_utc_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+        assert_eq!(civil_date(951_782_400), (2000, 2, 29));
+        assert_eq!(civil_date(951_868_800), (2000, 3, 1));
+        assert_eq!(civil_date(1_709_251_199), (2024, 2, 29));
+        assert_eq!(date_of(1_735_689_599), "2024-12-31");
+        assert_eq!(month_of(1_735_689_600), "2025-01");
+    }
+}
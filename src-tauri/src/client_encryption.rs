@@ -0,0 +1,59 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::Aes256Gcm;
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+
+/// Marks an object written by `encrypt_payload`, followed by the format version.
+pub const MAGIC: &[u8; 4] = b"MLT1";
+/// Fragment parameter carrying the decryption key in share links.
+pub const KEY_FRAGMENT_PARAM: &str = "key";
+
+pub struct EncryptedPayload {
+    /// `MAGIC || nonce || AES-256-GCM(content type length || content type || data)`.
+    pub ciphertext: Vec<u8>,
+    /// URL-safe base64 key, only ever placed in a URL fragment.
+    pub key: String,
+}
+
+/// Encrypts `data` under a fresh random key. The content type travels inside
+/// the ciphertext so the viewer can render the image without the bucket
+/// learning what it holds.
+pub fn encrypt_payload(data: &[u8], content_type: &str) -> Result<EncryptedPayload> {
+    let content_type = content_type.as_bytes();
+    if content_type.len() > u8::MAX as usize {
+        return Err(anyhow::anyhow!("Content type is too long"));
+    }
+
+    let mut plaintext = Vec::with_capacity(1 + content_type.len() + data.len());
+    plaintext.push(content_type.len() as u8);
+    plaintext.extend_from_slice(content_type);
+    plaintext.extend_from_slice(data);
+
+    let key = Aes256Gcm::generate_key(OsRng);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let sealed = Aes256Gcm::new(&key)
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt image"))?;
+
+    let mut ciphertext = Vec::with_capacity(MAGIC.len() + nonce.len() + sealed.len());
+    ciphertext.extend_from_slice(MAGIC);
+    ciphertext.extend_from_slice(&nonce);
+    ciphertext.extend_from_slice(&sealed);
+
+    Ok(EncryptedPayload {
+        ciphertext,
+        key: general_purpose::URL_SAFE_NO_PAD.encode(key),
+    })
+}
+
+/// `url#key=...`: the fragment is never sent to the server hosting `url`.
+pub fn share_url(object_url: &str, key: &str) -> String {
+    format!("{}#{}={}", object_url, KEY_FRAGMENT_PARAM, key)
+}
+
+/// Link to the API proxy's local viewer, which decrypts `share_url` in the
+/// browser. Everything after `/view#` stays on the client as well;
+/// `view_token` lets the page fetch the ciphertext through the proxy.
+pub fn view_url(proxy_port: u16, view_token: &str, share_url: &str) -> String {
+    format!("http://127.0.0.1:{}/view?token={}#{}", proxy_port, view_token, share_url)
+}
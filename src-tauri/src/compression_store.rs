@@ -0,0 +1,262 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::r2_uploader::R2Uploader;
+use crate::types::{CompressionFiles, CompressionRecord, SaveCompressionRequest};
+use crate::vault::{self, MasterKey};
+
+/// Keeps the original and compressed files of the compression history in a
+/// directory of the app data dir; the records themselves are in the history
+/// database. Files are encrypted with the master key while encryption at
+/// rest is enabled, and are read and written without holding the database.
+#[derive(Clone)]
+pub struct CompressionStore {
+    dir: PathBuf,
+}
+
+impl CompressionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Saves the files, then the record. The original is optional for
+    /// entries moved from the webview's storage that no longer have it.
+    pub fn save(
+        &self,
+        db: &Arc<Mutex<Option<Database>>>,
+        master_key: Option<&MasterKey>,
+        request: SaveCompressionRequest,
+        original: Option<Vec<u8>>,
+        compressed: Vec<u8>,
+    ) -> Result<CompressionRecord> {
+        let record = CompressionRecord {
+            id: Uuid::new_v4().to_string(),
+            original_name: request.original_name,
+            original_size: original
+                .as_ref()
+                .map(|data| data.len() as u64)
+                .or(request.original_size)
+                .unwrap_or(0),
+            compressed_size: compressed.len() as u64,
+            compression_ratio: request.compression_ratio,
+            compressed_at: request.compressed_at.unwrap_or_else(Database::get_current_timestamp),
+            config: request.config,
+            source: request.source,
+            content_type: request.content_type,
+            has_original: original.is_some(),
+            original_hash: original.as_deref().map(R2Uploader::calculate_hash),
+            compressed_hash: R2Uploader::calculate_hash(&compressed),
+            upload_id: None,
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        let written = (|| -> Result<()> {
+            if let Some(original) = &original {
+                write_file(&self.original_path(&record.id), original, master_key)?;
+            }
+            write_file(&self.compressed_path(&record.id), &compressed, master_key)
+        })();
+
+        if let Err(e) = written.and_then(|()| with_db(db, |db| db.insert_compression(&record))) {
+            self.remove_files(&record.id);
+            return Err(e);
+        }
+        println!(
+            "[CompressionStore] Saved compression of {} ({} -> {} bytes)",
+            record.original_name, record.original_size, record.compressed_size
+        );
+        Ok(record)
+    }
+
+    /// The record with both of its files, for previewing or editing it again.
+    pub fn load(
+        &self,
+        db: &Arc<Mutex<Option<Database>>>,
+        master_key: Option<&MasterKey>,
+        id: &str,
+    ) -> Result<CompressionFiles> {
+        let record = with_db(db, |db| db.get_compression(id))?;
+        let original_data = if record.has_original {
+            Some(read_file(&self.original_path(&record.id), master_key)?)
+        } else {
+            None
+        };
+        let compressed_data = read_file(&self.compressed_path(&record.id), master_key)
+            .map_err(|e| anyhow::anyhow!("The compressed file of {} is gone: {}", record.original_name, e))?;
+        Ok(CompressionFiles {
+            record,
+            original_data,
+            compressed_data,
+        })
+    }
+
+    pub fn delete(&self, db: &Arc<Mutex<Option<Database>>>, id: &str) -> Result<()> {
+        // Looked up first so only ids of real records end up in file paths.
+        let record = with_db(db, |db| {
+            let record = db.get_compression(id)?;
+            db.delete_compression(&record.id)?;
+            Ok(record)
+        })?;
+        self.remove_files(&record.id);
+        Ok(())
+    }
+
+    /// Returns the number of records removed.
+    pub fn clear(&self, db: &Arc<Mutex<Option<Database>>>) -> Result<usize> {
+        let records = with_db(db, |db| {
+            let records = db.list_compressions()?;
+            for record in &records {
+                db.delete_compression(&record.id)?;
+            }
+            Ok(records)
+        })?;
+        for record in &records {
+            self.remove_files(&record.id);
+        }
+        Ok(records.len())
+    }
+
+    /// Rewrites every file under `to` (plaintext when `None`). Files already
+    /// under `to` are left alone, so running it again after a failure, or
+    /// with the keys swapped to roll back, picks up where it stopped.
+    pub fn rekey(&self, from: Option<&MasterKey>, to: Option<&MasterKey>) -> Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut rewritten = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if !matches!(
+                path.extension().and_then(|extension| extension.to_str()),
+                Some("original" | "compressed")
+            ) {
+                continue;
+            }
+
+            let file = fs::read(&path)?;
+            let data = match (vault::is_encrypted_file(&file), to) {
+                (false, None) => continue,
+                (true, Some(to)) if to.decrypt_file(&file).is_ok() => continue,
+                (false, Some(_)) => file,
+                (true, _) => from
+                    .ok_or_else(|| anyhow::anyhow!("{} is encrypted", path.display()))?
+                    .decrypt_file(&file)?,
+            };
+
+            let tmp_path = path.with_extension("rekey");
+            write_file(&tmp_path, &data, to)?;
+            fs::rename(&tmp_path, &path)?;
+            rewritten += 1;
+        }
+        Ok(rewritten)
+    }
+
+    fn remove_files(&self, id: &str) {
+        for path in [self.original_path(id), self.compressed_path(id)] {
+            if let Err(e) = fs::remove_file(&path) {
+                if e.kind() != ErrorKind::NotFound {
+                    println!("[CompressionStore] Failed to remove {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    fn original_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.original", id))
+    }
+
+    fn compressed_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.compressed", id))
+    }
+}
+
+/// Runs `f` with the history database, holding its lock only for the call.
+fn with_db<T>(db: &Arc<Mutex<Option<Database>>>, f: impl FnOnce(&Database) -> Result<T>) -> Result<T> {
+    let db_guard = db.lock().unwrap();
+    match db_guard.as_ref() {
+        Some(db) => f(db),
+        None => Err(anyhow::anyhow!("Database not available")),
+    }
+}
+
+fn write_file(path: &Path, data: &[u8], master_key: Option<&MasterKey>) -> Result<()> {
+    match master_key {
+        Some(master_key) => fs::write(path, master_key.encrypt_file(data)?)?,
+        None => fs::write(path, data)?,
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path, master_key: Option<&MasterKey>) -> Result<Vec<u8>> {
+    let file = fs::read(path)?;
+    if !vault::is_encrypted_file(&file) {
+        return Ok(file);
+    }
+    master_key
+        .ok_or_else(|| anyhow::anyhow!("Compression history is locked; unlock the app first"))?
+        .decrypt_file(&file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::Vault;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("moli-compressions-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn request() -> SaveCompressionRequest {
+        SaveCompressionRequest {
+            original_name: "photo.png".to_string(),
+            compression_ratio: 50.0,
+            config: serde_json::json!({ "format": "webp" }),
+            source: Default::default(),
+            content_type: Some("image/webp".to_string()),
+            original_size: None,
+            compressed_at: None,
+        }
+    }
+
+    #[test]
+    fn files_are_encrypted_and_follow_the_master_key() {
+        let dir = temp_dir();
+        let first = Vault::new(dir.join("first")).create("first passphrase").unwrap();
+        let second = Vault::new(dir.join("second")).create("second passphrase").unwrap();
+        let db = Arc::new(Mutex::new(Some(Database::new(PathBuf::from(":memory:"), None).unwrap())));
+        let store = CompressionStore::new(dir.join("compressions"));
+
+        let record = store
+            .save(&db, Some(&first), request(), Some(b"original".to_vec()), b"compressed".to_vec())
+            .unwrap();
+        let on_disk = fs::read(store.compressed_path(&record.id)).unwrap();
+        assert!(vault::is_encrypted_file(&on_disk));
+        assert!(store.load(&db, None, &record.id).is_err());
+
+        assert_eq!(store.rekey(Some(&first), Some(&second)).unwrap(), 2);
+        // Already under the new key, so a second run has nothing to do.
+        assert_eq!(store.rekey(Some(&first), Some(&second)).unwrap(), 0);
+        let files = store.load(&db, Some(&second), &record.id).unwrap();
+        assert_eq!(files.original_data.as_deref(), Some(b"original".as_slice()));
+        assert_eq!(files.compressed_data, b"compressed");
+
+        assert_eq!(store.rekey(Some(&second), None).unwrap(), 2);
+        assert_eq!(fs::read(store.compressed_path(&record.id)).unwrap(), b"compressed");
+        assert_eq!(store.load(&db, None, &record.id).unwrap().compressed_data, b"compressed");
+
+        store.clear(&db).unwrap();
+        assert_eq!(fs::read_dir(dir.join("compressions")).unwrap().count(), 0);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::types::{AppSettings, R2Config};
+use crate::types::{AppSettings, R2Config, SseConfig};
+use crate::vault::{self, MasterKey};
 use anyhow::Result;
 use serde_json;
 use std::fs;
@@ -7,6 +8,8 @@ use std::path::PathBuf;
 pub struct ConfigManager {
     config_path: PathBuf,
     settings_path: PathBuf,
+    /// Set while encryption at rest is enabled and the app is unlocked.
+    master_key: Option<MasterKey>,
 }
 
 impl ConfigManager {
@@ -16,16 +19,35 @@ impl ConfigManager {
         Self {
             config_path,
             settings_path,
+            master_key: None,
         }
     }
 
+    pub fn master_key(&self) -> Option<&MasterKey> {
+        self.master_key.as_ref()
+    }
+
+    pub fn set_master_key(&mut self, master_key: Option<MasterKey>) {
+        self.master_key = master_key;
+    }
+
     pub fn load_config(&self) -> Result<Option<R2Config>> {
         if !self.config_path.exists() {
             return Ok(None);
         }
         
         let content = fs::read_to_string(&self.config_path)?;
-        let config: R2Config = serde_json::from_str(&content)?;
+        let mut config: R2Config = serde_json::from_str(&content)?;
+        for_each_secret(&mut config, |value| {
+            if vault::is_encrypted(value) {
+                let master_key = self
+                    .master_key
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("Configuration is locked; unlock the app first"))?;
+                *value = master_key.decrypt_string(value)?;
+            }
+            Ok(())
+        })?;
         Ok(Some(config))
     }
 
@@ -34,7 +56,17 @@ impl ConfigManager {
             fs::create_dir_all(parent)?;
         }
         
-        let content = serde_json::to_string_pretty(config)?;
+        let mut config = config.clone();
+        if let Some(master_key) = &self.master_key {
+            for_each_secret(&mut config, |value| {
+                if !value.is_empty() {
+                    *value = master_key.encrypt_string(value)?;
+                }
+                Ok(())
+            })?;
+        }
+        
+        let content = serde_json::to_string_pretty(&config)?;
         fs::write(&self.config_path, content)?;
         Ok(())
     }
@@ -63,3 +95,27 @@ impl ConfigManager {
         self.config_path.exists()
     }
 }
+
+/// Visits every config field that must not be written to disk in plaintext
+/// while encryption at rest is enabled.
+fn for_each_secret(
+    config: &mut R2Config,
+    mut f: impl FnMut(&mut String) -> Result<()>,
+) -> Result<()> {
+    f(&mut config.secret_access_key)?;
+    if let Some(session_token) = config.session_token.as_mut() {
+        f(session_token)?;
+    }
+    if let SseConfig::CustomerKey { key } = &mut config.server_side_encryption {
+        f(key)?;
+    }
+    if let Some(password) = config
+        .network
+        .proxy
+        .as_mut()
+        .and_then(|proxy| proxy.password.as_mut())
+    {
+        f(password)?;
+    }
+    Ok(())
+}
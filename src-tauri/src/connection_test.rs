@@ -0,0 +1,209 @@
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::http_client;
+use crate::operation_meter::OperationMeter;
+use crate::r2_uploader::R2Uploader;
+use crate::types::{BucketListRequest, CheckStatus, ConnectionCheck, ConnectionTestReport, R2Config};
+
+pub const PROBE_PREFIX: &str = ".moli-tutu-probe/";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CHECK_ENDPOINT: &str = "endpoint";
+const CHECK_AUTH: &str = "auth";
+const CHECK_BUCKET: &str = "bucket";
+const CHECK_LIST: &str = "list";
+const CHECK_PUT: &str = "put";
+const CHECK_GET: &str = "get";
+const CHECK_PUBLIC_URL: &str = "public_url";
+const CHECK_DELETE: &str = "delete";
+
+#[derive(Default)]
+struct CheckRecorder {
+    checks: Vec<ConnectionCheck>,
+}
+
+impl CheckRecorder {
+    fn record(&mut self, name: &str, started: Instant, result: Result<String, String>) -> bool {
+        let (status, message) = match result {
+            Ok(message) => (CheckStatus::Passed, message),
+            Err(message) => (CheckStatus::Failed, message),
+        };
+        println!("[ConnectionTest] {}: {:?} - {}", name, status, message);
+        self.checks.push(ConnectionCheck {
+            name: name.to_string(),
+            status,
+            message,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+        status == CheckStatus::Passed
+    }
+
+    fn skip(&mut self, names: &[&str], reason: &str) {
+        for name in names {
+            self.checks.push(ConnectionCheck {
+                name: name.to_string(),
+                status: CheckStatus::Skipped,
+                message: reason.to_string(),
+                duration_ms: 0,
+            });
+        }
+    }
+
+    fn finish(self) -> ConnectionTestReport {
+        ConnectionTestReport {
+            success: self.checks.iter().all(|check| check.status != CheckStatus::Failed),
+            checks: self.checks,
+        }
+    }
+}
+
+/// Runs every check against `config` and reports each one separately, so a
+/// half-working setup shows exactly which permission or setting is missing.
+/// A temporary probe object is written and removed along the way.
+pub async fn run_connection_test(config: R2Config, meter: &OperationMeter) -> ConnectionTestReport {
+    let mut recorder = CheckRecorder::default();
+
+    // Probe through the same proxy and trusted roots the S3 client will use.
+    let mut network = config.network.clone();
+    network.connect_timeout_secs.get_or_insert(HTTP_TIMEOUT.as_secs());
+    network.read_timeout_secs.get_or_insert(HTTP_TIMEOUT.as_secs());
+    let http = match http_client::build_reqwest_client(&network) {
+        Ok(client) => client,
+        Err(e) => {
+            recorder.record(CHECK_ENDPOINT, Instant::now(), Err(format!("Failed to create HTTP client: {}", e)));
+            recorder.skip(
+                &[CHECK_AUTH, CHECK_BUCKET, CHECK_LIST, CHECK_PUT, CHECK_GET, CHECK_PUBLIC_URL, CHECK_DELETE],
+                "HTTP client unavailable",
+            );
+            return recorder.finish();
+        }
+    };
+
+    let started = Instant::now();
+    let reachable = match http.get(&config.endpoint).send().await {
+        Ok(response) => Ok(format!("Endpoint responded with HTTP {}", response.status().as_u16())),
+        Err(e) => Err(format!("Endpoint unreachable: {}", e)),
+    };
+    if !recorder.record(CHECK_ENDPOINT, started, reachable) {
+        recorder.skip(
+            &[CHECK_AUTH, CHECK_BUCKET, CHECK_LIST, CHECK_PUT, CHECK_GET, CHECK_PUBLIC_URL, CHECK_DELETE],
+            "Endpoint unreachable",
+        );
+        return recorder.finish();
+    }
+
+    let started = Instant::now();
+    let uploader = match R2Uploader::new(config, meter).await {
+        Ok(uploader) => uploader,
+        Err(e) => {
+            recorder.record(CHECK_AUTH, started, Err(e.to_string()));
+            recorder.skip(
+                &[CHECK_BUCKET, CHECK_LIST, CHECK_PUT, CHECK_GET, CHECK_PUBLIC_URL, CHECK_DELETE],
+                "Invalid configuration",
+            );
+            return recorder.finish();
+        }
+    };
+
+    let started = Instant::now();
+    match uploader.head_bucket_status().await {
+        Ok(200) => {
+            recorder.record(CHECK_AUTH, started, Ok("Credentials accepted".to_string()));
+            recorder.record(CHECK_BUCKET, started, Ok("Bucket exists".to_string()));
+        }
+        Ok(status @ (401 | 403)) => {
+            // Tokens scoped to objects may still be able to list and write,
+            // so keep going and let the permission checks tell.
+            recorder.record(
+                CHECK_AUTH,
+                started,
+                Err(format!("Access denied (HTTP {}); check the access keys and token permissions", status)),
+            );
+            recorder.skip(&[CHECK_BUCKET], "Bucket access denied");
+        }
+        Ok(404) => {
+            recorder.record(CHECK_AUTH, started, Ok("Credentials accepted".to_string()));
+            recorder.record(CHECK_BUCKET, started, Err("Bucket not found".to_string()));
+            recorder.skip(
+                &[CHECK_LIST, CHECK_PUT, CHECK_GET, CHECK_PUBLIC_URL, CHECK_DELETE],
+                "Bucket not found",
+            );
+            return recorder.finish();
+        }
+        Ok(status) => {
+            recorder.record(CHECK_AUTH, started, Err(format!("Unexpected HTTP {} from HeadBucket", status)));
+            recorder.skip(&[CHECK_BUCKET], "Bucket status unknown");
+        }
+        Err(e) => {
+            recorder.record(CHECK_AUTH, started, Err(e.to_string()));
+            recorder.skip(&[CHECK_BUCKET], "Bucket status unknown");
+        }
+    }
+
+    let started = Instant::now();
+    let list_request = BucketListRequest {
+        max_keys: Some(1),
+        ..Default::default()
+    };
+    let listed = uploader
+        .list_objects(&list_request)
+        .await
+        .map(|_| "Objects can be listed".to_string())
+        .map_err(|e| format!("List failed: {}", e));
+    recorder.record(CHECK_LIST, started, listed);
+
+    let probe_key = format!("{}{}.txt", PROBE_PREFIX, Uuid::new_v4());
+    let probe_body = format!("moli-tutu connection probe {}", Uuid::new_v4()).into_bytes();
+
+    let started = Instant::now();
+    let written = uploader
+        .put_object(&probe_key, probe_body.clone(), "text/plain")
+        .await
+        .map(|_| format!("Probe object written: {}", probe_key))
+        .map_err(|e| format!("Write failed: {}", e));
+    if !recorder.record(CHECK_PUT, started, written) {
+        recorder.skip(&[CHECK_GET, CHECK_PUBLIC_URL, CHECK_DELETE], "Probe object could not be written");
+        return recorder.finish();
+    }
+
+    let started = Instant::now();
+    let read = match uploader.download_object(&probe_key).await {
+        Ok(data) if data == probe_body => Ok("Probe object read back".to_string()),
+        Ok(_) => Err("Probe object was read but its content differs".to_string()),
+        Err(e) => Err(format!("Read failed: {}", e)),
+    };
+    recorder.record(CHECK_GET, started, read);
+
+    if uploader.config().public_url_base.trim().is_empty() {
+        recorder.skip(&[CHECK_PUBLIC_URL], "No public URL base configured");
+    } else if uploader.uses_customer_key() {
+        recorder.skip(&[CHECK_PUBLIC_URL], "Objects encrypted with a customer key can't be fetched publicly");
+    } else {
+        let started = Instant::now();
+        let public_url = uploader.public_url(&probe_key);
+        let fetched = match http.get(&public_url).send().await {
+            Ok(response) if response.status().is_success() => match response.bytes().await {
+                Ok(data) if data.as_ref() == probe_body.as_slice() => {
+                    Ok(format!("Probe object served from {}", public_url))
+                }
+                Ok(_) => Err(format!("{} served different content; is it bound to this bucket?", public_url)),
+                Err(e) => Err(format!("Failed to read {}: {}", public_url, e)),
+            },
+            Ok(response) => Err(format!("{} returned HTTP {}", public_url, response.status().as_u16())),
+            Err(e) => Err(format!("Failed to fetch {}: {}", public_url, e)),
+        };
+        recorder.record(CHECK_PUBLIC_URL, started, fetched);
+    }
+
+    let started = Instant::now();
+    let deleted = uploader
+        .delete_object(&probe_key)
+        .await
+        .map(|_| "Probe object deleted".to_string())
+        .map_err(|e| format!("Delete failed, remove {} manually: {}", probe_key, e));
+    recorder.record(CHECK_DELETE, started, deleted);
+
+    recorder.finish()
+}
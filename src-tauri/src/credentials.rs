@@ -0,0 +1,69 @@
+use anyhow::Result;
+use aws_config::credential_process::CredentialProcessProvider;
+use aws_config::environment::credentials::EnvironmentVariableCredentialsProvider;
+use aws_config::profile::ProfileFileCredentialsProvider;
+use aws_credential_types::provider::SharedCredentialsProvider;
+use aws_credential_types::Credentials;
+
+use crate::types::{CredentialSource, R2Config};
+
+/// Builds the credentials provider for a profile. Providers that hand out
+/// temporary credentials report an expiry, and the SDK's identity cache
+/// refreshes them before they run out.
+pub fn credentials_provider(config: &R2Config) -> SharedCredentialsProvider {
+    match &config.credential_source {
+        CredentialSource::Inline => {
+            println!("[Credentials] Using inline access keys");
+            SharedCredentialsProvider::new(Credentials::new(
+                &config.access_key_id,
+                &config.secret_access_key,
+                config.session_token.clone().filter(|token| !token.is_empty()),
+                None,
+                "cloudflare-r2",
+            ))
+        }
+        CredentialSource::Environment => {
+            println!("[Credentials] Using AWS_* environment variables");
+            SharedCredentialsProvider::new(EnvironmentVariableCredentialsProvider::new())
+        }
+        CredentialSource::AwsProfile { profile_name } => {
+            println!("[Credentials] Using AWS shared config profile: {}", profile_name);
+            SharedCredentialsProvider::new(
+                ProfileFileCredentialsProvider::builder()
+                    .profile_name(profile_name)
+                    .build(),
+            )
+        }
+        CredentialSource::CredentialProcess { command } => {
+            println!("[Credentials] Using credential_process command");
+            SharedCredentialsProvider::new(CredentialProcessProvider::new(command.clone()))
+        }
+    }
+}
+
+pub fn validate_credential_source(config: &R2Config) -> Result<()> {
+    match &config.credential_source {
+        CredentialSource::Inline => {
+            if config.access_key_id.is_empty() {
+                return Err(anyhow::anyhow!("Access Key ID is empty"));
+            }
+
+            if config.secret_access_key.is_empty() {
+                return Err(anyhow::anyhow!("Secret Access Key is empty"));
+            }
+        }
+        CredentialSource::Environment => {}
+        CredentialSource::AwsProfile { profile_name } => {
+            if profile_name.trim().is_empty() {
+                return Err(anyhow::anyhow!("AWS profile name is empty"));
+            }
+        }
+        CredentialSource::CredentialProcess { command } => {
+            if command.trim().is_empty() {
+                return Err(anyhow::anyhow!("credential_process command is empty"));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,46 +1,201 @@
-use crate::types::UploadRecord;
+use crate::migrations;
+use crate::types::{
+    Album, BucketStats, CompressionRecord, HistoryFilter, HistorySort, RetentionPolicy, TagCount, UploadRecord,
+    UploadSource, UploadStatus,
+};
+use crate::vault::MasterKey;
 use anyhow::Result;
-use rusqlite::{params, Connection};
-use std::path::PathBuf;
+use base64::{engine::general_purpose, Engine as _};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, Row};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+const RECORD_COLUMNS: &str = "id, original_filename, file_hash, file_size, url, upload_time, status, expires_at, \
+     content_type, profile, source, bucket, object_key, width, height, client, original_hash, notes, alt_text, caption";
+/// Selected after `RECORD_COLUMNS`: the record's tags, joined by `TAG_SEPARATOR`.
+const TAGS_COLUMN: &str =
+    "(SELECT group_concat(tag, char(31)) FROM upload_tags WHERE upload_tags.upload_id = uploads.id)";
+const TAG_SEPARATOR: char = '\u{1f}';
+/// Longest tag kept, in characters.
+const MAX_TAG_LENGTH: usize = 64;
+const COMPRESSION_COLUMNS: &str = "id, original_name, original_size, compressed_size, compression_ratio, \
+     compressed_at, config, source, content_type, has_original, original_hash, compressed_hash, upload_id";
 
 pub struct Database {
     conn: Connection,
 }
 
 impl Database {
-    pub fn new(db_path: PathBuf) -> Result<Self> {
+    pub fn new(db_path: PathBuf, master_key: Option<&MasterKey>) -> Result<Self> {
+        let conn = Self::open_connection(&db_path, master_key)?;
+        migrations::migrate(&conn, &db_path)?;
+
+        Ok(Self { conn })
+    }
+
+    /// Opens the database file, applying the SQLCipher key when encryption at
+    /// rest is enabled. Fails if the key doesn't match the file.
+    fn open_connection(db_path: &Path, master_key: Option<&MasterKey>) -> Result<Connection> {
         let conn = Connection::open(db_path)?;
-        
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS uploads (
-                id TEXT PRIMARY KEY,
-                original_filename TEXT NOT NULL,
-                file_hash TEXT NOT NULL UNIQUE,
-                file_size INTEGER NOT NULL,
-                url TEXT NOT NULL,
-                upload_time INTEGER NOT NULL
-            )",
-            [],
-        )?;
+        if let Some(master_key) = master_key {
+            conn.execute_batch(&format!("PRAGMA key = \"{}\";", master_key.database_key()))?;
+        }
 
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_file_hash ON uploads(file_hash)",
-            [],
-        )?;
+        conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+            .map_err(|e| anyhow::anyhow!("Failed to open history database (wrong key?): {}", e))?;
+        Ok(conn)
+    }
 
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_upload_time ON uploads(upload_time)",
-            [],
-        )?;
+    /// Rewrites the database file with a different key. `None` on either side
+    /// means plaintext, so this also turns encryption on and off. The
+    /// connection to the file must be closed while this runs.
+    pub fn rekey_file(db_path: &Path, from: Option<&MasterKey>, to: Option<&MasterKey>) -> Result<()> {
+        if !db_path.exists() {
+            return Ok(());
+        }
 
-        Ok(Self { conn })
+        let tmp_path = db_path.with_extension("db.rekey");
+        if tmp_path.exists() {
+            fs::remove_file(&tmp_path)?;
+        }
+
+        let exported = (|| -> Result<()> {
+            let conn = Self::open_connection(db_path, from)?;
+            let user_version = migrations::schema_version(&conn)?;
+            let target_key = to.map(|key| key.database_key()).unwrap_or_default();
+            conn.execute(
+                "ATTACH DATABASE ?1 AS rekeyed KEY ?2",
+                params![tmp_path.to_string_lossy(), target_key],
+            )?;
+            conn.query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(()))?;
+            conn.execute_batch(&format!("PRAGMA rekeyed.user_version = {};", user_version))?;
+            conn.execute_batch("DETACH DATABASE rekeyed;")?;
+            Ok(())
+        })();
+
+        // The original file is only replaced once the copy is complete
+        if let Err(e) = exported.and_then(|()| Ok(fs::rename(&tmp_path, db_path)?)) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        Ok(())
     }
 
+    /// Rekeys the migration backups next to the database the same way.
+    /// Backups that can't be rekeyed are deleted rather than left under the
+    /// old key, or in plaintext.
+    pub fn rekey_backups(db_path: &Path, from: Option<&MasterKey>, to: Option<&MasterKey>) {
+        for backup in migrations::backups(db_path) {
+            if let Err(e) = Self::rekey_file(&backup, from, to) {
+                println!("[Database] Removing backup {:?} that couldn't be re-encrypted: {}", backup, e);
+                if let Err(e) = fs::remove_file(&backup) {
+                    println!("[Database] Failed to remove backup {:?}: {}", backup, e);
+                }
+            }
+        }
+    }
+
+    fn row_to_record(row: &Row) -> rusqlite::Result<UploadRecord> {
+        Ok(UploadRecord {
+            id: row.get(0)?,
+            original_filename: row.get(1)?,
+            file_hash: row.get(2)?,
+            file_size: row.get::<_, i64>(3)? as u64,
+            url: row.get(4)?,
+            upload_time: row.get(5)?,
+            status: UploadStatus::from_db(&row.get::<_, String>(6)?),
+            expires_at: row.get(7)?,
+            content_type: row.get(8)?,
+            profile: row.get(9)?,
+            source: UploadSource::from_db(&row.get::<_, String>(10)?),
+            bucket: row.get(11)?,
+            object_key: row.get(12)?,
+            width: row.get(13)?,
+            height: row.get(14)?,
+            client: row.get(15)?,
+            original_hash: row.get(16)?,
+            notes: row.get(17)?,
+            alt_text: row.get(18)?,
+            caption: row.get(19)?,
+            tags: {
+                let mut tags: Vec<String> = row
+                    .get::<_, Option<String>>(20)?
+                    .map(|tags| tags.split(TAG_SEPARATOR).map(str::to_string).collect())
+                    .unwrap_or_default();
+                tags.sort_by_key(|tag| tag.to_lowercase());
+                tags
+            },
+        })
+    }
+
+    /// Saves `record`, updating the row with the same id. Another row with
+    /// the same hash in the same profile is deleted first.
     pub fn insert_upload_record(&self, record: &UploadRecord) -> Result<()> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO uploads (id, original_filename, file_hash, file_size, url, upload_time)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        self.write_record(record, true)?;
+        Ok(())
+    }
+
+    /// Inserts a record found outside the app, leaving existing rows with the
+    /// same id, or the same hash in the same profile, untouched. Returns
+    /// whether a row was added.
+    pub fn import_upload_record(&self, record: &UploadRecord) -> Result<bool> {
+        Ok(self.write_record(record, false)? > 0)
+    }
+
+    /// Writes `record`, overwriting existing rows when `replace` is set and
+    /// skipping it otherwise. Rows are only ever removed with a plain DELETE
+    /// (never `INSERT OR REPLACE`), so the delete triggers clean up their
+    /// tags, album links, search entries and compression links.
+    fn write_record(&self, record: &UploadRecord, replace: bool) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        if replace {
+            tx.execute(
+                "DELETE FROM uploads WHERE file_hash = ?1 AND profile = ?2 AND id <> ?3",
+                params![record.file_hash, record.profile, record.id],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO contents (hash, file_size, content_type, width, height, first_seen)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(hash) DO UPDATE SET
+                content_type = coalesce(content_type, excluded.content_type),
+                width = coalesce(width, excluded.width),
+                height = coalesce(height, excluded.height)",
+            params![
+                record.file_hash,
+                record.file_size as i64,
+                record.content_type,
+                record.width,
+                record.height,
+                record.upload_time,
+            ],
+        )?;
+
+        let placeholders = (1..=RECORD_COLUMNS.split(',').count())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = if replace {
+            let updates = RECORD_COLUMNS
+                .split(',')
+                .map(str::trim)
+                .filter(|column| *column != "id")
+                .map(|column| format!("{0} = excluded.{0}", column))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "INSERT INTO uploads ({}) VALUES ({}) ON CONFLICT(id) DO UPDATE SET {}",
+                RECORD_COLUMNS, placeholders, updates
+            )
+        } else {
+            format!("INSERT OR IGNORE INTO uploads ({}) VALUES ({})", RECORD_COLUMNS, placeholders)
+        };
+        let written = tx.execute(
+            &sql,
             params![
                 record.id,
                 record.original_filename,
@@ -48,39 +203,40 @@ impl Database {
                 record.file_size as i64,
                 record.url,
                 record.upload_time,
+                record.status.as_str(),
+                record.expires_at,
+                record.content_type,
+                record.profile,
+                record.source.as_str(),
+                record.bucket,
+                record.object_key,
+                record.width,
+                record.height,
+                record.client,
+                record.original_hash,
+                record.notes,
+                record.alt_text,
+                record.caption,
             ],
         )?;
-        
-        // Keep only the latest 100 records
-        self.conn.execute(
-            "DELETE FROM uploads 
-             WHERE id NOT IN (
-                 SELECT id FROM uploads 
-                 ORDER BY upload_time DESC 
-                 LIMIT 100
-             )",
-            [],
-        )?;
-        
-        Ok(())
+        // Tags are only ever added here: re-saving a record keeps the ones
+        // it already has.
+        if written > 0 {
+            Self::insert_tags(&tx, &record.id, &normalize_tags(&record.tags))?;
+        }
+        tx.commit()?;
+        Ok(written)
     }
 
-    pub fn find_by_hash(&self, file_hash: &str) -> Result<Option<UploadRecord>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, original_filename, file_hash, file_size, url, upload_time 
-             FROM uploads WHERE file_hash = ?1",
-        )?;
+    /// The upload of `file_hash` to `profile` (an `R2Config::profile_key`).
+    pub fn find_by_hash(&self, file_hash: &str, profile: &str) -> Result<Option<UploadRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, {} FROM uploads WHERE file_hash = ?1 AND profile = ?2",
+            RECORD_COLUMNS,
+            TAGS_COLUMN
+        ))?;
         
-        let record = stmt.query_row(params![file_hash], |row| {
-            Ok(UploadRecord {
-                id: row.get(0)?,
-                original_filename: row.get(1)?,
-                file_hash: row.get(2)?,
-                file_size: row.get::<_, i64>(3)? as u64,
-                url: row.get(4)?,
-                upload_time: row.get(5)?,
-            })
-        });
+        let record = stmt.query_row(params![file_hash, profile], Self::row_to_record);
         
         match record {
             Ok(record) => Ok(Some(record)),
@@ -89,22 +245,44 @@ impl Database {
         }
     }
 
+    /// The most recent record with exactly this URL.
+    pub fn find_by_url(&self, url: &str) -> Result<Option<UploadRecord>> {
+        let record = self.conn.query_row(
+            &format!(
+                "SELECT {}, {} FROM uploads WHERE url = ?1 ORDER BY upload_time DESC LIMIT 1",
+                RECORD_COLUMNS, TAGS_COLUMN
+            ),
+            params![url],
+            Self::row_to_record,
+        );
+        match record {
+            Ok(record) => Ok(Some(record)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Every upload of the same content, across profiles, newest first.
+    pub fn find_uploads_by_hash(&self, file_hash: &str) -> Result<Vec<UploadRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, {} FROM uploads WHERE file_hash = ?1 ORDER BY upload_time DESC",
+            RECORD_COLUMNS,
+            TAGS_COLUMN
+        ))?;
+        let records = stmt
+            .query_map(params![file_hash], Self::row_to_record)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(records)
+    }
+
     pub fn get_recent_uploads(&self, limit: usize) -> Result<Vec<UploadRecord>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, original_filename, file_hash, file_size, url, upload_time 
-             FROM uploads ORDER BY upload_time DESC LIMIT ?1",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, {} FROM uploads ORDER BY upload_time DESC LIMIT ?1",
+            RECORD_COLUMNS,
+            TAGS_COLUMN
+        ))?;
         
-        let rows = stmt.query_map(params![limit], |row| {
-            Ok(UploadRecord {
-                id: row.get(0)?,
-                original_filename: row.get(1)?,
-                file_hash: row.get(2)?,
-                file_size: row.get::<_, i64>(3)? as u64,
-                url: row.get(4)?,
-                upload_time: row.get(5)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![limit], Self::row_to_record)?;
         
         let mut records = Vec::new();
         for row in rows {
@@ -114,10 +292,927 @@ impl Database {
         Ok(records)
     }
 
+    /// One page of the records matching `filter` in `sort` order, starting
+    /// after `cursor`. Returns the records and the cursor of the next page,
+    /// if any.
+    pub fn search_uploads(
+        &self,
+        filter: &HistoryFilter,
+        sort: HistorySort,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<UploadRecord>, Option<String>)> {
+        let (column, descending) = match sort {
+            HistorySort::Newest => ("upload_time", true),
+            HistorySort::Oldest => ("upload_time", false),
+            HistorySort::Largest => ("file_size", true),
+            HistorySort::Smallest => ("file_size", false),
+            HistorySort::Name => ("original_filename COLLATE NOCASE", false),
+        };
+        let (comparison, direction) = if descending { ("<", "DESC") } else { (">", "ASC") };
+
+        let (mut conditions, mut values) = Self::filter_conditions(filter);
+        // Keyset pagination on (sort column, id), so pages stay stable while
+        // records are added.
+        if let Some(cursor) = cursor {
+            let (value, id) = Self::decode_cursor(cursor)?;
+            conditions.push(format!("({}, id) {} (?, ?)", column, comparison));
+            values.push(value);
+            values.push(Value::Text(id));
+        }
+
+        let sql = format!(
+            "SELECT {}, {} FROM uploads{} ORDER BY {} {}, id {} LIMIT {}",
+            RECORD_COLUMNS,
+            TAGS_COLUMN,
+            Self::where_clause(&conditions),
+            column,
+            direction,
+            direction,
+            limit + 1
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), Self::row_to_record)?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(row?);
+        }
+
+        if records.len() <= limit {
+            return Ok((records, None));
+        }
+        records.truncate(limit);
+        let last = records.last().unwrap();
+        let value = match sort {
+            HistorySort::Newest | HistorySort::Oldest => serde_json::json!(last.upload_time),
+            HistorySort::Largest | HistorySort::Smallest => serde_json::json!(last.file_size),
+            HistorySort::Name => serde_json::json!(last.original_filename),
+        };
+        let cursor = general_purpose::URL_SAFE_NO_PAD.encode(serde_json::json!([value, last.id]).to_string());
+        Ok((records, Some(cursor)))
+    }
+
+    pub fn count_matching_uploads(&self, filter: &HistoryFilter) -> Result<usize> {
+        let (conditions, values) = Self::filter_conditions(filter);
+        let count: i64 = self.conn.query_row(
+            &format!("SELECT count(*) FROM uploads{}", Self::where_clause(&conditions)),
+            rusqlite::params_from_iter(values),
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// SQL conditions (with `?` placeholders) and their values for `filter`.
+    fn filter_conditions(filter: &HistoryFilter) -> (Vec<String>, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        if let Some(query) = filter.text.as_deref().and_then(Self::fts_query) {
+            conditions.push("id IN (SELECT id FROM uploads_fts WHERE uploads_fts MATCH ?)".to_string());
+            values.push(Value::Text(query));
+        }
+        if let Some(after) = filter.uploaded_after {
+            conditions.push("upload_time >= ?".to_string());
+            values.push(Value::Integer(after));
+        }
+        if let Some(before) = filter.uploaded_before {
+            conditions.push("upload_time < ?".to_string());
+            values.push(Value::Integer(before));
+        }
+        if let Some(min_size) = filter.min_size {
+            conditions.push("file_size >= ?".to_string());
+            values.push(Value::Integer(min_size.min(i64::MAX as u64) as i64));
+        }
+        if let Some(max_size) = filter.max_size {
+            conditions.push("file_size <= ?".to_string());
+            values.push(Value::Integer(max_size.min(i64::MAX as u64) as i64));
+        }
+        if let Some(content_type) = filter.content_type.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            if content_type.ends_with('/') {
+                conditions.push("substr(content_type, 1, ?) = ?".to_string());
+                values.push(Value::Integer(content_type.len() as i64));
+            } else {
+                conditions.push("content_type = ?".to_string());
+            }
+            values.push(Value::Text(content_type.to_string()));
+        }
+        if let Some(profile) = &filter.profile {
+            conditions.push("profile = ?".to_string());
+            values.push(Value::Text(profile.clone()));
+        }
+        if let Some(source) = filter.source {
+            conditions.push("source = ?".to_string());
+            values.push(Value::Text(source.as_str().to_string()));
+        }
+        if let Some(status) = filter.status {
+            conditions.push("status = ?".to_string());
+            values.push(Value::Text(status.as_str().to_string()));
+        }
+        for tag in normalize_tags(&filter.tags) {
+            conditions.push("id IN (SELECT upload_id FROM upload_tags WHERE tag = ?)".to_string());
+            values.push(Value::Text(tag));
+        }
+        if let Some(album) = &filter.album {
+            conditions.push("id IN (SELECT upload_id FROM album_uploads WHERE album_id = ?)".to_string());
+            values.push(Value::Text(album.clone()));
+        }
+
+        (conditions, values)
+    }
+
+    fn where_clause(conditions: &[String]) -> String {
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        }
+    }
+
+    /// Turns free text into an FTS5 query matching every word as a prefix.
+    /// Words are quoted so FTS5 operators in the input are taken literally.
+    fn fts_query(text: &str) -> Option<String> {
+        let terms: Vec<String> = text
+            .split_whitespace()
+            .map(|word| word.replace('"', ""))
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .map(|word| format!("\"{}\"*", word))
+            .collect();
+
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" "))
+        }
+    }
+
+    fn decode_cursor(cursor: &str) -> Result<(Value, String)> {
+        let invalid = || anyhow::anyhow!("Invalid history cursor");
+        let json = general_purpose::URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let (value, id): (serde_json::Value, String) = serde_json::from_slice(&json).map_err(|_| invalid())?;
+        let value = match value {
+            serde_json::Value::Number(number) => Value::Integer(number.as_i64().ok_or_else(invalid)?),
+            serde_json::Value::String(text) => Value::Text(text),
+            _ => return Err(invalid()),
+        };
+        Ok((value, id))
+    }
+
+    /// Deletes the records `policy` doesn't keep and returns how many.
+    /// Records whose object is still due to expire are kept, or the object
+    /// would never be deleted.
+    pub fn prune_history(&self, policy: &RetentionPolicy, now: i64) -> Result<usize> {
+        const PENDING_EXPIRY: &str = "(expires_at IS NOT NULL AND status = 'active')";
+        let deleted = match policy {
+            RetentionPolicy::Unlimited => 0,
+            RetentionPolicy::Count { max_records } => self.conn.execute(
+                &format!(
+                    "DELETE FROM uploads
+                     WHERE NOT {}
+                       AND id NOT IN (SELECT id FROM uploads ORDER BY upload_time DESC, id DESC LIMIT ?1)",
+                    PENDING_EXPIRY
+                ),
+                params![*max_records as i64],
+            )?,
+            RetentionPolicy::Age { max_age_days } => self.conn.execute(
+                &format!("DELETE FROM uploads WHERE NOT {} AND upload_time < ?1", PENDING_EXPIRY),
+                params![now.saturating_sub((*max_age_days as i64).saturating_mul(24 * 60 * 60))],
+            )?,
+        };
+        Ok(deleted)
+    }
+
+    pub fn get_all_uploads(&self) -> Result<Vec<UploadRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, {} FROM uploads ORDER BY upload_time DESC",
+            RECORD_COLUMNS,
+            TAGS_COLUMN
+        ))?;
+
+        let rows = stmt.query_map([], Self::row_to_record)?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(row?);
+        }
+
+        Ok(records)
+    }
+
+    /// Records uploaded with `profile`, along with those saved before
+    /// profiles were stored.
+    pub fn get_profile_uploads(&self, profile: &str) -> Result<Vec<UploadRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, {} FROM uploads WHERE profile = ?1 OR profile IS NULL ORDER BY upload_time DESC",
+            RECORD_COLUMNS,
+            TAGS_COLUMN
+        ))?;
+
+        let rows = stmt.query_map(params![profile], Self::row_to_record)?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(row?);
+        }
+
+        Ok(records)
+    }
+
+    pub fn set_upload_status(&self, id: &str, status: UploadStatus) -> Result<()> {
+        self.conn.execute(
+            "UPDATE uploads SET status = ?1 WHERE id = ?2",
+            params![status.as_str(), id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_upload_expiry(&self, id: &str, expires_at: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE uploads SET expires_at = ?1 WHERE id = ?2",
+            params![expires_at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Records of `profile` past their expiry time that haven't been marked
+    /// expired yet, along with those saved before profiles were stored.
+    pub fn get_due_expirations(&self, now: i64, profile: &str) -> Result<Vec<UploadRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, {} FROM uploads
+             WHERE expires_at IS NOT NULL AND expires_at <= ?1 AND status != 'expired'
+               AND (profile = ?2 OR profile IS NULL)
+             ORDER BY expires_at",
+            RECORD_COLUMNS,
+            TAGS_COLUMN
+        ))?;
+
+        let rows = stmt.query_map(params![now, profile], Self::row_to_record)?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(row?);
+        }
+
+        Ok(records)
+    }
+
+    pub fn save_bucket_stats(&self, stats: &BucketStats) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO bucket_stats (profile, stats, refreshed_at) VALUES (?1, ?2, ?3)",
+            params![stats.profile, serde_json::to_string(stats)?, stats.refreshed_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_bucket_stats(&self, profile: &str) -> Result<Option<BucketStats>> {
+        let stats = self.conn.query_row(
+            "SELECT stats FROM bucket_stats WHERE profile = ?1",
+            params![profile],
+            |row| row.get::<_, String>(0),
+        );
+
+        match stats {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Adds `(profile, month, operation) -> count` to the stored totals.
+    pub fn add_operation_counts(&self, counts: &HashMap<(String, String, String), u64>) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO operation_counts (profile, month, operation, count) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(profile, month, operation) DO UPDATE SET count = count + excluded.count",
+            )?;
+            for ((profile, month, operation), count) in counts {
+                stmt.execute(params![profile, month, operation, *count as i64])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_operation_counts(&self, profile: &str, month: &str) -> Result<Vec<(String, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT operation, count FROM operation_counts
+             WHERE profile = ?1 AND month = ?2
+             ORDER BY count DESC",
+        )?;
+
+        let counts = stmt
+            .query_map(params![profile, month], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(counts)
+    }
+
+    /// Replaces the tags of an upload and returns them as saved.
+    pub fn set_upload_tags(&self, id: &str, tags: &[String]) -> Result<Vec<String>> {
+        self.ensure_upload_exists(id)?;
+        let tags = normalize_tags(tags);
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM upload_tags WHERE upload_id = ?1", params![id])?;
+        Self::insert_tags(&tx, id, &tags)?;
+        tx.commit()?;
+        Ok(tags)
+    }
+
+    /// Adds tags to an upload, keeping the ones it has.
+    pub fn add_upload_tags(&self, id: &str, tags: &[String]) -> Result<()> {
+        self.ensure_upload_exists(id)?;
+        Self::insert_tags(&self.conn, id, &normalize_tags(tags))
+    }
+
+    fn insert_tags(conn: &Connection, id: &str, tags: &[String]) -> Result<()> {
+        let mut stmt = conn.prepare("INSERT OR IGNORE INTO upload_tags (upload_id, tag) VALUES (?1, ?2)")?;
+        for tag in tags {
+            stmt.execute(params![id, tag])?;
+        }
+        Ok(())
+    }
+
+    /// Every tag in use and how many records carry it, by name.
+    pub fn list_tags(&self) -> Result<Vec<TagCount>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT min(tag), count(*) FROM upload_tags GROUP BY tag ORDER BY tag",
+        )?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(TagCount {
+                    name: row.get(0)?,
+                    count: row.get::<_, i64>(1)? as usize,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tags)
+    }
+
+    /// Renames a tag on every record, merging it into `to` where a record
+    /// already has both. Returns the number of records affected.
+    pub fn rename_tag(&self, from: &str, to: &str) -> Result<usize> {
+        let to = normalize_tags([to])
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Tag name cannot be empty"))?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO upload_tags (upload_id, tag)
+             SELECT upload_id, ?2 FROM upload_tags WHERE tag = ?1",
+            params![from, to],
+        )?;
+        // Renaming only the case of a tag leaves nothing to insert above.
+        let renamed = if from.eq_ignore_ascii_case(&to) {
+            tx.execute(
+                "UPDATE upload_tags SET tag = ?2 WHERE tag = ?1",
+                params![from, to],
+            )?
+        } else {
+            tx.execute("DELETE FROM upload_tags WHERE tag = ?1", params![from])?
+        };
+        tx.commit()?;
+        Ok(renamed)
+    }
+
+    /// Removes a tag from every record; returns the number of records affected.
+    pub fn delete_tag(&self, tag: &str) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM upload_tags WHERE tag = ?1", params![tag])?)
+    }
+
+    pub fn set_upload_notes(&self, id: &str, notes: Option<&str>) -> Result<()> {
+        self.set_text_column(id, "notes", notes)
+    }
+
+    pub fn set_upload_alt_text(&self, id: &str, alt_text: Option<&str>) -> Result<()> {
+        self.set_text_column(id, "alt_text", alt_text)
+    }
+
+    pub fn set_upload_caption(&self, id: &str, caption: Option<&str>) -> Result<()> {
+        self.set_text_column(id, "caption", caption)
+    }
+
+    /// Sets a free-text column of a record; blank text clears it.
+    fn set_text_column(&self, id: &str, column: &str, value: Option<&str>) -> Result<()> {
+        let value = value.map(str::trim).filter(|value| !value.is_empty());
+        let updated = self.conn.execute(
+            &format!("UPDATE uploads SET {} = ?1 WHERE id = ?2", column),
+            params![value, id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow::anyhow!("Upload record not found"));
+        }
+        Ok(())
+    }
+
+    pub fn get_upload(&self, id: &str) -> Result<Option<UploadRecord>> {
+        let record = self.conn.query_row(
+            &format!("SELECT {}, {} FROM uploads WHERE id = ?1", RECORD_COLUMNS, TAGS_COLUMN),
+            params![id],
+            Self::row_to_record,
+        );
+        match record {
+            Ok(record) => Ok(Some(record)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn ensure_upload_exists(&self, id: &str) -> Result<()> {
+        let exists: bool = self.conn.query_row(
+            "SELECT count(*) > 0 FROM uploads WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(anyhow::anyhow!("Upload record not found"));
+        }
+        Ok(())
+    }
+
+    pub fn list_albums(&self) -> Result<Vec<Album>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT albums.id, albums.name, albums.description, albums.created_at, count(album_uploads.upload_id)
+             FROM albums LEFT JOIN album_uploads ON album_uploads.album_id = albums.id
+             GROUP BY albums.id
+             ORDER BY albums.name",
+        )?;
+        let albums = stmt
+            .query_map([], Self::row_to_album)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(albums)
+    }
+
+    fn get_album(&self, id: &str) -> Result<Album> {
+        let album = self.conn.query_row(
+            "SELECT albums.id, albums.name, albums.description, albums.created_at, count(album_uploads.upload_id)
+             FROM albums LEFT JOIN album_uploads ON album_uploads.album_id = albums.id
+             WHERE albums.id = ?1
+             GROUP BY albums.id",
+            params![id],
+            Self::row_to_album,
+        );
+        match album {
+            Ok(album) => Ok(album),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(anyhow::anyhow!("Album not found")),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn row_to_album(row: &Row) -> rusqlite::Result<Album> {
+        Ok(Album {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            created_at: row.get(3)?,
+            upload_count: row.get::<_, i64>(4)? as usize,
+        })
+    }
+
+    pub fn create_album(&self, name: &str, description: Option<&str>) -> Result<Album> {
+        let id = Uuid::new_v4().to_string();
+        self.conn
+            .execute(
+                "INSERT INTO albums (id, name, description, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![id, Self::album_name(name)?, Self::album_description(description), Self::get_current_timestamp()],
+            )
+            .map_err(|e| Self::album_error(e, name))?;
+        self.get_album(&id)
+    }
+
+    pub fn update_album(&self, id: &str, name: &str, description: Option<&str>) -> Result<Album> {
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE albums SET name = ?1, description = ?2 WHERE id = ?3",
+                params![Self::album_name(name)?, Self::album_description(description), id],
+            )
+            .map_err(|e| Self::album_error(e, name))?;
+        if updated == 0 {
+            return Err(anyhow::anyhow!("Album not found"));
+        }
+        self.get_album(id)
+    }
+
+    /// Deletes the album only; its uploads stay in the history.
+    pub fn delete_album(&self, id: &str) -> Result<()> {
+        if self.conn.execute("DELETE FROM albums WHERE id = ?1", params![id])? == 0 {
+            return Err(anyhow::anyhow!("Album not found"));
+        }
+        Ok(())
+    }
+
+    /// Adds uploads to an album, skipping ids that aren't in the history or
+    /// already in the album. Returns the number added.
+    pub fn add_to_album(&self, album_id: &str, upload_ids: &[String]) -> Result<usize> {
+        self.get_album(album_id)?;
+        let tx = self.conn.unchecked_transaction()?;
+        let mut added = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO album_uploads (album_id, upload_id, added_at)
+                 SELECT ?1, id, ?3 FROM uploads WHERE id = ?2",
+            )?;
+            let now = Self::get_current_timestamp();
+            for upload_id in upload_ids {
+                added += stmt.execute(params![album_id, upload_id, now])?;
+            }
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    /// Returns the number of uploads removed from the album.
+    pub fn remove_from_album(&self, album_id: &str, upload_ids: &[String]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut removed = 0;
+        {
+            let mut stmt = tx.prepare("DELETE FROM album_uploads WHERE album_id = ?1 AND upload_id = ?2")?;
+            for upload_id in upload_ids {
+                removed += stmt.execute(params![album_id, upload_id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    fn album_name(name: &str) -> Result<&str> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Album name cannot be empty"));
+        }
+        Ok(name)
+    }
+
+    fn album_description(description: Option<&str>) -> Option<&str> {
+        description.map(str::trim).filter(|description| !description.is_empty())
+    }
+
+    fn album_error(error: rusqlite::Error, name: &str) -> anyhow::Error {
+        match error {
+            rusqlite::Error::SqliteFailure(e, _) if e.code == rusqlite::ErrorCode::ConstraintViolation => {
+                anyhow::anyhow!("An album named \"{}\" already exists", name.trim())
+            }
+            e => e.into(),
+        }
+    }
+
+    pub fn insert_compression(&self, record: &CompressionRecord) -> Result<()> {
+        self.conn.execute(
+            &format!(
+                "INSERT INTO compressions ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                COMPRESSION_COLUMNS
+            ),
+            params![
+                record.id,
+                record.original_name,
+                record.original_size as i64,
+                record.compressed_size as i64,
+                record.compression_ratio,
+                record.compressed_at,
+                record.config.to_string(),
+                record.source.as_str(),
+                record.content_type,
+                record.has_original,
+                record.original_hash,
+                record.compressed_hash,
+                record.upload_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every compression, newest first.
+    pub fn list_compressions(&self) -> Result<Vec<CompressionRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM compressions ORDER BY compressed_at DESC, rowid DESC",
+            COMPRESSION_COLUMNS
+        ))?;
+        let records = stmt
+            .query_map([], Self::row_to_compression)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(records)
+    }
+
+    pub fn get_compression(&self, id: &str) -> Result<CompressionRecord> {
+        match self.conn.query_row(
+            &format!("SELECT {} FROM compressions WHERE id = ?1", COMPRESSION_COLUMNS),
+            params![id],
+            Self::row_to_compression,
+        ) {
+            Ok(record) => Ok(record),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(anyhow::anyhow!("Compression record not found")),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn delete_compression(&self, id: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM compressions WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Links compressions whose output is the file with `file_hash` to its
+    /// upload, unless they were already uploaded.
+    pub fn link_compressions(&self, file_hash: &str, upload_id: &str) -> Result<usize> {
+        Ok(self.conn.execute(
+            "UPDATE compressions SET upload_id = ?2 WHERE compressed_hash = ?1 AND upload_id IS NULL",
+            params![file_hash, upload_id],
+        )?)
+    }
+
+    fn row_to_compression(row: &Row) -> rusqlite::Result<CompressionRecord> {
+        Ok(CompressionRecord {
+            id: row.get(0)?,
+            original_name: row.get(1)?,
+            original_size: row.get::<_, i64>(2)? as u64,
+            compressed_size: row.get::<_, i64>(3)? as u64,
+            compression_ratio: row.get(4)?,
+            compressed_at: row.get(5)?,
+            config: serde_json::from_str(&row.get::<_, String>(6)?).unwrap_or_default(),
+            source: UploadSource::from_db(&row.get::<_, String>(7)?),
+            content_type: row.get(8)?,
+            has_original: row.get(9)?,
+            original_hash: row.get(10)?,
+            compressed_hash: row.get(11)?,
+            upload_id: row.get(12)?,
+        })
+    }
+
     pub fn get_current_timestamp() -> i64 {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64
     }
-}
\ No newline at end of file
+}
+
+/// Trims tags, drops empty ones and case-insensitive duplicates, and cuts
+/// them to `MAX_TAG_LENGTH` characters.
+pub fn normalize_tags<S: AsRef<str>>(tags: impl IntoIterator<Item = S>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag: String = tag.as_ref().trim().chars().take(MAX_TAG_LENGTH).collect();
+        let tag = tag.trim_end().to_string();
+        if !tag.is_empty() && !normalized.iter().any(|existing| existing.to_lowercase() == tag.to_lowercase()) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> Database {
+        Database::new(PathBuf::from(":memory:"), None).unwrap()
+    }
+
+    fn record(id: &str, file_hash: &str, upload_time: i64) -> UploadRecord {
+        UploadRecord {
+            id: id.to_string(),
+            original_filename: format!("{}.png", id),
+            file_hash: file_hash.to_string(),
+            file_size: 100,
+            url: format!("https://img.example.com/{}.png", id),
+            upload_time,
+            status: UploadStatus::Active,
+            expires_at: None,
+            content_type: Some("image/png".to_string()),
+            profile: Some("main".to_string()),
+            source: UploadSource::App,
+            bucket: Some("bucket".to_string()),
+            object_key: Some(format!("{}.png", id)),
+            width: None,
+            height: None,
+            client: None,
+            original_hash: None,
+            tags: Vec::new(),
+            notes: None,
+            alt_text: None,
+            caption: None,
+        }
+    }
+
+    fn count(db: &Database, sql: &str) -> i64 {
+        db.conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    fn text_search(db: &Database, text: &str) -> Vec<String> {
+        let filter = HistoryFilter {
+            text: Some(text.to_string()),
+            ..Default::default()
+        };
+        let (records, _) = db.search_uploads(&filter, HistorySort::Newest, None, 50).unwrap();
+        records.into_iter().map(|record| record.id).collect()
+    }
+
+    #[test]
+    fn replacing_a_record_with_the_same_hash_releases_its_links() {
+        let db = open();
+        let mut first = record("first", "hash", 1);
+        first.tags = vec!["cat".to_string()];
+        db.insert_upload_record(&first).unwrap();
+        let album = db.create_album("Pets", None).unwrap();
+        db.add_to_album(&album.id, &["first".to_string()]).unwrap();
+
+        db.insert_upload_record(&record("second", "hash", 2)).unwrap();
+
+        assert!(db.get_upload("first").unwrap().is_none());
+        assert_eq!(count(&db, "SELECT count(*) FROM upload_tags WHERE upload_id = 'first'"), 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM album_uploads"), 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads_fts WHERE id = 'first'"), 0);
+        assert_eq!(count(&db, "SELECT count(*) FROM contents"), 1);
+        assert!(text_search(&db, "cat").is_empty());
+    }
+
+    #[test]
+    fn resaving_a_record_keeps_its_tags_and_updates_search() {
+        let db = open();
+        let mut saved = record("upload", "hash", 1);
+        saved.tags = vec!["cat".to_string()];
+        db.insert_upload_record(&saved).unwrap();
+
+        saved.original_filename = "renamed.png".to_string();
+        saved.tags = Vec::new();
+        db.insert_upload_record(&saved).unwrap();
+
+        assert_eq!(count(&db, "SELECT count(*) FROM uploads"), 1);
+        assert_eq!(db.get_upload("upload").unwrap().unwrap().tags, vec!["cat"]);
+        assert_eq!(text_search(&db, "renamed"), vec!["upload"]);
+        assert_eq!(text_search(&db, "cat"), vec!["upload"]);
+    }
+
+    #[test]
+    fn backups_follow_the_database_key() {
+        let dir = std::env::temp_dir().join(format!("moli-database-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("uploads.db");
+        let backup = dir.join("uploads.db.v3.bak");
+        drop(Database::new(db_path.clone(), None).unwrap());
+        fs::copy(&db_path, &backup).unwrap();
+        fs::write(dir.join("uploads.db.v2.bak"), b"truncated").unwrap();
+        let master_key = crate::vault::Vault::new(dir.clone()).create("master passphrase").unwrap();
+
+        Database::rekey_backups(&db_path, None, Some(&master_key));
+
+        assert!(Database::open_connection(&backup, None).is_err());
+        assert!(Database::open_connection(&backup, Some(&master_key)).is_ok());
+        assert_eq!(migrations::backups(&db_path), vec![backup]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn importing_never_overwrites_existing_records() {
+        let db = open();
+        db.insert_upload_record(&record("upload", "hash", 1)).unwrap();
+
+        let mut imported = record("upload", "other", 2);
+        imported.original_filename = "imported.png".to_string();
+        assert!(!db.import_upload_record(&imported).unwrap());
+        assert!(!db.import_upload_record(&record("copy", "hash", 3)).unwrap());
+        assert_eq!(db.get_upload("upload").unwrap().unwrap().original_filename, "upload.png");
+        assert!(db.import_upload_record(&record("new", "new", 4)).unwrap());
+    }
+
+    /// Every page of `filter` in `sort` order, `limit` records at a time.
+    fn all_pages(db: &Database, filter: &HistoryFilter, sort: HistorySort, limit: usize) -> Vec<Vec<String>> {
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let (records, next) = db.search_uploads(filter, sort, cursor.as_deref(), limit).unwrap();
+            pages.push(records.into_iter().map(|record| record.id).collect());
+            match next {
+                Some(next) => cursor = Some(next),
+                None => return pages,
+            }
+        }
+    }
+
+    #[test]
+    fn pages_through_records_with_equal_sort_values() {
+        let db = open();
+        for (i, id) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            db.insert_upload_record(&record(id, &format!("hash-{}", i), 1)).unwrap();
+        }
+
+        let filter = HistoryFilter::default();
+        assert_eq!(
+            all_pages(&db, &filter, HistorySort::Newest, 2),
+            vec![vec!["e", "d"], vec!["c", "b"], vec!["a"]]
+        );
+        assert_eq!(
+            all_pages(&db, &filter, HistorySort::Oldest, 2),
+            vec![vec!["a", "b"], vec!["c", "d"], vec!["e"]]
+        );
+        // Sizes are all equal too.
+        assert_eq!(all_pages(&db, &filter, HistorySort::Largest, 5), vec![vec!["e", "d", "c", "b", "a"]]);
+    }
+
+    #[test]
+    fn last_full_page_has_no_cursor() {
+        let db = open();
+        for (i, id) in ["a", "b", "c", "d"].iter().enumerate() {
+            db.insert_upload_record(&record(id, &format!("hash-{}", i), i as i64)).unwrap();
+        }
+
+        let pages = all_pages(&db, &HistoryFilter::default(), HistorySort::Newest, 2);
+        assert_eq!(pages, vec![vec!["d", "c"], vec!["b", "a"]]);
+        assert_eq!(all_pages(&db, &HistoryFilter::default(), HistorySort::Newest, 4).len(), 1);
+        assert!(db.search_uploads(&HistoryFilter::default(), HistorySort::Newest, Some("not a cursor"), 2).is_err());
+    }
+
+    #[test]
+    fn text_search_combines_with_filters() {
+        let db = open();
+        let mut beach = record("beach", "hash-1", 10);
+        beach.original_filename = "Beach sunset.png".to_string();
+        beach.tags = vec!["holiday".to_string()];
+        db.insert_upload_record(&beach).unwrap();
+
+        let mut party = record("party", "hash-2", 20);
+        party.original_filename = "beach-party.jpg".to_string();
+        party.content_type = Some("image/jpeg".to_string());
+        party.file_size = 5000;
+        party.source = UploadSource::Proxy;
+        db.insert_upload_record(&party).unwrap();
+
+        let mut notes = record("notes", "hash-3", 30);
+        notes.notes = Some("taken at the beach".to_string());
+        db.insert_upload_record(&notes).unwrap();
+
+        let matching = |filter: HistoryFilter| {
+            let (records, _) = db.search_uploads(&filter, HistorySort::Oldest, None, 50).unwrap();
+            assert_eq!(db.count_matching_uploads(&filter).unwrap(), records.len());
+            records.into_iter().map(|record| record.id).collect::<Vec<_>>()
+        };
+        let text = |text: &str| HistoryFilter {
+            text: Some(text.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(matching(text("beach")), vec!["beach", "party", "notes"]);
+        assert_eq!(matching(text("sun")), vec!["beach"]);
+        assert_eq!(matching(text("beach holiday")), vec!["beach"]);
+        assert_eq!(
+            matching(HistoryFilter {
+                content_type: Some("image/".to_string()),
+                min_size: Some(1000),
+                ..text("beach")
+            }),
+            vec!["party"]
+        );
+        assert_eq!(
+            matching(HistoryFilter {
+                source: Some(UploadSource::App),
+                uploaded_before: Some(30),
+                ..text("beach")
+            }),
+            vec!["beach"]
+        );
+        assert_eq!(
+            matching(HistoryFilter {
+                tags: vec!["HOLIDAY".to_string()],
+                ..text("party")
+            }),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(
+            normalize_tags(["  Cats ", "cats", "", "dogs", "CATS"]),
+            vec!["Cats".to_string(), "dogs".to_string()]
+        );
+        let long = "x".repeat(MAX_TAG_LENGTH + 10);
+        assert_eq!(normalize_tags([long.as_str()])[0].chars().count(), MAX_TAG_LENGTH);
+        let padded = format!("{} y", "x".repeat(MAX_TAG_LENGTH - 1));
+        assert_eq!(normalize_tags([padded.as_str()]), vec!["x".repeat(MAX_TAG_LENGTH - 1)]);
+    }
+
+    #[test]
+    fn renames_and_merges_tags() {
+        let db = open();
+        db.insert_upload_record(&record("a", "hash-a", 1)).unwrap();
+        db.insert_upload_record(&record("b", "hash-b", 2)).unwrap();
+        db.set_upload_tags("a", &["cat".to_string(), "kitty".to_string()]).unwrap();
+        db.set_upload_tags("b", &["Kitty".to_string()]).unwrap();
+
+        assert_eq!(db.rename_tag("kitty", "cat").unwrap(), 2);
+        assert_eq!(db.get_upload("a").unwrap().unwrap().tags, vec!["cat"]);
+        assert_eq!(db.get_upload("b").unwrap().unwrap().tags, vec!["cat"]);
+
+        // Only the case changes.
+        assert_eq!(db.rename_tag("cat", "Cat").unwrap(), 2);
+        let tags = db.list_tags().unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!((tags[0].name.as_str(), tags[0].count), ("Cat", 2));
+        assert_eq!(text_search(&db, "cat").len(), 2);
+
+        assert!(db.rename_tag("Cat", "   ").is_err());
+        assert_eq!(db.delete_tag("cat").unwrap(), 2);
+        assert!(db.list_tags().unwrap().is_empty());
+    }
+}
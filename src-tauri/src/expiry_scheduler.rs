@@ -0,0 +1,83 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::database::Database;
+use crate::r2_uploader::R2Uploader;
+use crate::types::{ExpiryReport, UploadStatus, UploaderStatus};
+use crate::uploader_manager::UploaderManager;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Periodically deletes expired uploads for as long as the app runs. Rounds
+/// are skipped while the app is locked or the uploader isn't ready.
+pub fn spawn(uploader: UploaderManager, db: Arc<Mutex<Option<Database>>>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if uploader.status() == UploaderStatus::Ready {
+                if let Ok(uploader) = uploader.uploader().await {
+                    match purge_expired(&uploader, &db).await {
+                        Ok(report) if report.expired + report.already_missing > 0 || !report.errors.is_empty() => {
+                            println!(
+                                "[ExpiryScheduler] Expired {} uploads ({} already missing), {} errors",
+                                report.expired,
+                                report.already_missing,
+                                report.errors.len()
+                            );
+                        }
+                        Ok(_) => {}
+                        Err(e) => println!("[ExpiryScheduler] Sweep failed: {}", e),
+                    }
+                }
+            }
+
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    });
+}
+
+/// Deletes the objects of every upload past its expiry time and marks the
+/// records expired. Only uploads of the active profile are touched; the
+/// others are left until it's active again.
+pub async fn purge_expired(uploader: &R2Uploader, db: &Arc<Mutex<Option<Database>>>) -> Result<ExpiryReport> {
+    let profile = uploader.config().profile_key();
+    let due = {
+        let db_guard = db.lock().unwrap();
+        match db_guard.as_ref() {
+            Some(db) => db.get_due_expirations(Database::get_current_timestamp(), &profile)?,
+            None => return Err(anyhow::anyhow!("Database not available")),
+        }
+    };
+
+    let mut report = ExpiryReport::default();
+    for record in due {
+        if record.status != UploadStatus::Missing {
+            let key = match uploader.record_key(&record) {
+                Some(key) => key,
+                None => {
+                    report
+                        .errors
+                        .push(format!("{}: not stored in the current bucket", record.url));
+                    continue;
+                }
+            };
+
+            // DeleteObject succeeds for keys that are already gone.
+            if let Err(e) = uploader.delete_object(&key).await {
+                report.errors.push(format!("{}: {}", key, e));
+                continue;
+            }
+            report.expired += 1;
+        } else {
+            report.already_missing += 1;
+        }
+
+        let db_guard = db.lock().unwrap();
+        if let Some(ref db) = *db_guard {
+            db.set_upload_status(&record.id, UploadStatus::Expired)?;
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::r2_uploader::R2Uploader;
+use crate::types::{ExternalHistoryApp, UploadRecord, UploadStatus};
+
+/// Local files larger than this are not hashed; no image host takes them.
+const MAX_LOCAL_FILE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Reads the gallery of another image uploader into records. PicGo and
+/// PicList keep theirs in `data.json` (or `picgo.db`) under `uploaded`; uPic
+/// exports a list of entries or plain URLs. Entries without a URL are
+/// reported in the returned errors, and repeated URLs are dropped.
+///
+/// Entries whose local file still exists get its content hash; the others
+/// get a `url:` hash of their link, which never matches a content hash.
+pub fn parse(app: ExternalHistoryApp, content: &str) -> Result<(Vec<UploadRecord>, Vec<String>)> {
+    let value: Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| anyhow::anyhow!("Not a {} history file: {}", app.name(), e))?;
+    let entries = match &value {
+        Value::Array(entries) => entries,
+        Value::Object(object) => match object.get("uploaded").or_else(|| object.get("history")) {
+            Some(Value::Array(entries)) => entries,
+            _ => return Err(anyhow::anyhow!("No upload history found in this {} file", app.name())),
+        },
+        _ => return Err(anyhow::anyhow!("No upload history found in this {} file", app.name())),
+    };
+
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen_urls = HashSet::new();
+    for (i, entry) in entries.iter().enumerate() {
+        match entry_record(app, entry) {
+            Some(record) => {
+                if seen_urls.insert(record.url.clone()) {
+                    records.push(record);
+                }
+            }
+            None => errors.push(format!("Entry {}: no URL", i + 1)),
+        }
+    }
+    println!(
+        "[ExternalHistory] Read {} entries from {} ({} without a URL)",
+        records.len(),
+        app.name(),
+        errors.len()
+    );
+    Ok((records, errors))
+}
+
+fn entry_record(app: ExternalHistoryApp, entry: &Value) -> Option<UploadRecord> {
+    let text = |keys: &[&str]| {
+        keys.iter()
+            .filter_map(|key| entry.get(*key).and_then(Value::as_str))
+            .map(str::trim)
+            .find(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let number = |keys: &[&str]| keys.iter().find_map(|key| entry.get(*key).and_then(Value::as_u64));
+
+    let url = match entry {
+        Value::String(url) => Some(url.trim().to_string()).filter(|url| !url.is_empty()),
+        _ => text(&["imgUrl", "url"]),
+    }?;
+    let url_name = url.split(['?', '#']).next().unwrap_or(&url).rsplit('/').next().unwrap_or(&url);
+    let original_filename = text(&["fileName", "filename", "name"]).unwrap_or_else(|| url_name.to_string());
+
+    // Only some versions keep the path of the uploaded file.
+    let local_file = text(&["path", "filePath", "localPath"])
+        .and_then(|path| read_local_file(Path::new(&path), MAX_LOCAL_FILE_BYTES));
+    let (file_hash, file_size) = match &local_file {
+        Some(data) => (R2Uploader::calculate_hash(data), data.len() as u64),
+        None => (
+            format!("url:{}", R2Uploader::calculate_hash(url.as_bytes())),
+            number(&["size", "fileSize"]).unwrap_or(0),
+        ),
+    };
+
+    // PicGo stores milliseconds; accept seconds too.
+    let upload_time = number(&["createdAt", "created_at", "date", "time"])
+        .map(|time| if time > 100_000_000_000 { time / 1000 } else { time } as i64)
+        .unwrap_or_else(Database::get_current_timestamp);
+
+    // PicGo's `type` is the image host the entry was uploaded to.
+    let client = match text(&["type"]) {
+        Some(host) => format!("{} ({})", app.name(), host),
+        None => app.name().to_string(),
+    };
+
+    let content_type = mime_guess::from_path(&original_filename)
+        .first()
+        .or_else(|| mime_guess::from_path(url_name).first())
+        .map(|mime| mime.to_string());
+
+    Some(UploadRecord {
+        id: String::new(),
+        original_filename,
+        file_hash,
+        file_size,
+        url,
+        upload_time,
+        status: UploadStatus::Active,
+        expires_at: None,
+        content_type,
+        profile: None,
+        source: app.source(),
+        bucket: None,
+        object_key: None,
+        width: number(&["width"]).and_then(|width| u32::try_from(width).ok()),
+        height: number(&["height"]).and_then(|height| u32::try_from(height).ok()),
+        client: Some(client),
+        original_hash: None,
+        tags: Vec::new(),
+        notes: None,
+        alt_text: None,
+        caption: None,
+    })
+}
+
+/// Contents of a file a gallery entry points at, if it is a regular file of
+/// at most `max_bytes`. The paths come from another app's history, so
+/// directories, devices and huge files are skipped rather than read.
+fn read_local_file(path: &Path, max_bytes: u64) -> Option<Vec<u8>> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.len() > max_bytes {
+        return None;
+    }
+    std::fs::read(path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::UploadSource;
+
+    #[test]
+    fn reads_picgo_galleries() {
+        let content = r#"{
+            "uploaded": [
+                {"fileName": "cat.png", "imgUrl": "https://img.example.com/cat.png", "type": "github",
+                 "createdAt": 1700000000123, "width": 640, "height": 480},
+                {"fileName": "dup.png", "imgUrl": "https://img.example.com/cat.png"},
+                {"fileName": "broken.png"},
+                {"imgUrl": "https://img.example.com/dir/dog.jpg?v=2", "date": 1700000000}
+            ]
+        }"#;
+        let (records, errors) = parse(ExternalHistoryApp::PicGo, content).unwrap();
+
+        assert_eq!(errors, vec!["Entry 3: no URL"]);
+        assert_eq!(records.len(), 2);
+        let cat = &records[0];
+        assert_eq!(cat.original_filename, "cat.png");
+        assert_eq!(cat.upload_time, 1_700_000_000);
+        assert_eq!((cat.width, cat.height), (Some(640), Some(480)));
+        assert_eq!(cat.client.as_deref(), Some("PicGo (github)"));
+        assert_eq!(cat.source, UploadSource::PicGo);
+        assert_eq!(cat.content_type.as_deref(), Some("image/png"));
+        assert_eq!(
+            cat.file_hash,
+            format!("url:{}", R2Uploader::calculate_hash(b"https://img.example.com/cat.png"))
+        );
+
+        let dog = &records[1];
+        assert_eq!(dog.original_filename, "dog.jpg");
+        assert_eq!(dog.upload_time, 1_700_000_000);
+        assert_eq!(dog.content_type.as_deref(), Some("image/jpeg"));
+    }
+
+    #[test]
+    fn hashes_local_files_that_still_exist() {
+        let path = std::env::temp_dir().join(format!("moli-external-{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"image bytes").unwrap();
+        let content = serde_json::json!([{ "url": "https://img.example.com/a.png", "path": path }]).to_string();
+
+        let (records, _) = parse(ExternalHistoryApp::PicList, &content).unwrap();
+        assert_eq!(records[0].file_hash, R2Uploader::calculate_hash(b"image bytes"));
+        assert_eq!(records[0].file_size, 11);
+        assert_eq!(records[0].source, UploadSource::PicList);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn skips_large_or_special_local_files() {
+        let path = std::env::temp_dir().join(format!("moli-external-{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"image bytes").unwrap();
+        assert_eq!(read_local_file(&path, 11).as_deref(), Some(&b"image bytes"[..]));
+        assert_eq!(read_local_file(&path, 10), None);
+        assert_eq!(read_local_file(&std::env::temp_dir(), MAX_LOCAL_FILE_BYTES), None);
+        assert_eq!(read_local_file(&path.with_extension("missing"), MAX_LOCAL_FILE_BYTES), None);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn reads_upic_url_lists() {
+        let content = "\u{feff}[\"https://img.example.com/a.webp\", \" \", \"https://img.example.com/b.gif#x\"]";
+        let (records, errors) = parse(ExternalHistoryApp::UPic, content).unwrap();
+        assert_eq!(errors, vec!["Entry 2: no URL"]);
+        assert_eq!(
+            records.iter().map(|record| record.original_filename.as_str()).collect::<Vec<_>>(),
+            vec!["a.webp", "b.gif"]
+        );
+        assert_eq!(records[0].client.as_deref(), Some("uPic"));
+    }
+
+    #[test]
+    fn rejects_files_without_history() {
+        assert!(parse(ExternalHistoryApp::PicGo, "not json").is_err());
+        assert!(parse(ExternalHistoryApp::PicGo, r#"{"settings": {}}"#).is_err());
+        assert!(parse(ExternalHistoryApp::UPic, "42").is_err());
+    }
+}
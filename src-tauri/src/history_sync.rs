@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::r2_uploader::R2Uploader;
+use crate::types::{
+    RemoteObject, SyncHashMode, SyncOptions, SyncReport, UploadRecord, UploadSource, UploadStatus,
+};
+
+/// Compares the active profile's upload history with the bucket contents,
+/// marking records whose objects are gone and optionally importing objects
+/// the history doesn't know about.
+pub async fn reconcile_history(
+    uploader: &R2Uploader,
+    db: &Arc<Mutex<Option<Database>>>,
+    options: &SyncOptions,
+) -> Result<SyncReport> {
+    let mut report = SyncReport {
+        started_at: Database::get_current_timestamp(),
+        ..Default::default()
+    };
+
+    let records = {
+        let db_guard = db.lock().unwrap();
+        match db_guard.as_ref() {
+            Some(db) => db.get_profile_uploads(&uploader.config().profile_key())?,
+            None => return Err(anyhow::anyhow!("Database not available")),
+        }
+    };
+
+    println!("[HistorySync] Listing bucket objects...");
+    let objects = uploader.list_all_objects(options.prefix.as_deref()).await?;
+    report.scanned_objects = objects.len();
+
+    let object_keys: HashSet<&str> = objects.iter().map(|object| object.key.as_str()).collect();
+    let mut tracked_keys: HashSet<String> = HashSet::new();
+    let mut status_changes: Vec<(String, UploadStatus)> = Vec::new();
+
+    for record in records {
+        let key = match uploader.record_key(&record) {
+            Some(key) => key,
+            None => {
+                report.skipped_records += 1;
+                continue;
+            }
+        };
+
+        if let Some(prefix) = options.prefix.as_deref() {
+            if !key.starts_with(prefix) {
+                continue;
+            }
+        }
+
+        // Expired objects were deleted on purpose; keep them out of both the
+        // missing and the untracked lists.
+        if record.status == UploadStatus::Expired {
+            tracked_keys.insert(key);
+            continue;
+        }
+
+        report.tracked_records += 1;
+        let present = object_keys.contains(key.as_str());
+        tracked_keys.insert(key);
+
+        match (present, record.status) {
+            (true, UploadStatus::Missing) => {
+                status_changes.push((record.id.clone(), UploadStatus::Active));
+                report.restored.push(UploadRecord {
+                    status: UploadStatus::Active,
+                    ..record
+                });
+            }
+            (true, _) => report.matched += 1,
+            (false, UploadStatus::Missing) => report.missing.push(record),
+            (false, _) => {
+                status_changes.push((record.id.clone(), UploadStatus::Missing));
+                report.missing.push(UploadRecord {
+                    status: UploadStatus::Missing,
+                    ..record
+                });
+            }
+        }
+    }
+
+    report.untracked = objects
+        .into_iter()
+        .filter(|object| !tracked_keys.contains(&object.key))
+        .collect();
+
+    if options.dry_run {
+        report.finished_at = Database::get_current_timestamp();
+        return Ok(report);
+    }
+
+    {
+        let db_guard = db.lock().unwrap();
+        if let Some(ref db) = *db_guard {
+            for (id, status) in &status_changes {
+                db.set_upload_status(id, *status)?;
+            }
+        }
+    }
+
+    if options.import_untracked {
+        import_untracked(uploader, db, options.hash_mode, &mut report).await;
+    }
+
+    println!(
+        "[HistorySync] Done: {} matched, {} missing, {} restored, {} untracked, {} imported",
+        report.matched,
+        report.missing.len(),
+        report.restored.len(),
+        report.untracked.len(),
+        report.imported.len()
+    );
+    report.finished_at = Database::get_current_timestamp();
+    Ok(report)
+}
+
+async fn import_untracked(
+    uploader: &R2Uploader,
+    db: &Arc<Mutex<Option<Database>>>,
+    hash_mode: SyncHashMode,
+    report: &mut SyncReport,
+) {
+    let mut seen_hashes: HashMap<String, String> = HashMap::new();
+
+    for object in &report.untracked {
+        let file_hash = match object_hash(uploader, object, hash_mode).await {
+            Ok(hash) => hash,
+            Err(err) => {
+                report.errors.push(format!("{}: {}", object.key, err));
+                continue;
+            }
+        };
+
+        if let Some(first_key) = seen_hashes.get(&file_hash) {
+            report.errors.push(format!(
+                "{}: same content as {}, not imported",
+                object.key, first_key
+            ));
+            continue;
+        }
+        seen_hashes.insert(file_hash.clone(), object.key.clone());
+
+        let record = UploadRecord {
+            id: Uuid::new_v4().to_string(),
+            original_filename: object
+                .key
+                .rsplit('/')
+                .next()
+                .unwrap_or(&object.key)
+                .to_string(),
+            file_hash,
+            file_size: object.size,
+            url: object.url.clone(),
+            upload_time: object
+                .last_modified
+                .unwrap_or_else(Database::get_current_timestamp),
+            status: UploadStatus::Active,
+            expires_at: None,
+            content_type: Some(object.content_type.clone()),
+            profile: Some(uploader.config().profile_key()),
+            source: UploadSource::BucketSync,
+            bucket: Some(uploader.config().bucket_name.clone()),
+            object_key: Some(object.key.clone()),
+            width: None,
+            height: None,
+            client: None,
+            original_hash: None,
+            tags: Vec::new(),
+            notes: None,
+            alt_text: None,
+            caption: None,
+        };
+
+        let db_guard = db.lock().unwrap();
+        if let Some(ref db) = *db_guard {
+            match db.import_upload_record(&record) {
+                Ok(true) => report.imported.push(record),
+                Ok(false) => report.errors.push(format!(
+                    "{}: content already tracked under another URL in this profile, not imported",
+                    object.key
+                )),
+                Err(err) => report.errors.push(format!("{}: {}", object.key, err)),
+            }
+        }
+    }
+}
+
+async fn object_hash(
+    uploader: &R2Uploader,
+    object: &RemoteObject,
+    hash_mode: SyncHashMode,
+) -> Result<String> {
+    match hash_mode {
+        SyncHashMode::Etag => object
+            .etag
+            .as_deref()
+            .map(|etag| format!("etag:{}", etag.trim_matches('"')))
+            .ok_or_else(|| anyhow::anyhow!("object has no ETag")),
+        SyncHashMode::Download => {
+            let data = uploader.download_object(&object.key).await?;
+            Ok(R2Uploader::calculate_hash(&data))
+        }
+    }
+}
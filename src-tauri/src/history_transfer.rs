@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::bucket_stats;
+use crate::database::{self, Database};
+use crate::snippets;
+use crate::types::{
+    HistoryExport, HistoryExportFormat, HistoryFilter, HistoryImportReport, HistorySort, UploadRecord, UploadSource,
+    UploadStatus,
+};
+
+const EXPORT_PAGE_SIZE: usize = 500;
+
+type TextSetter = fn(&Database, &str, Option<&str>) -> Result<()>;
+
+/// CSV columns, in export order. Tags are joined with commas, like the
+/// `tags` parameter of the upload API.
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "original_filename",
+    "file_hash",
+    "file_size",
+    "url",
+    "upload_time",
+    "status",
+    "expires_at",
+    "content_type",
+    "profile",
+    "source",
+    "bucket",
+    "object_key",
+    "width",
+    "height",
+    "client",
+    "original_hash",
+    "tags",
+    "notes",
+    "alt_text",
+    "caption",
+];
+/// Leading characters that make spreadsheet apps evaluate a cell as a formula.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Exports the records matching `filter`, oldest first.
+pub fn export(db: &Database, filter: &HistoryFilter, format: HistoryExportFormat) -> Result<HistoryExport> {
+    let mut records = Vec::new();
+    let mut cursor = None;
+    loop {
+        let (page, next) = db.search_uploads(filter, HistorySort::Oldest, cursor.as_deref(), EXPORT_PAGE_SIZE)?;
+        records.extend(page);
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    let (content, extension) = match format {
+        HistoryExportFormat::Json => (serde_json::to_string_pretty(&records)?, "json"),
+        HistoryExportFormat::Csv => (to_csv(&records), "csv"),
+        HistoryExportFormat::Markdown => (to_markdown(&records), "md"),
+    };
+    let filename = format!(
+        "moli-tutu-history-{}.{}",
+        bucket_stats::date_of(Database::get_current_timestamp()),
+        extension
+    );
+    println!("[HistoryTransfer] Exported {} records as {}", records.len(), extension);
+
+    Ok(HistoryExport {
+        content,
+        filename,
+        record_count: records.len(),
+    })
+}
+
+/// Imports a JSON or CSV export. A record matching an existing one by URL,
+/// or by hash within the same profile, is merged into it: its tags are
+/// added and its notes, alt text and caption fill in those that are empty.
+pub fn import(db: &Database, content: &str, format: HistoryExportFormat) -> Result<HistoryImportReport> {
+    let mut report = HistoryImportReport::default();
+    let records = match format {
+        HistoryExportFormat::Json => serde_json::from_str::<Vec<UploadRecord>>(content)
+            .map_err(|e| anyhow::anyhow!("Not a history export: {}", e))?,
+        HistoryExportFormat::Csv => {
+            let (records, errors) = from_csv(content)?;
+            report.errors = errors;
+            records
+        }
+        HistoryExportFormat::Markdown => {
+            return Err(anyhow::anyhow!(
+                "Markdown exports can't be imported; export the history as JSON or CSV instead"
+            ))
+        }
+    };
+
+    import_records(db, records, &mut report)?;
+
+    println!(
+        "[HistoryTransfer] Imported history: {} added, {} merged, {} unchanged, {} errors",
+        report.added,
+        report.merged,
+        report.unchanged,
+        report.errors.len()
+    );
+    Ok(report)
+}
+
+/// Adds `records` to the history, merging those already present into the
+/// existing records, and counts them in `report`. Imported records never
+/// schedule deletions; see `untrusted`.
+pub fn import_records(db: &Database, records: Vec<UploadRecord>, report: &mut HistoryImportReport) -> Result<()> {
+    for record in records {
+        let mut record = untrusted(record);
+        let existing = match db.find_by_url(&record.url)? {
+            Some(existing) => Some(existing),
+            None => match &record.profile {
+                Some(profile) => db.find_by_hash(&record.file_hash, profile)?,
+                None => None,
+            },
+        };
+
+        match existing {
+            Some(existing) => {
+                if merge(db, &existing, &record)? {
+                    report.merged += 1;
+                } else {
+                    report.unchanged += 1;
+                }
+            }
+            None => {
+                // Ids are only unique per machine, so a taken one is replaced.
+                if record.id.is_empty() || db.get_upload(&record.id)?.is_some() {
+                    record.id = Uuid::new_v4().to_string();
+                }
+                if db.import_upload_record(&record)? {
+                    report.added += 1;
+                } else {
+                    report.unchanged += 1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Strips what an imported file could use to make the expiry sweep delete
+/// objects: the expiry time, and an object key that doesn't name the object
+/// behind the record's URL.
+fn untrusted(mut record: UploadRecord) -> UploadRecord {
+    record.expires_at = None;
+    if let Some(key) = &record.object_key {
+        let url = record.url.split(['?', '#']).next().unwrap_or_default();
+        if key.is_empty() || !url.ends_with(&format!("/{}", key)) {
+            record.object_key = None;
+        }
+    }
+    record
+}
+
+/// Adds what `imported` knows and `existing` doesn't. Returns whether
+/// anything changed.
+fn merge(db: &Database, existing: &UploadRecord, imported: &UploadRecord) -> Result<bool> {
+    let mut changed = false;
+
+    let known: Vec<String> = existing.tags.iter().map(|tag| tag.to_lowercase()).collect();
+    let new_tags: Vec<String> = database::normalize_tags(&imported.tags)
+        .into_iter()
+        .filter(|tag| !known.contains(&tag.to_lowercase()))
+        .collect();
+    if !new_tags.is_empty() {
+        db.add_upload_tags(&existing.id, &new_tags)?;
+        changed = true;
+    }
+
+    let fields: [(&Option<String>, &Option<String>, TextSetter); 3] = [
+        (&existing.notes, &imported.notes, Database::set_upload_notes),
+        (&existing.alt_text, &imported.alt_text, Database::set_upload_alt_text),
+        (&existing.caption, &imported.caption, Database::set_upload_caption),
+    ];
+    for (current, incoming, set) in fields {
+        if let (None, Some(text)) = (current, incoming) {
+            if !text.trim().is_empty() {
+                set(db, &existing.id, Some(text))?;
+                changed = true;
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+fn to_csv(records: &[UploadRecord]) -> String {
+    let mut csv = String::new();
+    push_csv_row(&mut csv, CSV_COLUMNS.iter().map(|column| column.to_string()));
+    for record in records {
+        let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+        let number = |value: Option<i64>| value.map(|value| value.to_string()).unwrap_or_default();
+        push_csv_row(
+            &mut csv,
+            [
+                record.id.clone(),
+                record.original_filename.clone(),
+                record.file_hash.clone(),
+                record.file_size.to_string(),
+                record.url.clone(),
+                record.upload_time.to_string(),
+                record.status.as_str().to_string(),
+                number(record.expires_at),
+                optional(&record.content_type),
+                optional(&record.profile),
+                record.source.as_str().to_string(),
+                optional(&record.bucket),
+                optional(&record.object_key),
+                number(record.width.map(i64::from)),
+                number(record.height.map(i64::from)),
+                optional(&record.client),
+                optional(&record.original_hash),
+                record.tags.join(","),
+                optional(&record.notes),
+                optional(&record.alt_text),
+                optional(&record.caption),
+            ],
+        );
+    }
+    csv
+}
+
+fn push_csv_row(csv: &mut String, fields: impl IntoIterator<Item = String>) {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            csv.push(',');
+        }
+        let field = escape_formula(field);
+        if field.contains(['"', ',', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&field.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(&field);
+        }
+    }
+    csv.push_str("\r\n");
+}
+
+/// Prefixes fields a spreadsheet would run as a formula with `'`, which makes
+/// it show them as text. Fields already starting with `'` get one as well so
+/// `unescape_formula` can tell the two apart.
+fn escape_formula(field: String) -> String {
+    if field.starts_with(FORMULA_PREFIXES) || field.starts_with('\'') {
+        format!("'{}", field)
+    } else {
+        field
+    }
+}
+
+fn unescape_formula(field: &str) -> &str {
+    match field.strip_prefix('\'') {
+        Some(rest) if rest.starts_with(FORMULA_PREFIXES) || rest.starts_with('\'') => rest,
+        _ => field,
+    }
+}
+
+/// Parses a CSV export. Columns are matched by header name, so files edited
+/// in a spreadsheet may reorder or drop the optional ones. Rows that can't be
+/// read are reported rather than failing the whole import.
+fn from_csv(content: &str) -> Result<(Vec<UploadRecord>, Vec<String>)> {
+    let mut rows = parse_csv(content.trim_start_matches('\u{feff}')).into_iter();
+    let header = rows.next().ok_or_else(|| anyhow::anyhow!("The CSV file is empty"))?;
+    let columns: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.trim().to_lowercase(), i))
+        .collect();
+    for required in ["url", "file_hash"] {
+        if !columns.contains_key(required) {
+            return Err(anyhow::anyhow!("The CSV file has no \"{}\" column", required));
+        }
+    }
+
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    // The header is line 1.
+    for (line, row) in (2..).zip(rows) {
+        if row.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        match csv_record(&columns, &row) {
+            Ok(record) => records.push(record),
+            Err(e) => errors.push(format!("Row {}: {}", line, e)),
+        }
+    }
+    Ok((records, errors))
+}
+
+fn csv_record(columns: &HashMap<String, usize>, row: &[String]) -> Result<UploadRecord> {
+    let text = |column: &str| {
+        columns
+            .get(column)
+            .and_then(|&i| row.get(i))
+            .map(|value| unescape_formula(value).trim())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    fn number<T: std::str::FromStr>(column: &str, value: Option<String>) -> Result<Option<T>> {
+        value
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("\"{}\" is not a valid {}", value, column))
+            })
+            .transpose()
+    }
+
+    let url = text("url").ok_or_else(|| anyhow::anyhow!("missing url"))?;
+    let file_hash = text("file_hash").ok_or_else(|| anyhow::anyhow!("missing file_hash"))?;
+    let original_filename = text("original_filename")
+        .unwrap_or_else(|| url.rsplit('/').next().unwrap_or(&url).to_string());
+
+    Ok(UploadRecord {
+        id: text("id").unwrap_or_default(),
+        original_filename,
+        file_hash,
+        file_size: number("file_size", text("file_size"))?.unwrap_or(0),
+        url,
+        upload_time: number("upload_time", text("upload_time"))?.unwrap_or_else(Database::get_current_timestamp),
+        status: text("status").map(|status| UploadStatus::from_db(&status)).unwrap_or_default(),
+        expires_at: number("expires_at", text("expires_at"))?,
+        content_type: text("content_type"),
+        profile: text("profile"),
+        source: text("source").map(|source| UploadSource::from_db(&source)).unwrap_or_default(),
+        bucket: text("bucket"),
+        object_key: text("object_key"),
+        width: number("width", text("width"))?,
+        height: number("height", text("height"))?,
+        client: text("client"),
+        original_hash: text("original_hash"),
+        tags: database::normalize_tags(text("tags").unwrap_or_default().split(',')),
+        notes: text("notes"),
+        alt_text: text("alt_text"),
+        caption: text("caption"),
+    })
+}
+
+/// Splits RFC 4180 CSV into rows of fields.
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+fn to_markdown(records: &[UploadRecord]) -> String {
+    let mut markdown = String::from("# Upload history\n");
+    for record in records {
+        markdown.push_str(&format!("\n## {}\n\n", record.original_filename));
+        markdown.push_str(&snippets::snippets(record).markdown);
+        markdown.push_str("\n\n");
+
+        let mut details = vec![
+            bucket_stats::date_of(record.upload_time),
+            format_size(record.file_size),
+        ];
+        if let (Some(width), Some(height)) = (record.width, record.height) {
+            details.push(format!("{} × {}", width, height));
+        }
+        if !record.tags.is_empty() {
+            details.push(record.tags.iter().map(|tag| format!("`{}`", tag)).collect::<Vec<_>>().join(" "));
+        }
+        markdown.push_str(&details.join(" · "));
+        markdown.push('\n');
+
+        if let Some(notes) = &record.notes {
+            markdown.push_str(&format!("\n> {}\n", notes.lines().collect::<Vec<_>>().join("\n> ")));
+        }
+    }
+    markdown
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> UploadRecord {
+        UploadRecord {
+            id: "upload".to_string(),
+            original_filename: "say \"cheese\", please.png".to_string(),
+            file_hash: "hash".to_string(),
+            file_size: 2048,
+            url: "https://img.example.com/a,b.png".to_string(),
+            upload_time: 1_700_000_000,
+            status: UploadStatus::Active,
+            expires_at: Some(1_800_000_000),
+            content_type: Some("image/png".to_string()),
+            profile: Some("https://r2.example.com/bucket".to_string()),
+            source: UploadSource::Proxy,
+            bucket: Some("bucket".to_string()),
+            object_key: Some("a,b.png".to_string()),
+            width: Some(640),
+            height: Some(480),
+            client: Some("@bot".to_string()),
+            original_hash: None,
+            tags: vec!["cats".to_string(), "holiday".to_string()],
+            notes: Some("first line\r\nsecond line".to_string()),
+            alt_text: Some("-1 point".to_string()),
+            caption: Some("=HYPERLINK(\"https://evil.example.com\")".to_string()),
+        }
+    }
+
+    #[test]
+    fn csv_round_trips_special_characters() {
+        let mut quoted = record();
+        quoted.id = "quoted".to_string();
+        quoted.original_filename = "'=already quoted".to_string();
+        quoted.caption = Some("'plain".to_string());
+        let csv = to_csv(&[record(), quoted.clone()]);
+
+        let (records, errors) = from_csv(&csv).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(records.len(), 2);
+        for (parsed, original) in records.iter().zip([record(), quoted]) {
+            assert_eq!(
+                serde_json::to_value(parsed).unwrap(),
+                serde_json::to_value(&original).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn csv_fields_never_start_a_formula() {
+        let csv = to_csv(&[record()]);
+        let rows = parse_csv(&csv);
+        assert_eq!(rows.len(), 2);
+        for field in &rows[1] {
+            assert!(!field.starts_with(FORMULA_PREFIXES), "{:?} starts a formula", field);
+        }
+        assert!(rows[1].contains(&"'=HYPERLINK(\"https://evil.example.com\")".to_string()));
+        assert!(rows[1].contains(&"'@bot".to_string()));
+    }
+
+    #[test]
+    fn csv_import_reports_bad_rows() {
+        let csv = "url,file_hash,file_size\r\nhttps://a.example.com/1.png,h1,12\r\n,h2,1\r\nhttps://a.example.com/3.png,h3,big\r\n\r\n";
+        let (records, errors) = from_csv(csv).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].file_size, 12);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("Row 3"));
+        assert!(from_csv("id,name\r\n").is_err());
+    }
+
+    #[test]
+    fn imported_records_never_expire() {
+        let db = Database::new(std::path::PathBuf::from(":memory:"), None).unwrap();
+        let profile = record().profile.unwrap();
+        let mut foreign = record();
+        foreign.id = "foreign".to_string();
+        foreign.url = "https://img.example.com/other.png".to_string();
+        foreign.file_hash = "other".to_string();
+        foreign.object_key = Some("private/backup.db".to_string());
+
+        let mut report = HistoryImportReport::default();
+        import_records(&db, vec![record(), foreign], &mut report).unwrap();
+        assert_eq!(report.added, 2);
+
+        assert!(db.get_due_expirations(i64::MAX, &profile).unwrap().is_empty());
+        let kept = db.find_by_url(&record().url).unwrap().unwrap();
+        assert_eq!(kept.expires_at, None);
+        assert_eq!(kept.object_key.as_deref(), Some("a,b.png"));
+        let foreign = db.find_by_url("https://img.example.com/other.png").unwrap().unwrap();
+        assert_eq!(foreign.object_key, None);
+    }
+}
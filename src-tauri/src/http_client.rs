@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use aws_smithy_runtime_api::client::http::{
+    http_client_fn, HttpConnector, HttpConnectorFuture, SharedHttpClient, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::{HttpRequest, HttpResponse};
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use aws_smithy_runtime_api::http::StatusCode;
+use aws_smithy_types::body::SdkBody;
+use reqwest::{Certificate, NoProxy, Proxy};
+
+use crate::types::NetworkConfig;
+
+/// Builds a reqwest client honoring the profile's proxy, extra root
+/// certificates and timeouts.
+pub fn build_reqwest_client(network: &NetworkConfig) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy_config) = &network.proxy {
+        let url = proxy_config.url.trim();
+        if url.is_empty() {
+            return Err(anyhow::anyhow!("Proxy URL is empty"));
+        }
+        println!("[HttpClient] Using outbound proxy: {}", url);
+
+        let mut proxy = Proxy::all(url).map_err(|e| anyhow::anyhow!("Invalid proxy URL: {}", e))?;
+        if let Some(username) = proxy_config.username.as_deref().filter(|u| !u.is_empty()) {
+            proxy = proxy.basic_auth(username, proxy_config.password.as_deref().unwrap_or(""));
+        }
+        let no_proxy: Vec<&str> = proxy_config
+            .no_proxy
+            .iter()
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        if !no_proxy.is_empty() {
+            proxy = proxy.no_proxy(NoProxy::from_string(&no_proxy.join(",")));
+        }
+        builder = builder.proxy(proxy);
+    } else {
+        // Don't let HTTP(S)_PROXY from the environment override a direct connection.
+        builder = builder.no_proxy();
+    }
+
+    for entry in network.ca_certificates.iter().filter(|entry| !entry.trim().is_empty()) {
+        for certificate in load_certificates(entry)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some(secs) = network.connect_timeout_secs.filter(|secs| *secs > 0) {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = network.read_timeout_secs.filter(|secs| *secs > 0) {
+        builder = builder.read_timeout(Duration::from_secs(secs));
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Wraps a reqwest client so the AWS SDK sends its requests through it.
+pub fn sdk_http_client(network: &NetworkConfig) -> Result<SharedHttpClient> {
+    let connector = SharedHttpConnector::new(ReqwestConnector {
+        client: build_reqwest_client(network)?,
+    });
+    Ok(http_client_fn(move |_, _| connector.clone()))
+}
+
+/// Accepts either inline PEM text or the path of a PEM file.
+fn load_certificates(entry: &str) -> Result<Vec<Certificate>> {
+    let entry = entry.trim();
+    let pem = if entry.contains("-----BEGIN") {
+        entry.as_bytes().to_vec()
+    } else {
+        println!("[HttpClient] Loading CA certificates from: {}", entry);
+        fs::read(Path::new(entry))
+            .map_err(|e| anyhow::anyhow!("Failed to read CA certificate {}: {}", entry, e))?
+    };
+
+    let certificates = Certificate::from_pem_bundle(&pem)
+        .map_err(|e| anyhow::anyhow!("Invalid CA certificate: {}", e))?;
+    if certificates.is_empty() {
+        return Err(anyhow::anyhow!("No certificates found in {}", entry));
+    }
+    Ok(certificates)
+}
+
+#[derive(Debug)]
+struct ReqwestConnector {
+    client: reqwest::Client,
+}
+
+impl HttpConnector for ReqwestConnector {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let client = self.client.clone();
+        HttpConnectorFuture::new(async move {
+            let method = reqwest::Method::from_bytes(request.method().as_bytes())
+                .map_err(|e| ConnectorError::user(e.into()))?;
+            let body = request
+                .body()
+                .bytes()
+                .map(|bytes| bytes.to_vec())
+                .ok_or_else(|| ConnectorError::user("Streaming request bodies are not supported".into()))?;
+
+            let mut builder = client.request(method, request.uri());
+            for (name, value) in request.headers() {
+                builder = builder.header(name, value);
+            }
+
+            let response = builder.body(body).send().await.map_err(map_reqwest_error)?;
+
+            let status = StatusCode::try_from(response.status().as_u16())
+                .map_err(|e| ConnectorError::other(e.into(), None))?;
+            let headers: Vec<(String, String)> = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    value.to_str().ok().map(|value| (name.as_str().to_string(), value.to_string()))
+                })
+                .collect();
+            let data = response.bytes().await.map_err(map_reqwest_error)?;
+
+            let mut sdk_response = HttpResponse::new(status, SdkBody::from(data));
+            for (name, value) in headers {
+                sdk_response.headers_mut().append(name, value);
+            }
+            Ok(sdk_response)
+        })
+    }
+}
+
+fn map_reqwest_error(error: reqwest::Error) -> ConnectorError {
+    if error.is_timeout() {
+        ConnectorError::timeout(error.into())
+    } else if error.is_connect() || error.is_request() || error.is_body() {
+        ConnectorError::io(error.into())
+    } else {
+        ConnectorError::other(error.into(), None)
+    }
+}
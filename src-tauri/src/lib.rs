@@ -1,26 +1,147 @@
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tauri::{Manager, State};
-use uuid::Uuid;
 use base64;
 
 mod types;
 mod r2_uploader;
 mod database;
+mod migrations;
 mod config;
+mod credentials;
+mod http_client;
 mod proxy_server;
+mod history_sync;
+mod history_transfer;
+mod external_history;
+mod bucket_stats;
+mod bucket_rules;
+mod upload_service;
+mod connection_test;
+mod client_encryption;
+mod compression_store;
+mod snippets;
+mod expiry_scheduler;
+mod operation_meter;
+mod uploader_manager;
+mod vault;
 
-use types::{ApiProxyStatus, AppSettings, R2Config, UploadRecord, UploadResult};
-use r2_uploader::R2Uploader;
+use types::{
+    Album, ApiProxyStatus, AppSettings, BucketListRequest, BucketListing, BucketStats, CompressionFiles,
+    CompressionRecord, ConnectionTestReport, CorsPreset, CorsRuleConfig, EncryptedUploadResult, ExpiryReport,
+    ExternalHistoryApp, HistoryExport, HistoryExportFormat, HistoryFilter, HistoryImportReport, HistoryPage,
+    HistoryPageRequest, LifecyclePreset, LifecycleRuleConfig, OperationUsage, PresignRequest, PresignedUpload,
+    R2Config, RemoteObject, RetentionPolicy, SaveCompressionRequest, SecurityStatus, SyncOptions, SyncReport,
+    TagCount, UploadRecord, UploadResult, UploadSnippets, UploadSource, UploaderStatus,
+};
+use compression_store::CompressionStore;
 use database::Database;
 use config::ConfigManager;
+use operation_meter::OperationMeter;
 use proxy_server::ProxyServer;
+use r2_uploader::R2Uploader;
+use upload_service::UploadOptions;
+use uploader_manager::UploaderManager;
+use vault::Vault;
+
+const LOCKED_ERROR: &str = "App is locked; unlock it with the master passphrase first";
+const DEFAULT_HISTORY_PAGE_SIZE: usize = 50;
+const MAX_HISTORY_PAGE_SIZE: usize = 500;
 
 struct AppState {
     db: Arc<Mutex<Option<Database>>>,
+    db_path: PathBuf,
     config_manager: Mutex<ConfigManager>,
-    uploader: Arc<Mutex<Option<R2Uploader>>>,
+    uploader: UploaderManager,
+    meter: OperationMeter,
     proxy: Mutex<ProxyServer>,
-    settings: Mutex<AppSettings>,
+    settings: Arc<Mutex<AppSettings>>,
+    vault: Vault,
+    compressions: CompressionStore,
+}
+
+impl AppState {
+    /// Encryption at rest is enabled but the master passphrase hasn't been
+    /// entered yet, so secrets and history are unavailable.
+    fn is_locked(&self) -> bool {
+        self.vault.is_enabled() && self.config_manager.lock().unwrap().master_key().is_none()
+    }
+
+    fn security_status(&self) -> SecurityStatus {
+        SecurityStatus {
+            encryption_enabled: self.vault.is_enabled(),
+            locked: self.is_locked(),
+        }
+    }
+}
+
+/// Opens the history database, builds the uploader from the saved config and
+/// starts the API proxy if it's enabled. Runs at startup, or on unlock when
+/// encryption at rest is enabled.
+fn activate_backend(state: &AppState) -> Result<(), String> {
+    let master_key = state.config_manager.lock().unwrap().master_key().cloned();
+    let db = Database::new(state.db_path.clone(), master_key.as_ref()).map_err(|e| {
+        println!("[Backend] Failed to open database: {}", e);
+        e.to_string()
+    })?;
+    let retention = state.settings.lock().unwrap().history_retention.clone();
+    match db.prune_history(&retention, Database::get_current_timestamp()) {
+        Ok(0) => {}
+        Ok(pruned) => println!("[Backend] Pruned {} history records", pruned),
+        Err(e) => println!("[Backend] Failed to prune history: {}", e),
+    }
+    *state.db.lock().unwrap() = Some(db);
+
+    // Build the uploader in the background so a slow or missing network
+    // doesn't hold up startup
+    match state.config_manager.lock().unwrap().load_config() {
+        Ok(Some(config)) => state.uploader.start(config),
+        Ok(None) => state.uploader.reset(),
+        Err(e) => {
+            println!("[Backend] Failed to load config: {}", e);
+            state.uploader.reset();
+        }
+    }
+
+    let proxy_enabled = state.settings.lock().unwrap().api_proxy_enabled;
+    if proxy_enabled {
+        let started = {
+            let mut proxy = state.proxy.lock().unwrap();
+            tauri::async_runtime::block_on(proxy.start(
+                state.uploader.clone(),
+                state.db.clone(),
+                state.settings.clone(),
+            ))
+        };
+        if let Err(err) = started {
+            println!("[Backend] Failed to start API proxy: {}", err);
+            let mut settings = state.settings.lock().unwrap();
+            settings.api_proxy_enabled = false;
+            let _ = state.config_manager.lock().unwrap().save_settings(&settings);
+        }
+    }
+
+    Ok(())
+}
+
+/// Stops everything that needs decrypted secrets or history.
+fn deactivate_backend(state: &AppState) -> Result<(), String> {
+    state.proxy.lock().unwrap().stop()?;
+    state.uploader.reset();
+    flush_operation_counts(state);
+    *state.db.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Saves the operation counts buffered since the meter's last periodic
+/// flush, so they aren't lost when the database closes or the app quits.
+fn flush_operation_counts(state: &AppState) {
+    let db_guard = state.db.lock().unwrap();
+    if let Some(db) = db_guard.as_ref() {
+        if let Err(e) = state.meter.flush(db) {
+            println!("[Backend] Failed to save operation counts: {}", e);
+        }
+    }
 }
 
 #[tauri::command]
@@ -30,6 +151,10 @@ async fn save_r2_config(
 ) -> Result<(), String> {
     println!("[Backend] Saving R2 config: endpoint={}, bucket={}", config.endpoint, config.bucket_name);
     
+    if state.is_locked() {
+        return Err(LOCKED_ERROR.to_string());
+    }
+    
     // Save config
     {
         let config_manager = state.config_manager.lock().unwrap();
@@ -39,21 +164,26 @@ async fn save_r2_config(
         })?;
     }
     
+    // Counts of the previous profile go to the database before switching
+    flush_operation_counts(&state);
+
     // Initialize uploader
     println!("[Backend] Initializing R2 uploader...");
-    let uploader = R2Uploader::new(config).await.map_err(|e| {
+    state.uploader.configure(config).await.map_err(|e| {
         println!("[Backend] Failed to initialize uploader: {}", e);
         e.to_string()
     })?;
-    {
-        let mut uploader_guard = state.uploader.lock().unwrap();
-        *uploader_guard = Some(uploader);
-    }
     
     println!("[Backend] R2 config saved and uploader initialized successfully");
     Ok(())
 }
 
+#[tauri::command]
+fn generate_sse_customer_key() -> Result<String, String> {
+    println!("[Backend] Generating SSE-C customer key");
+    Ok(R2Uploader::generate_customer_key())
+}
+
 #[tauri::command]
 async fn get_r2_config(
     state: State<'_, AppState>,
@@ -74,56 +204,85 @@ async fn get_r2_config(
     result
 }
 
+#[tauri::command]
+async fn test_storage_connection(
+    config: Option<R2Config>,
+    state: State<'_, AppState>,
+) -> Result<ConnectionTestReport, String> {
+    // Test the submitted form when given, otherwise the saved configuration
+    let config = match config {
+        Some(config) => config,
+        None => {
+            let config_manager = state.config_manager.lock().unwrap();
+            config_manager
+                .load_config()
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "R2 configuration not set".to_string())?
+        }
+    };
+    println!("[Backend] Testing storage connection: endpoint={}, bucket={}", config.endpoint, config.bucket_name);
+
+    let report = connection_test::run_connection_test(config, &state.meter).await;
+    println!("[Backend] Storage connection test finished: success={}", report.success);
+    Ok(report)
+}
+
 #[tauri::command]
 async fn upload_image(
     file_data: Vec<u8>,
     filename: String,
+    ttl_secs: Option<u64>,
+    source: Option<UploadSource>,
+    original_hash: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<UploadResult, String> {
-    println!("[Backend] Upload request: filename={}, size={} bytes", filename, file_data.len());
+    println!(
+        "[Backend] Upload request: filename={}, size={} bytes, ttl={:?}, source={:?}",
+        filename,
+        file_data.len(),
+        ttl_secs,
+        source
+    );
+    
+    if state.is_locked() {
+        println!("[Backend] App is locked, returning error");
+        return Ok(UploadResult {
+            success: false,
+            url: None,
+            error: Some(LOCKED_ERROR.to_string()),
+            from_cache: false,
+            record: None,
+        });
+    }
     
     // Check if uploader is initialized and get a copy
-    let uploader = {
-        let uploader_guard = state.uploader.lock().unwrap();
-        match uploader_guard.as_ref() {
-            Some(uploader) => {
-                println!("[Backend] Uploader found, proceeding with upload");
-                uploader.clone()
-            },
-            None => {
-                println!("[Backend] No uploader configured, returning error");
-                return Ok(UploadResult {
-                    success: false,
-                    url: None,
-                    error: Some("R2 configuration not set".to_string()),
-                    from_cache: false,
-                });
-            }
+    let uploader = match state.uploader.uploader().await {
+        Ok(uploader) => {
+            println!("[Backend] Uploader ready, proceeding with upload");
+            uploader
+        },
+        Err(e) => {
+            println!("[Backend] Uploader unavailable, returning error: {}", e);
+            return Ok(UploadResult {
+                success: false,
+                url: None,
+                error: Some(e),
+                from_cache: false,
+                record: None,
+            });
         }
     };
     
-    // Calculate file hash
-    println!("[Backend] Calculating file hash...");
-    let file_hash = R2Uploader::calculate_hash(&file_data);
-    println!("[Backend] File hash calculated: {}", file_hash);
-    
-    // Check if file already exists in database
-    {
-        println!("[Backend] Checking database for existing file...");
-        let db_guard = state.db.lock().unwrap();
-        if let Some(ref db) = *db_guard {
-            if let Ok(Some(existing_record)) = db.find_by_hash(&file_hash) {
-                println!("[Backend] Found existing file in cache, returning cached URL: {}", existing_record.url);
-                return Ok(UploadResult {
-                    success: true,
-                    url: Some(existing_record.url),
-                    error: None,
-                    from_cache: true,
-                });
-            }
-        }
-        println!("[Backend] File not found in cache, proceeding with upload");
-    }
+    let verify_remote = state.settings.lock().unwrap().verify_cache_with_head;
+    let options = UploadOptions {
+        expires_at: upload_service::resolve_expiry(&uploader, ttl_secs),
+        source: source.unwrap_or_default(),
+        client: Some(upload_service::app_client()),
+        original_hash,
+        tags: Vec::new(),
+        alt_text: None,
+        caption: None,
+    };
     
     // Determine content type
     let content_type = mime_guess::from_path(&filename)
@@ -131,54 +290,384 @@ async fn upload_image(
         .to_string();
     println!("[Backend] Detected content type: {}", content_type);
     
-    // Upload to R2
-    println!("[Backend] Starting R2 upload...");
-    let url = match uploader.upload_file(file_data.clone(), &filename, &content_type).await {
-        Ok(url) => {
-            println!("[Backend] R2 upload successful: {}", url);
-            url
+    // Upload to R2, reusing a verified earlier upload of the same file
+    println!("[Backend] Starting upload (verify_remote={})...", verify_remote);
+    match upload_service::upload_with_dedupe(
+        &uploader,
+        &state.db,
+        verify_remote,
+        file_data,
+        &filename,
+        &content_type,
+        &options,
+    )
+    .await
+    {
+        Ok(outcome) => {
+            println!("[Backend] Upload completed successfully: {} (from_cache={})", outcome.url, outcome.from_cache);
+            Ok(UploadResult {
+                success: true,
+                url: Some(outcome.url),
+                error: None,
+                from_cache: outcome.from_cache,
+                record: Some(outcome.record),
+            })
         },
         Err(e) => {
             println!("[Backend] R2 upload failed: {}", e);
-            return Ok(UploadResult {
+            Ok(UploadResult {
                 success: false,
                 url: None,
                 error: Some(e.to_string()),
                 from_cache: false,
-            });
+                record: None,
+            })
+        }
+    }
+}
+
+#[tauri::command]
+async fn upload_encrypted_image(
+    file_data: Vec<u8>,
+    filename: String,
+    ttl_secs: Option<u64>,
+    source: Option<UploadSource>,
+    state: State<'_, AppState>,
+) -> Result<EncryptedUploadResult, String> {
+    println!("[Backend] Encrypted upload request: filename={}, size={} bytes", filename, file_data.len());
+
+    let failed = |error: String| EncryptedUploadResult {
+        success: false,
+        share_url: None,
+        object_url: None,
+        view_url: None,
+        error: Some(error),
+        record: None,
+    };
+
+    if state.is_locked() {
+        return Ok(failed(LOCKED_ERROR.to_string()));
+    }
+
+    let uploader = match state.uploader.uploader().await {
+        Ok(uploader) => uploader,
+        Err(e) => {
+            println!("[Backend] Uploader unavailable, returning error: {}", e);
+            return Ok(failed(e));
         }
     };
-    
-    // Save to database
-    println!("[Backend] Saving upload record to database...");
-    let record = UploadRecord {
-        id: Uuid::new_v4().to_string(),
-        original_filename: filename.clone(),
-        file_hash,
-        file_size: file_data.len() as u64,
-        url: url.clone(),
-        upload_time: Database::get_current_timestamp(),
+
+    let content_type = mime_guess::from_path(&filename)
+        .first_or_octet_stream()
+        .to_string();
+
+    let options = UploadOptions {
+        expires_at: upload_service::resolve_expiry(&uploader, ttl_secs),
+        source: source.unwrap_or_default(),
+        client: Some(upload_service::app_client()),
+        original_hash: None,
+        tags: Vec::new(),
+        alt_text: None,
+        caption: None,
     };
-    
-    {
-        let db_guard = state.db.lock().unwrap();
-        if let Some(ref db) = *db_guard {
-            match db.insert_upload_record(&record) {
-                Ok(_) => println!("[Backend] Upload record saved successfully"),
-                Err(e) => println!("[Backend] Failed to save upload record: {}", e),
-            }
+
+    match upload_service::upload_encrypted(&uploader, &state.db, file_data, &filename, &content_type, &options).await {
+        Ok(outcome) => {
+            println!("[Backend] Encrypted upload completed: {}", outcome.object_url);
+            let view_url = {
+                let proxy = state.proxy.lock().unwrap();
+                proxy
+                    .issue_view_token()
+                    .map(|token| client_encryption::view_url(proxy.port(), &token, &outcome.share_url))
+            };
+            Ok(EncryptedUploadResult {
+                success: true,
+                share_url: Some(outcome.share_url),
+                object_url: Some(outcome.object_url),
+                view_url,
+                error: None,
+                record: Some(outcome.record),
+            })
+        }
+        Err(e) => {
+            println!("[Backend] Encrypted upload failed: {}", e);
+            Ok(failed(e.to_string()))
         }
     }
-    
-    println!("[Backend] Upload completed successfully: {}", url);
-    Ok(UploadResult {
-        success: true,
-        url: Some(url),
-        error: None,
-        from_cache: false,
+}
+
+#[tauri::command]
+async fn create_presigned_upload(
+    request: PresignRequest,
+    state: State<'_, AppState>,
+) -> Result<PresignedUpload, String> {
+    println!("[Backend] Presign request: key={:?}, content_type={}", request.key, request.content_type);
+
+    let uploader = state.uploader.uploader().await?;
+
+    uploader.presign_upload(&request).await.map_err(|e| {
+        println!("[Backend] Failed to presign upload: {}", e);
+        e.to_string()
     })
 }
 
+#[tauri::command]
+async fn list_bucket_objects(
+    request: Option<BucketListRequest>,
+    state: State<'_, AppState>,
+) -> Result<BucketListing, String> {
+    let request = request.unwrap_or_default();
+    println!("[Backend] Listing bucket objects: prefix={:?}", request.prefix);
+
+    let uploader = state.uploader.uploader().await?;
+
+    uploader.list_objects(&request).await.map_err(|e| {
+        println!("[Backend] Failed to list bucket objects: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+async fn get_remote_object(
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<RemoteObject, String> {
+    let uploader = state.uploader.uploader().await?;
+
+    uploader.head_object(&key).await.map_err(|e| {
+        println!("[Backend] Failed to fetch object metadata: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+async fn sync_history_with_bucket(
+    options: Option<SyncOptions>,
+    state: State<'_, AppState>,
+) -> Result<SyncReport, String> {
+    let options = options.unwrap_or_default();
+    println!(
+        "[Backend] Syncing history with bucket: import_untracked={}, dry_run={}",
+        options.import_untracked, options.dry_run
+    );
+
+    let uploader = state.uploader.uploader().await?;
+
+    history_sync::reconcile_history(&uploader, &state.db, &options)
+        .await
+        .map_err(|e| {
+            println!("[Backend] History sync failed: {}", e);
+            e.to_string()
+        })
+}
+
+/// Cached usage stats of the configured bucket, if they were ever computed.
+#[tauri::command]
+async fn get_bucket_stats(
+    state: State<'_, AppState>,
+) -> Result<Option<BucketStats>, String> {
+    let config = match state.config_manager.lock().unwrap().load_config().map_err(|e| e.to_string())? {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+
+    let db_guard = state.db.lock().unwrap();
+    match db_guard.as_ref() {
+        Some(db) => db
+            .get_bucket_stats(&config.profile_key())
+            .map_err(|e| e.to_string()),
+        None => Err("Database not available".to_string()),
+    }
+}
+
+/// Recomputes the bucket's usage stats by listing every object.
+#[tauri::command]
+async fn refresh_bucket_stats(
+    state: State<'_, AppState>,
+) -> Result<BucketStats, String> {
+    println!("[Backend] Refreshing bucket stats...");
+    let uploader = state.uploader.uploader().await?;
+
+    let stats = bucket_stats::compute_stats(&uploader).await.map_err(|e| {
+        println!("[Backend] Failed to compute bucket stats: {}", e);
+        e.to_string()
+    })?;
+
+    let db_guard = state.db.lock().unwrap();
+    if let Some(ref db) = *db_guard {
+        if let Err(e) = db.save_bucket_stats(&stats) {
+            println!("[Backend] Failed to cache bucket stats: {}", e);
+        }
+    }
+    Ok(stats)
+}
+
+/// Operation counts and estimated cost of the configured profile for
+/// `month` (`YYYY-MM`, defaults to the current month).
+#[tauri::command]
+async fn get_operation_usage(
+    month: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<OperationUsage, String> {
+    let config = state
+        .config_manager
+        .lock()
+        .unwrap()
+        .load_config()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "R2 configuration not set".to_string())?;
+    let month = month.unwrap_or_else(|| bucket_stats::month_of(Database::get_current_timestamp()));
+
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    // Include requests that haven't been written out yet
+    if let Err(e) = state.meter.flush(db) {
+        println!("[Backend] Failed to save operation counts: {}", e);
+    }
+
+    operation_meter::monthly_usage(db, &config.profile_key(), &month, &config.billing)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_uploader_status(
+    state: State<'_, AppState>,
+) -> Result<UploaderStatus, String> {
+    Ok(state.uploader.status())
+}
+
+#[tauri::command]
+async fn purge_expired_uploads(
+    state: State<'_, AppState>,
+) -> Result<ExpiryReport, String> {
+    println!("[Backend] Purging expired uploads...");
+    let uploader = state.uploader.uploader().await?;
+
+    expiry_scheduler::purge_expired(&uploader, &state.db)
+        .await
+        .map_err(|e| {
+            println!("[Backend] Failed to purge expired uploads: {}", e);
+            e.to_string()
+        })
+}
+
+#[tauri::command]
+async fn apply_expiry_lifecycle_rule(
+    days: Option<i32>,
+    state: State<'_, AppState>,
+) -> Result<i32, String> {
+    let uploader = state.uploader.uploader().await?;
+
+    // Default to the profile's TTL, rounded up to whole days
+    let days = match days {
+        Some(days) => days,
+        None => {
+            let ttl = uploader
+                .config()
+                .expiry
+                .default_ttl_secs
+                .filter(|ttl| *ttl > 0)
+                .ok_or_else(|| "No default TTL configured; pass the number of days".to_string())?;
+            ttl.div_ceil(24 * 60 * 60).min(i32::MAX as u64) as i32
+        }
+    };
+    if days < 1 {
+        return Err("Lifecycle expiration must be at least 1 day".to_string());
+    }
+
+    uploader.apply_expiry_lifecycle_rule(days).await.map_err(|e| {
+        println!("[Backend] Failed to apply lifecycle rule: {}", e);
+        e.to_string()
+    })?;
+    Ok(days)
+}
+
+#[tauri::command]
+async fn get_bucket_cors(
+    state: State<'_, AppState>,
+) -> Result<Vec<CorsRuleConfig>, String> {
+    let uploader = state.uploader.uploader().await?;
+    uploader.get_cors_rules().await.map_err(|e| {
+        println!("[Backend] Failed to get CORS rules: {}", e);
+        e.to_string()
+    })
+}
+
+/// Replaces the bucket's CORS rules; an empty list removes them.
+#[tauri::command]
+async fn set_bucket_cors(
+    rules: Vec<CorsRuleConfig>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let uploader = state.uploader.uploader().await?;
+    uploader.set_cors_rules(&rules).await.map_err(|e| {
+        println!("[Backend] Failed to set CORS rules: {}", e);
+        e.to_string()
+    })
+}
+
+/// Replaces the bucket's CORS rules with a preset and returns what was applied.
+#[tauri::command]
+async fn apply_cors_preset(
+    preset: CorsPreset,
+    origins: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CorsRuleConfig>, String> {
+    println!("[Backend] Applying CORS preset: {:?}", preset);
+    let rules = bucket_rules::cors_preset(preset, origins);
+    set_bucket_cors(rules.clone(), state).await?;
+    Ok(rules)
+}
+
+#[tauri::command]
+async fn get_lifecycle_rules(
+    state: State<'_, AppState>,
+) -> Result<Vec<LifecycleRuleConfig>, String> {
+    let uploader = state.uploader.uploader().await?;
+    uploader.get_lifecycle_rules().await.map_err(|e| {
+        println!("[Backend] Failed to get lifecycle rules: {}", e);
+        e.to_string()
+    })
+}
+
+/// Adds a lifecycle rule, replacing any rule with the same ID.
+#[tauri::command]
+async fn put_lifecycle_rule(
+    rule: LifecycleRuleConfig,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    println!("[Backend] Saving lifecycle rule: {}", rule.id);
+    let uploader = state.uploader.uploader().await?;
+    uploader.put_lifecycle_rule(&rule).await.map_err(|e| {
+        println!("[Backend] Failed to save lifecycle rule: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+async fn delete_lifecycle_rule(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    println!("[Backend] Deleting lifecycle rule: {}", id);
+    let uploader = state.uploader.uploader().await?;
+    uploader.delete_lifecycle_rule(&id).await.map_err(|e| {
+        println!("[Backend] Failed to delete lifecycle rule: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+async fn apply_lifecycle_preset(
+    preset: LifecyclePreset,
+    state: State<'_, AppState>,
+) -> Result<LifecycleRuleConfig, String> {
+    println!("[Backend] Applying lifecycle preset: {:?}", preset);
+    let rule = bucket_rules::lifecycle_preset(preset);
+    put_lifecycle_rule(rule.clone(), state).await?;
+    Ok(rule)
+}
+
 #[tauri::command]
 fn get_api_proxy_status(
     state: State<'_, AppState>,
@@ -189,6 +678,7 @@ fn get_api_proxy_status(
         enabled: settings.api_proxy_enabled,
         running: proxy.is_running(),
         port: proxy.port(),
+        token: proxy.token().map(str::to_string),
     })
 }
 
@@ -198,8 +688,13 @@ fn set_api_proxy_enabled(
     port: Option<u16>,
     state: State<'_, AppState>,
 ) -> Result<ApiProxyStatus, String> {
+    if enabled && state.is_locked() {
+        return Err(LOCKED_ERROR.to_string());
+    }
+
     let uploader = state.uploader.clone();
     let db = state.db.clone();
+    let shared_settings = state.settings.clone();
     let mut settings = state.settings.lock().unwrap();
     let mut proxy = state.proxy.lock().unwrap();
 
@@ -214,7 +709,7 @@ fn set_api_proxy_enabled(
             proxy.set_port(port);
             settings.api_proxy_port = port;
         }
-        tauri::async_runtime::block_on(proxy.start(uploader, db))?;
+        tauri::async_runtime::block_on(proxy.start(uploader, db, shared_settings))?;
         settings.api_proxy_enabled = true;
     } else {
         proxy.stop()?;
@@ -231,18 +726,270 @@ fn set_api_proxy_enabled(
         enabled: settings.api_proxy_enabled,
         running: proxy.is_running(),
         port: proxy.port(),
+        token: proxy.token().map(str::to_string),
     })
 }
 
+#[tauri::command]
+fn get_app_settings(
+    state: State<'_, AppState>,
+) -> Result<AppSettings, String> {
+    Ok(state.settings.lock().unwrap().clone())
+}
+
+#[tauri::command]
+fn set_cache_verification(
+    verify_with_head: bool,
+    state: State<'_, AppState>,
+) -> Result<AppSettings, String> {
+    println!("[Backend] Setting cache verification: verify_with_head={}", verify_with_head);
+    let mut settings = state.settings.lock().unwrap();
+    settings.verify_cache_with_head = verify_with_head;
+
+    let config_manager = state.config_manager.lock().unwrap();
+    config_manager
+        .save_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    Ok(settings.clone())
+}
+
+#[tauri::command]
+fn get_security_status(
+    state: State<'_, AppState>,
+) -> Result<SecurityStatus, String> {
+    Ok(state.security_status())
+}
+
+#[tauri::command]
+fn unlock_app(
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<SecurityStatus, String> {
+    println!("[Backend] Unlocking app...");
+    if !state.is_locked() {
+        return Ok(state.security_status());
+    }
+
+    let master_key = state.vault.unlock(&passphrase).map_err(|e| {
+        println!("[Backend] Failed to unlock: {}", e);
+        e.to_string()
+    })?;
+    state.config_manager.lock().unwrap().set_master_key(Some(master_key));
+
+    if let Err(e) = activate_backend(&state) {
+        state.config_manager.lock().unwrap().set_master_key(None);
+        return Err(e);
+    }
+
+    println!("[Backend] App unlocked");
+    Ok(state.security_status())
+}
+
+#[tauri::command]
+fn lock_app(
+    state: State<'_, AppState>,
+) -> Result<SecurityStatus, String> {
+    if !state.vault.is_enabled() {
+        return Err("Encryption at rest is not enabled".to_string());
+    }
+
+    println!("[Backend] Locking app...");
+    deactivate_backend(&state)?;
+    state.config_manager.lock().unwrap().set_master_key(None);
+    Ok(state.security_status())
+}
+
+#[tauri::command]
+fn enable_encryption(
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<SecurityStatus, String> {
+    println!("[Backend] Enabling encryption at rest...");
+    if state.vault.is_enabled() {
+        return Err("Encryption at rest is already enabled".to_string());
+    }
+
+    // Read the plaintext config before any key is set
+    let config = state
+        .config_manager
+        .lock()
+        .unwrap()
+        .load_config()
+        .map_err(|e| e.to_string())?;
+
+    let master_key = state.vault.create(&passphrase).map_err(|e| e.to_string())?;
+    if let Err(e) = reencrypt_storage(&state, config.as_ref(), None, Some(master_key)) {
+        let _ = state.vault.remove();
+        return Err(e);
+    }
+    activate_backend(&state)?;
+
+    println!("[Backend] Encryption at rest enabled");
+    Ok(state.security_status())
+}
+
+#[tauri::command]
+fn change_master_passphrase(
+    current_passphrase: String,
+    new_passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<SecurityStatus, String> {
+    println!("[Backend] Changing master passphrase...");
+    let current_key = state.vault.unlock(&current_passphrase).map_err(|e| e.to_string())?;
+    state.config_manager.lock().unwrap().set_master_key(Some(current_key.clone()));
+    let config = state
+        .config_manager
+        .lock()
+        .unwrap()
+        .load_config()
+        .map_err(|e| e.to_string())?;
+
+    let previous_vault = state.vault.snapshot().map_err(|e| e.to_string())?;
+    let new_key = state.vault.create(&new_passphrase).map_err(|e| e.to_string())?;
+    if let Err(e) = reencrypt_storage(&state, config.as_ref(), Some(current_key), Some(new_key)) {
+        let _ = state.vault.restore(&previous_vault);
+        return Err(e);
+    }
+    activate_backend(&state)?;
+
+    println!("[Backend] Master passphrase changed");
+    Ok(state.security_status())
+}
+
+#[tauri::command]
+fn disable_encryption(
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<SecurityStatus, String> {
+    println!("[Backend] Disabling encryption at rest...");
+    if !state.vault.is_enabled() {
+        return Ok(state.security_status());
+    }
+
+    let master_key = state.vault.unlock(&passphrase).map_err(|e| e.to_string())?;
+    state.config_manager.lock().unwrap().set_master_key(Some(master_key.clone()));
+    let config = state
+        .config_manager
+        .lock()
+        .unwrap()
+        .load_config()
+        .map_err(|e| e.to_string())?;
+
+    reencrypt_storage(&state, config.as_ref(), Some(master_key.clone()), None)?;
+    if let Err(e) = state.vault.remove() {
+        // With the vault still there the next unlock expects encrypted storage
+        println!("[Backend] Failed to remove vault: {}", e);
+        reencrypt_storage(&state, config.as_ref(), None, Some(master_key))?;
+        activate_backend(&state)?;
+        return Err(e.to_string());
+    }
+    activate_backend(&state)?;
+
+    println!("[Backend] Encryption at rest disabled");
+    Ok(state.security_status())
+}
+
+/// Rewrites the config secrets, the compression files and then the history
+/// database under `to` (plaintext when `None`). The database goes last
+/// because it's the one step that can't be redone without the old key. On
+/// failure the rest is put back under `from` (see `rekey_stores`) and the
+/// backend is brought back up; on success the caller reactivates it once the
+/// vault matches `to`.
+fn reencrypt_storage(
+    state: &AppState,
+    config: Option<&R2Config>,
+    from: Option<vault::MasterKey>,
+    to: Option<vault::MasterKey>,
+) -> Result<(), String> {
+    deactivate_backend(state)?;
+
+    if let Err(e) = rekey_stores(
+        &state.config_manager,
+        &state.compressions,
+        &state.db_path,
+        config,
+        from.clone(),
+        to.clone(),
+    ) {
+        let _ = activate_backend(state);
+        return Err(e);
+    }
+
+    Database::rekey_backups(&state.db_path, from.as_ref(), to.as_ref());
+    Ok(())
+}
+
+/// The steps of `reencrypt_storage` with the backend down. If the database
+/// can't be rekeyed the config and compression files are put back under
+/// `from`; the error names any store that could not be, since those are left
+/// on a different key than the database.
+fn rekey_stores(
+    config_manager: &Mutex<ConfigManager>,
+    compressions: &CompressionStore,
+    db_path: &Path,
+    config: Option<&R2Config>,
+    from: Option<vault::MasterKey>,
+    to: Option<vault::MasterKey>,
+) -> Result<(), String> {
+    let result = {
+        let mut config_manager = config_manager.lock().unwrap();
+        config_manager.set_master_key(to.clone());
+        match config {
+            Some(config) => config_manager.save_config(config),
+            None => Ok(()),
+        }
+    }
+    .and_then(|()| compressions.rekey(from.as_ref(), to.as_ref()))
+    .and_then(|_| Database::rekey_file(db_path, from.as_ref(), to.as_ref()));
+
+    let Err(e) = result else {
+        return Ok(());
+    };
+    println!("[Backend] Failed to re-encrypt storage: {}", e);
+
+    // The database is still under `from`; put the rest back to match
+    let mut stranded = Vec::new();
+    if let Err(e) = compressions.rekey(to.as_ref(), from.as_ref()) {
+        println!("[Backend] Failed to restore compression files: {}", e);
+        stranded.push(format!("some compression files are still on the new key ({})", e));
+    }
+    {
+        let mut config_manager = config_manager.lock().unwrap();
+        config_manager.set_master_key(from);
+        if let Some(config) = config {
+            if let Err(e) = config_manager.save_config(config) {
+                println!("[Backend] Failed to restore config: {}", e);
+                stranded.push(format!("the saved config is still on the new key ({})", e));
+            }
+        }
+    }
+
+    if stranded.is_empty() {
+        return Err(e.to_string());
+    }
+    Err(format!(
+        "{}. The history database is still on the previous key, but {}",
+        e,
+        stranded.join(" and ")
+    ))
+}
+
+/// The newest `limit` records, or the whole history without a limit.
 #[tauri::command]
 async fn get_upload_history(
+    limit: Option<usize>,
     state: State<'_, AppState>,
 ) -> Result<Vec<UploadRecord>, String> {
     println!("[Backend] Getting upload history...");
     let db_guard = state.db.lock().unwrap();
     match db_guard.as_ref() {
         Some(db) => {
-            let result = db.get_recent_uploads(100).map_err(|e| {
+            let result = match limit {
+                Some(limit) => db.get_recent_uploads(limit),
+                None => db.get_all_uploads(),
+            }
+            .map_err(|e| {
                 println!("[Backend] Failed to get upload history: {}", e);
                 e.to_string()
             });
@@ -258,6 +1005,353 @@ async fn get_upload_history(
     }
 }
 
+/// One page of the history, optionally narrowed by a full-text query and
+/// filters.
+#[tauri::command]
+async fn get_upload_history_page(
+    request: Option<HistoryPageRequest>,
+    state: State<'_, AppState>,
+) -> Result<HistoryPage, String> {
+    let request = request.unwrap_or_default();
+    let limit = request.limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE).clamp(1, MAX_HISTORY_PAGE_SIZE);
+
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    let (records, next_cursor) = db
+        .search_uploads(&request.filter, request.sort, request.cursor.as_deref(), limit)
+        .map_err(|e| {
+            println!("[Backend] Failed to get upload history page: {}", e);
+            e.to_string()
+        })?;
+    let total = db.count_matching_uploads(&request.filter).map_err(|e| e.to_string())?;
+
+    Ok(HistoryPage { records, next_cursor, total })
+}
+
+/// Every upload of the file with `file_hash`, one per profile it was
+/// uploaded to.
+#[tauri::command]
+async fn get_content_uploads(file_hash: String, state: State<'_, AppState>) -> Result<Vec<UploadRecord>, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.find_uploads_by_hash(&file_hash).map_err(|e| {
+        println!("[Backend] Failed to get uploads of {}: {}", file_hash, e);
+        e.to_string()
+    })
+}
+
+/// Saves the retention policy and prunes the history with it right away.
+/// Returns the number of records removed.
+#[tauri::command]
+fn set_history_retention(
+    policy: RetentionPolicy,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    println!("[Backend] Setting history retention: {:?}", policy);
+    {
+        let mut settings = state.settings.lock().unwrap();
+        settings.history_retention = policy;
+        state
+            .config_manager
+            .lock()
+            .unwrap()
+            .save_settings(&settings)
+            .map_err(|e| e.to_string())?;
+    }
+    prune_upload_history(state)
+}
+
+/// Applies the retention policy; returns the number of records removed.
+#[tauri::command]
+fn prune_upload_history(
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let policy = state.settings.lock().unwrap().history_retention.clone();
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    let pruned = db
+        .prune_history(&policy, Database::get_current_timestamp())
+        .map_err(|e| {
+            println!("[Backend] Failed to prune history: {}", e);
+            e.to_string()
+        })?;
+    println!("[Backend] Pruned {} history records", pruned);
+    Ok(pruned)
+}
+
+/// Exports the records matching `filter`, or the whole history, as JSON, CSV
+/// or a Markdown gallery.
+#[tauri::command]
+fn export_upload_history(
+    format: HistoryExportFormat,
+    filter: Option<HistoryFilter>,
+    state: State<'_, AppState>,
+) -> Result<HistoryExport, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    history_transfer::export(db, &filter.unwrap_or_default(), format).map_err(|e| {
+        println!("[Backend] Failed to export history: {}", e);
+        e.to_string()
+    })
+}
+
+/// Merges a JSON or CSV history export into the history.
+#[tauri::command]
+fn import_upload_history(
+    content: String,
+    format: HistoryExportFormat,
+    state: State<'_, AppState>,
+) -> Result<HistoryImportReport, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    history_transfer::import(db, &content, format).map_err(|e| {
+        println!("[Backend] Failed to import history: {}", e);
+        e.to_string()
+    })
+}
+
+/// Imports the gallery of PicGo, PicList or uPic, skipping links already in
+/// the history.
+#[tauri::command]
+async fn import_external_history(
+    app: ExternalHistoryApp,
+    content: String,
+    state: State<'_, AppState>,
+) -> Result<HistoryImportReport, String> {
+    // Hashing local files may take a while, so it happens before locking.
+    let (records, errors) = tauri::async_runtime::spawn_blocking(move || external_history::parse(app, &content))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let mut report = HistoryImportReport {
+        errors,
+        ..Default::default()
+    };
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    history_transfer::import_records(db, records, &mut report).map_err(|e| {
+        println!("[Backend] Failed to import {} history: {}", app.name(), e);
+        e.to_string()
+    })?;
+    println!(
+        "[Backend] Imported {} history: {} added, {} already present",
+        app.name(),
+        report.added,
+        report.merged + report.unchanged
+    );
+    Ok(report)
+}
+
+/// Adds a compression to the compression history, keeping both files.
+#[tauri::command]
+async fn save_compression(
+    request: SaveCompressionRequest,
+    original_data: Option<Vec<u8>>,
+    compressed_data: Vec<u8>,
+    state: State<'_, AppState>,
+) -> Result<CompressionRecord, String> {
+    let (store, db) = (state.compressions.clone(), state.db.clone());
+    let master_key = state.config_manager.lock().unwrap().master_key().cloned();
+    tauri::async_runtime::spawn_blocking(move || {
+        store.save(&db, master_key.as_ref(), request, original_data, compressed_data)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| {
+        println!("[Backend] Failed to save compression: {}", e);
+        e.to_string()
+    })
+}
+
+/// The compression history, newest first.
+#[tauri::command]
+async fn list_compressions(state: State<'_, AppState>) -> Result<Vec<CompressionRecord>, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.list_compressions().map_err(|e| e.to_string())
+}
+
+/// A compression with its files, to preview or restore it in the compressor.
+#[tauri::command]
+async fn restore_compression(id: String, state: State<'_, AppState>) -> Result<CompressionFiles, String> {
+    let (store, db) = (state.compressions.clone(), state.db.clone());
+    let master_key = state.config_manager.lock().unwrap().master_key().cloned();
+    tauri::async_runtime::spawn_blocking(move || {
+        store.load(&db, master_key.as_ref(), &id).map_err(|e| {
+            println!("[Backend] Failed to restore compression {}: {}", id, e);
+            e.to_string()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn delete_compression(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let (store, db) = (state.compressions.clone(), state.db.clone());
+    tauri::async_runtime::spawn_blocking(move || {
+        store.delete(&db, &id).map_err(|e| {
+            println!("[Backend] Failed to delete compression {}: {}", id, e);
+            e.to_string()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Returns the number of compressions removed.
+#[tauri::command]
+async fn clear_compressions(state: State<'_, AppState>) -> Result<usize, String> {
+    let (store, db) = (state.compressions.clone(), state.db.clone());
+    tauri::async_runtime::spawn_blocking(move || store.clear(&db))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| {
+            println!("[Backend] Failed to clear compressions: {}", e);
+            e.to_string()
+        })
+}
+
+/// Every tag in use with the number of records carrying it.
+#[tauri::command]
+fn list_tags(state: State<'_, AppState>) -> Result<Vec<TagCount>, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.list_tags().map_err(|e| e.to_string())
+}
+
+/// Replaces the tags of a record; returns them as saved.
+#[tauri::command]
+fn set_upload_tags(id: String, tags: Vec<String>, state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.set_upload_tags(&id, &tags).map_err(|e| {
+        println!("[Backend] Failed to tag {}: {}", id, e);
+        e.to_string()
+    })
+}
+
+/// Renames a tag on every record; returns the number of records affected.
+#[tauri::command]
+fn rename_tag(from: String, to: String, state: State<'_, AppState>) -> Result<usize, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.rename_tag(&from, &to).map_err(|e| {
+        println!("[Backend] Failed to rename tag {}: {}", from, e);
+        e.to_string()
+    })
+}
+
+/// Removes a tag from every record; returns the number of records affected.
+#[tauri::command]
+fn delete_tag(tag: String, state: State<'_, AppState>) -> Result<usize, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.delete_tag(&tag).map_err(|e| e.to_string())
+}
+
+/// Sets or, with empty text, clears the notes of a record.
+#[tauri::command]
+fn set_upload_notes(id: String, notes: Option<String>, state: State<'_, AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.set_upload_notes(&id, notes.as_deref()).map_err(|e| {
+        println!("[Backend] Failed to save notes of {}: {}", id, e);
+        e.to_string()
+    })
+}
+
+/// Sets or, with empty text, clears the alt text of a record.
+#[tauri::command]
+fn set_upload_alt_text(id: String, alt_text: Option<String>, state: State<'_, AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.set_upload_alt_text(&id, alt_text.as_deref()).map_err(|e| {
+        println!("[Backend] Failed to save alt text of {}: {}", id, e);
+        e.to_string()
+    })
+}
+
+/// Sets or, with empty text, clears the caption of a record.
+#[tauri::command]
+fn set_upload_caption(id: String, caption: Option<String>, state: State<'_, AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.set_upload_caption(&id, caption.as_deref()).map_err(|e| {
+        println!("[Backend] Failed to save caption of {}: {}", id, e);
+        e.to_string()
+    })
+}
+
+/// Markdown and HTML embeds of a record, using its alt text and caption.
+#[tauri::command]
+fn get_upload_snippets(id: String, state: State<'_, AppState>) -> Result<UploadSnippets, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    let record = db
+        .get_upload(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Upload record not found".to_string())?;
+    Ok(snippets::snippets(&record))
+}
+
+#[tauri::command]
+fn list_albums(state: State<'_, AppState>) -> Result<Vec<Album>, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.list_albums().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn create_album(name: String, description: Option<String>, state: State<'_, AppState>) -> Result<Album, String> {
+    println!("[Backend] Creating album: {}", name);
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.create_album(&name, description.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Renames an album and replaces its description.
+#[tauri::command]
+fn update_album(
+    id: String,
+    name: String,
+    description: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Album, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.update_album(&id, &name, description.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Deletes an album; the uploads in it stay in the history.
+#[tauri::command]
+fn delete_album(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    println!("[Backend] Deleting album: {}", id);
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.delete_album(&id).map_err(|e| e.to_string())
+}
+
+/// Returns the number of uploads added.
+#[tauri::command]
+fn add_to_album(album_id: String, upload_ids: Vec<String>, state: State<'_, AppState>) -> Result<usize, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.add_to_album(&album_id, &upload_ids).map_err(|e| {
+        println!("[Backend] Failed to add uploads to album {}: {}", album_id, e);
+        e.to_string()
+    })
+}
+
+/// Returns the number of uploads removed.
+#[tauri::command]
+fn remove_from_album(album_id: String, upload_ids: Vec<String>, state: State<'_, AppState>) -> Result<usize, String> {
+    let db_guard = state.db.lock().unwrap();
+    let db = db_guard.as_ref().ok_or_else(|| "Database not available".to_string())?;
+    db.remove_from_album(&album_id, &upload_ids).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn get_clipboard_image() -> Result<Option<Vec<u8>>, String> {
     println!("[Backend] Getting clipboard image...");
@@ -411,40 +1505,33 @@ pub fn run() {
             std::fs::create_dir_all(&app_dir).expect("failed to create app data dir");
             
             let config_manager = ConfigManager::new(app_dir.clone());
-            let mut settings = config_manager
+            let settings = config_manager
                 .load_settings()
                 .unwrap_or_default();
-            let db_path = app_dir.join("uploads.db");
-            let db = Database::new(db_path).expect("failed to initialize database");
-            
-            // Try to load existing config and initialize uploader
-            let uploader = if let Ok(Some(config)) = config_manager.load_config() {
-                tauri::async_runtime::block_on(async {
-                    R2Uploader::new(config).await.ok()
-                })
-            } else {
-                None
-            };
-            
-            let db = Arc::new(Mutex::new(Some(db)));
-            let uploader = Arc::new(Mutex::new(uploader));
-            let mut proxy = ProxyServer::new(settings.api_proxy_port);
-            if settings.api_proxy_enabled {
-                if let Err(err) = tauri::async_runtime::block_on(proxy.start(uploader.clone(), db.clone())) {
-                    println!("[Backend] Failed to start API proxy: {}", err);
-                    settings.api_proxy_enabled = false;
-                    let _ = config_manager.save_settings(&settings);
-                }
-            }
+            let proxy = ProxyServer::new(settings.api_proxy_port);
 
+            let meter = OperationMeter::new();
             let state = AppState {
-                db,
+                db: Arc::new(Mutex::new(None)),
+                db_path: app_dir.join("uploads.db"),
                 config_manager: Mutex::new(config_manager),
-                uploader,
+                uploader: UploaderManager::new(meter.clone()),
+                meter,
                 proxy: Mutex::new(proxy),
-                settings: Mutex::new(settings),
+                settings: Arc::new(Mutex::new(settings)),
+                vault: Vault::new(app_dir.clone()),
+                compressions: CompressionStore::new(app_dir.join("compressions")),
             };
+
+            // With encryption at rest, everything stays down until unlock_app
+            if state.is_locked() {
+                println!("[Backend] Encryption at rest enabled, waiting for unlock");
+            } else {
+                activate_backend(&state).expect("failed to initialize database");
+            }
             
+            expiry_scheduler::spawn(state.uploader.clone(), state.db.clone());
+            operation_meter::spawn(state.meter.clone(), state.db.clone(), app.handle().clone());
             app.manage(state);
             
             Ok(())
@@ -452,13 +1539,115 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             save_r2_config,
             get_r2_config,
+            generate_sse_customer_key,
+            test_storage_connection,
             upload_image,
+            upload_encrypted_image,
+            create_presigned_upload,
+            list_bucket_objects,
+            get_remote_object,
+            sync_history_with_bucket,
+            get_bucket_stats,
+            refresh_bucket_stats,
+            get_operation_usage,
             get_upload_history,
+            get_upload_history_page,
+            get_content_uploads,
+            set_history_retention,
+            prune_upload_history,
+            export_upload_history,
+            import_upload_history,
+            import_external_history,
+            save_compression,
+            list_compressions,
+            restore_compression,
+            delete_compression,
+            clear_compressions,
+            list_tags,
+            set_upload_tags,
+            rename_tag,
+            delete_tag,
+            set_upload_notes,
+            set_upload_alt_text,
+            set_upload_caption,
+            get_upload_snippets,
+            list_albums,
+            create_album,
+            update_album,
+            delete_album,
+            add_to_album,
+            remove_from_album,
+            get_uploader_status,
+            purge_expired_uploads,
+            apply_expiry_lifecycle_rule,
+            get_bucket_cors,
+            set_bucket_cors,
+            apply_cors_preset,
+            get_lifecycle_rules,
+            put_lifecycle_rule,
+            delete_lifecycle_rule,
+            apply_lifecycle_preset,
             get_api_proxy_status,
             set_api_proxy_enabled,
+            get_app_settings,
+            set_cache_verification,
+            get_security_status,
+            unlock_app,
+            lock_app,
+            enable_encryption,
+            change_master_passphrase,
+            disable_encryption,
             get_clipboard_image,
             read_file_from_path
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                flush_operation_counts(&app.state::<AppState>());
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_database_rekey_puts_the_other_stores_back() {
+        let dir = std::env::temp_dir().join(format!("moli-reencrypt-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let master_key = Vault::new(dir.join("vault")).create("passphrase").unwrap();
+        let config_manager = Mutex::new(ConfigManager::new(dir.clone()));
+        let compressions = CompressionStore::new(dir.join("compressions"));
+        let db = Arc::new(Mutex::new(Some(Database::new(PathBuf::from(":memory:"), None).unwrap())));
+        let request = serde_json::from_value(serde_json::json!({
+            "original_name": "photo.png",
+            "compression_ratio": 50.0,
+            "config": {},
+        }))
+        .unwrap();
+        let record = compressions.save(&db, None, request, None, b"compressed".to_vec()).unwrap();
+        let config: R2Config = serde_json::from_value(serde_json::json!({
+            "access_key_id": "AKIDEXAMPLE",
+            "secret_access_key": "plain secret",
+            "endpoint": "https://account.r2.cloudflarestorage.com",
+            "bucket_name": "images",
+            "public_url_base": "https://img.example.com",
+        }))
+        .unwrap();
+        // Not a database, so rekeying it fails after the other stores moved.
+        let db_path = dir.join("history.db");
+        std::fs::write(&db_path, b"not a database").unwrap();
+
+        let err = rekey_stores(&config_manager, &compressions, &db_path, Some(&config), None, Some(master_key));
+        assert!(err.is_err());
+
+        let saved = std::fs::read_to_string(dir.join("config.json")).unwrap();
+        assert!(saved.contains("plain secret"));
+        let files = compressions.load(&db, None, &record.id).unwrap();
+        assert_eq!(files.compressed_data, b"compressed");
+        assert_eq!(std::fs::read(&db_path).unwrap(), b"not a database");
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
@@ -0,0 +1,566 @@
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A forward migration, run inside a transaction. Migration `i` in
+/// `MIGRATIONS` brings the schema to version `i + 1`.
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Append only: released migrations must never change, since databases that
+/// already ran them won't run them again.
+const MIGRATIONS: &[Migration] = &[
+    baseline,
+    upload_metadata,
+    per_profile_uploads,
+    tags_albums_notes,
+    alt_text_and_captions,
+    url_index,
+    compressions,
+];
+
+/// Schema version written by this build of the app.
+pub const LATEST_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// Brings the database to `LATEST_VERSION`, copying the file next to itself
+/// first if an existing schema is about to change. Refuses to touch databases
+/// written by a newer version of the app.
+pub fn migrate(conn: &Connection, db_path: &Path) -> Result<()> {
+    let version = schema_version(conn)?;
+    if version > LATEST_VERSION {
+        return Err(anyhow::anyhow!(
+            "The history database was created by a newer version of moli-tutu (schema version {}, \
+             this version supports up to {}). Update the app to open it.",
+            version,
+            LATEST_VERSION
+        ));
+    }
+    if version == LATEST_VERSION {
+        return Ok(());
+    }
+
+    let has_tables: bool = conn.query_row("SELECT count(*) > 0 FROM sqlite_master", [], |row| row.get(0))?;
+    if has_tables {
+        let backup_path = backup_path(db_path, version);
+        fs::copy(db_path, &backup_path)
+            .map_err(|e| anyhow::anyhow!("Failed to back up history database before migrating: {}", e))?;
+        println!("[Database] Backed up schema version {} to {:?}", version, backup_path);
+        // Only the backup from before the latest migration is kept
+        for old_backup in backups(db_path) {
+            if old_backup != backup_path {
+                if let Err(e) = fs::remove_file(&old_backup) {
+                    println!("[Database] Failed to remove old backup {:?}: {}", old_backup, e);
+                }
+            }
+        }
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let target = index as i64 + 1;
+        let tx = conn.unchecked_transaction()?;
+        migration(&tx).map_err(|e| anyhow::anyhow!("Failed to migrate history database to version {}: {}", target, e))?;
+        tx.pragma_update(None, "user_version", target)?;
+        tx.commit()?;
+        println!("[Database] Migrated history database to schema version {}", target);
+    }
+
+    Ok(())
+}
+
+pub fn schema_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// `uploads.db` -> `uploads.db.v3.bak`
+fn backup_path(db_path: &Path, version: i64) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(format!(".v{}.bak", version));
+    PathBuf::from(path)
+}
+
+/// Backups made by `migrate` next to the database.
+pub fn backups(db_path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (db_path.parent(), db_path.file_name().and_then(|name| name.to_str())) else {
+        return Vec::new();
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let prefix = format!("{}.v", name);
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| file_name.strip_prefix(&prefix))
+                .and_then(|rest| rest.strip_suffix(".bak"))
+                .is_some_and(|version| !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .collect()
+}
+
+/// Version 1: the schema as it stood before it was versioned. Databases from
+/// those releases are at version 0 with any prefix of it applied, so every
+/// step here has to tolerate already being done.
+fn baseline(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS uploads (
+            id TEXT PRIMARY KEY,
+            original_filename TEXT NOT NULL,
+            file_hash TEXT NOT NULL UNIQUE,
+            file_size INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            upload_time INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_file_hash ON uploads(file_hash)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_upload_time ON uploads(upload_time)",
+        [],
+    )?;
+
+    ensure_column(conn, "uploads", "status", "TEXT NOT NULL DEFAULT 'active'")?;
+    ensure_column(conn, "uploads", "expires_at", "INTEGER")?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_expires_at ON uploads(expires_at)",
+        [],
+    )?;
+
+    ensure_column(conn, "uploads", "content_type", "TEXT")?;
+    ensure_column(conn, "uploads", "profile", "TEXT")?;
+    ensure_column(conn, "uploads", "source", "TEXT NOT NULL DEFAULT 'app'")?;
+    backfill_content_types(conn)?;
+    ensure_search_index(conn)?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS operation_counts (
+            profile TEXT NOT NULL,
+            month TEXT NOT NULL,
+            operation TEXT NOT NULL,
+            count INTEGER NOT NULL,
+            PRIMARY KEY (profile, month, operation)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS bucket_stats (
+            profile TEXT PRIMARY KEY,
+            stats TEXT NOT NULL,
+            refreshed_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Version 2: where each upload is stored and what it was. The bucket is
+/// recovered from the profile key (`endpoint/bucket`).
+fn upload_metadata(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE uploads ADD COLUMN bucket TEXT;
+        ALTER TABLE uploads ADD COLUMN object_key TEXT;
+        ALTER TABLE uploads ADD COLUMN width INTEGER;
+        ALTER TABLE uploads ADD COLUMN height INTEGER;
+        ALTER TABLE uploads ADD COLUMN client TEXT;
+        ALTER TABLE uploads ADD COLUMN original_hash TEXT;
+
+        UPDATE uploads SET bucket = substr(profile, length(rtrim(profile, replace(profile, '/', ''))) + 1)
+        WHERE profile IS NOT NULL;",
+    )?;
+    Ok(())
+}
+
+/// Version 3: one `contents` row per distinct file, linked by hash to an
+/// upload row per profile it was uploaded to. `file_hash` was `UNIQUE`, which
+/// SQLite can't drop in place, so `uploads` is rebuilt; its indexes and
+/// search triggers go with the old table and are recreated.
+fn per_profile_uploads(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE contents (
+            hash TEXT PRIMARY KEY,
+            file_size INTEGER NOT NULL,
+            content_type TEXT,
+            width INTEGER,
+            height INTEGER,
+            first_seen INTEGER NOT NULL
+        );
+
+        INSERT INTO contents (hash, file_size, content_type, width, height, first_seen)
+        SELECT file_hash, file_size, content_type, width, height, min(upload_time)
+        FROM uploads GROUP BY file_hash;
+
+        CREATE TABLE uploads_new (
+            id TEXT PRIMARY KEY,
+            original_filename TEXT NOT NULL,
+            file_hash TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            upload_time INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'active',
+            expires_at INTEGER,
+            content_type TEXT,
+            profile TEXT,
+            source TEXT NOT NULL DEFAULT 'app',
+            bucket TEXT,
+            object_key TEXT,
+            width INTEGER,
+            height INTEGER,
+            client TEXT,
+            original_hash TEXT
+        );
+
+        INSERT INTO uploads_new (id, original_filename, file_hash, file_size, url, upload_time, status,
+            expires_at, content_type, profile, source, bucket, object_key, width, height, client, original_hash)
+        SELECT id, original_filename, file_hash, file_size, url, upload_time, status,
+            expires_at, content_type, profile, source, bucket, object_key, width, height, client, original_hash
+        FROM uploads;
+
+        DROP TABLE uploads;
+        ALTER TABLE uploads_new RENAME TO uploads;
+
+        CREATE INDEX idx_file_hash ON uploads(file_hash);
+        CREATE INDEX idx_upload_time ON uploads(upload_time);
+        CREATE INDEX idx_expires_at ON uploads(expires_at);
+        -- Profiles are NULL on records from before they were stored; those
+        -- never collide, since NULLs are distinct here.
+        CREATE UNIQUE INDEX idx_uploads_hash_profile ON uploads(file_hash, profile);
+
+        CREATE TRIGGER uploads_fts_insert AFTER INSERT ON uploads BEGIN
+            DELETE FROM uploads_fts WHERE id = new.id;
+            INSERT INTO uploads_fts (id, original_filename, url, tags, notes)
+            VALUES (new.id, new.original_filename, new.url, '', '');
+        END;
+
+        CREATE TRIGGER uploads_fts_update AFTER UPDATE OF original_filename, url ON uploads BEGIN
+            UPDATE uploads_fts SET original_filename = new.original_filename, url = new.url
+            WHERE id = old.id;
+        END;
+
+        CREATE TRIGGER uploads_fts_delete AFTER DELETE ON uploads BEGIN
+            DELETE FROM uploads_fts WHERE id = old.id;
+        END;
+
+        -- A content row lives as long as any upload of it.
+        CREATE TRIGGER contents_release AFTER DELETE ON uploads
+        WHEN NOT EXISTS (SELECT 1 FROM uploads WHERE file_hash = old.file_hash) BEGIN
+            DELETE FROM contents WHERE hash = old.file_hash;
+        END;",
+    )?;
+    Ok(())
+}
+
+/// Version 4: free-form notes on uploads, tags, and albums grouping uploads.
+/// The search index picks up tags and notes through triggers, and links go
+/// away with the upload or album they belong to.
+fn tags_albums_notes(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE uploads ADD COLUMN notes TEXT;
+
+        CREATE TABLE upload_tags (
+            upload_id TEXT NOT NULL,
+            tag TEXT NOT NULL COLLATE NOCASE,
+            PRIMARY KEY (upload_id, tag)
+        );
+        CREATE INDEX idx_upload_tags_tag ON upload_tags(tag);
+
+        CREATE TABLE albums (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            description TEXT,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE album_uploads (
+            album_id TEXT NOT NULL,
+            upload_id TEXT NOT NULL,
+            added_at INTEGER NOT NULL,
+            PRIMARY KEY (album_id, upload_id)
+        );
+        CREATE INDEX idx_album_uploads_upload ON album_uploads(upload_id);
+
+        -- Re-saving a record (INSERT OR REPLACE) keeps its tags, so index them
+        -- from upload_tags rather than starting empty.
+        DROP TRIGGER uploads_fts_insert;
+        CREATE TRIGGER uploads_fts_insert AFTER INSERT ON uploads BEGIN
+            DELETE FROM uploads_fts WHERE id = new.id;
+            INSERT INTO uploads_fts (id, original_filename, url, tags, notes)
+            VALUES (
+                new.id,
+                new.original_filename,
+                new.url,
+                (SELECT coalesce(group_concat(tag, ' '), '') FROM upload_tags WHERE upload_id = new.id),
+                coalesce(new.notes, '')
+            );
+        END;
+
+        CREATE TRIGGER uploads_fts_notes AFTER UPDATE OF notes ON uploads BEGIN
+            UPDATE uploads_fts SET notes = coalesce(new.notes, '') WHERE id = old.id;
+        END;
+
+        CREATE TRIGGER upload_tags_fts_insert AFTER INSERT ON upload_tags BEGIN
+            UPDATE uploads_fts
+            SET tags = (SELECT group_concat(tag, ' ') FROM upload_tags WHERE upload_id = new.upload_id)
+            WHERE id = new.upload_id;
+        END;
+
+        CREATE TRIGGER upload_tags_fts_delete AFTER DELETE ON upload_tags BEGIN
+            UPDATE uploads_fts
+            SET tags = (SELECT coalesce(group_concat(tag, ' '), '') FROM upload_tags WHERE upload_id = old.upload_id)
+            WHERE id = old.upload_id;
+        END;
+
+        CREATE TRIGGER uploads_release_links AFTER DELETE ON uploads BEGIN
+            DELETE FROM upload_tags WHERE upload_id = old.id;
+            DELETE FROM album_uploads WHERE upload_id = old.id;
+        END;
+
+        CREATE TRIGGER albums_release AFTER DELETE ON albums BEGIN
+            DELETE FROM album_uploads WHERE album_id = old.id;
+        END;",
+    )?;
+    Ok(())
+}
+
+/// Version 5: alt text and caption used by the generated embed snippets.
+fn alt_text_and_captions(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE uploads ADD COLUMN alt_text TEXT;
+        ALTER TABLE uploads ADD COLUMN caption TEXT;",
+    )?;
+    Ok(())
+}
+
+/// Version 6: history imports match existing records by URL.
+fn url_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch("CREATE INDEX idx_uploads_url ON uploads(url);")?;
+    Ok(())
+}
+
+/// Version 7: compression history, moved out of the webview's storage. The
+/// files themselves live next to the database (see `CompressionStore`).
+fn compressions(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE compressions (
+            id TEXT PRIMARY KEY,
+            original_name TEXT NOT NULL,
+            original_size INTEGER NOT NULL,
+            compressed_size INTEGER NOT NULL,
+            compression_ratio REAL NOT NULL,
+            compressed_at INTEGER NOT NULL,
+            config TEXT NOT NULL,
+            source TEXT NOT NULL DEFAULT 'app',
+            content_type TEXT,
+            has_original INTEGER NOT NULL DEFAULT 0,
+            original_hash TEXT,
+            compressed_hash TEXT NOT NULL,
+            upload_id TEXT
+        );
+        CREATE INDEX idx_compressions_time ON compressions(compressed_at);
+        CREATE INDEX idx_compressions_hash ON compressions(compressed_hash);
+
+        CREATE TRIGGER uploads_release_compressions AFTER DELETE ON uploads BEGIN
+            UPDATE compressions SET upload_id = NULL WHERE upload_id = old.id;
+        END;",
+    )?;
+    Ok(())
+}
+
+/// Adds a column to databases created before it existed.
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Guesses the content type of records saved before it was stored.
+fn backfill_content_types(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT id, original_filename FROM uploads WHERE content_type IS NULL")?;
+    let missing = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, filename) in missing {
+        let content_type = mime_guess::from_path(&filename).first_or_octet_stream().to_string();
+        conn.execute(
+            "UPDATE uploads SET content_type = ?1 WHERE id = ?2",
+            params![content_type, id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Creates the FTS5 index over the history and the triggers keeping it
+/// in sync, indexing existing records the first time.
+fn ensure_search_index(conn: &Connection) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'uploads_fts'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS uploads_fts USING fts5(
+            id UNINDEXED,
+            original_filename,
+            url,
+            tags,
+            notes,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        -- INSERT OR REPLACE doesn't fire delete triggers, so clear any
+        -- previous entry here as well.
+        CREATE TRIGGER IF NOT EXISTS uploads_fts_insert AFTER INSERT ON uploads BEGIN
+            DELETE FROM uploads_fts WHERE id = new.id;
+            INSERT INTO uploads_fts (id, original_filename, url, tags, notes)
+            VALUES (new.id, new.original_filename, new.url, '', '');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS uploads_fts_update AFTER UPDATE OF original_filename, url ON uploads BEGIN
+            UPDATE uploads_fts SET original_filename = new.original_filename, url = new.url
+            WHERE id = old.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS uploads_fts_delete AFTER DELETE ON uploads BEGIN
+            DELETE FROM uploads_fts WHERE id = old.id;
+        END;",
+    )?;
+
+    if !exists {
+        conn.execute(
+            "INSERT INTO uploads_fts (id, original_filename, url, tags, notes)
+             SELECT id, original_filename, url, '', '' FROM uploads",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::types::HistoryFilter;
+
+    fn temp_db() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("moli-migrations-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("uploads.db")
+    }
+
+    /// A database left at `version` holding one upload, written with the
+    /// columns every schema has.
+    fn database_at(version: i64) -> PathBuf {
+        let db_path = temp_db();
+        let conn = Connection::open(&db_path).unwrap();
+        if version == 0 {
+            // The table as released before the schema was versioned.
+            conn.execute_batch(
+                "CREATE TABLE uploads (
+                    id TEXT PRIMARY KEY,
+                    original_filename TEXT NOT NULL,
+                    file_hash TEXT NOT NULL UNIQUE,
+                    file_size INTEGER NOT NULL,
+                    url TEXT NOT NULL,
+                    upload_time INTEGER NOT NULL
+                );",
+            )
+            .unwrap();
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().take(version as usize) {
+            migration(&conn).unwrap();
+            conn.pragma_update(None, "user_version", index as i64 + 1).unwrap();
+        }
+        conn.execute(
+            "INSERT INTO uploads (id, original_filename, file_hash, file_size, url, upload_time)
+             VALUES ('upload', 'sunset.png', 'hash', 100, 'https://img.example.com/sunset.png', 1)",
+            [],
+        )
+        .unwrap();
+        db_path
+    }
+
+    fn version_of(db_path: &Path) -> i64 {
+        schema_version(&Connection::open(db_path).unwrap()).unwrap()
+    }
+
+    fn assert_migrated(db_path: &Path, from: i64) {
+        {
+            let db = Database::new(db_path.to_path_buf(), None).unwrap();
+            let record = db.get_upload("upload").unwrap().expect("record survives the migration");
+            assert_eq!(record.original_filename, "sunset.png");
+            if from == 0 {
+                assert_eq!(record.content_type.as_deref(), Some("image/png"));
+            }
+
+            let filter = HistoryFilter {
+                text: Some("sunset".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(db.count_matching_uploads(&filter).unwrap(), 1, "migrated from version {}", from);
+        }
+        assert_eq!(version_of(db_path), LATEST_VERSION);
+        assert_eq!(backups(db_path), vec![backup_path(db_path, from)]);
+        let _ = fs::remove_dir_all(db_path.parent().unwrap());
+    }
+
+    #[test]
+    fn migrates_an_unversioned_database() {
+        let db_path = database_at(0);
+        assert_migrated(&db_path, 0);
+    }
+
+    #[test]
+    fn migrates_every_intermediate_version() {
+        for version in 1..LATEST_VERSION {
+            let db_path = database_at(version);
+            assert_eq!(version_of(&db_path), version);
+            assert_migrated(&db_path, version);
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_latest_backup() {
+        let db_path = database_at(1);
+        fs::write(backup_path(&db_path, 0), b"old backup").unwrap();
+        fs::write(db_path.with_file_name("uploads.db.vnext.bak"), b"not a backup").unwrap();
+
+        drop(Database::new(db_path.clone(), None).unwrap());
+
+        assert_eq!(backups(&db_path), vec![backup_path(&db_path, 1)]);
+        assert!(db_path.with_file_name("uploads.db.vnext.bak").exists());
+        let _ = fs::remove_dir_all(db_path.parent().unwrap());
+    }
+
+    #[test]
+    fn new_databases_are_not_backed_up() {
+        let db_path = temp_db();
+        drop(Database::new(db_path.clone(), None).unwrap());
+        assert_eq!(version_of(&db_path), LATEST_VERSION);
+        assert!(backups(&db_path).is_empty());
+        let _ = fs::remove_dir_all(db_path.parent().unwrap());
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::interceptors::context::BeforeTransmitInterceptorContextRef;
+use aws_smithy_runtime_api::client::interceptors::Intercept;
+use aws_smithy_runtime_api::client::orchestrator::Metadata;
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_types::config_bag::ConfigBag;
+use tauri::{AppHandle, Emitter};
+
+use crate::bucket_stats;
+use crate::database::Database;
+use crate::types::{BillingConfig, OperationClass, OperationCount, OperationUsage, R2Config};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+/// Emitted once per profile and month when the estimated cost reaches the budget.
+pub const BUDGET_EXCEEDED_EVENT: &str = "operation-budget-exceeded";
+
+/// (profile, month, operation)
+type CounterKey = (String, String, String);
+
+#[derive(Debug, Default)]
+struct Inner {
+    pending: HashMap<CounterKey, u64>,
+    /// Price tables of the profiles seen so far, for the budget check.
+    billing: HashMap<String, BillingConfig>,
+    /// (profile, month) pairs already warned about in this session.
+    warned: HashSet<(String, String)>,
+}
+
+/// Counts the S3 requests sent by every client it is attached to. Counts are
+/// buffered in memory and added to SQLite by `flush`, so recording never
+/// waits on the database.
+#[derive(Debug, Clone, Default)]
+pub struct OperationMeter {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl OperationMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interceptor counting the requests of a client built for `config`.
+    pub fn interceptor(&self, config: &R2Config) -> MeteringInterceptor {
+        let profile = config.profile_key();
+        self.inner
+            .lock()
+            .unwrap()
+            .billing
+            .insert(profile.clone(), config.billing.clone());
+        MeteringInterceptor {
+            meter: self.clone(),
+            profile,
+        }
+    }
+
+    fn record(&self, profile: &str, operation: &str) {
+        let month = bucket_stats::month_of(Database::get_current_timestamp());
+        let mut inner = self.inner.lock().unwrap();
+        *inner
+            .pending
+            .entry((profile.to_string(), month, operation.to_string()))
+            .or_insert(0) += 1;
+    }
+
+    /// Adds the buffered counts to the database and returns the profiles that
+    /// had any. Counts are kept for the next flush if the write fails.
+    pub fn flush(&self, db: &Database) -> Result<Vec<String>> {
+        let pending = std::mem::take(&mut self.inner.lock().unwrap().pending);
+        if pending.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if let Err(e) = db.add_operation_counts(&pending) {
+            let mut inner = self.inner.lock().unwrap();
+            for (key, count) in pending {
+                *inner.pending.entry(key).or_insert(0) += count;
+            }
+            return Err(e);
+        }
+
+        let profiles: HashSet<String> = pending.into_keys().map(|(profile, _, _)| profile).collect();
+        Ok(profiles.into_iter().collect())
+    }
+
+    /// Returns the usage of every profile in `profiles` whose estimated cost
+    /// this month has reached its budget and hasn't been reported yet.
+    fn newly_over_budget(&self, db: &Database, profiles: &[String]) -> Result<Vec<OperationUsage>> {
+        let month = bucket_stats::month_of(Database::get_current_timestamp());
+        let mut exceeded = Vec::new();
+        for profile in profiles {
+            let billing = match self.inner.lock().unwrap().billing.get(profile) {
+                Some(billing) if billing.monthly_budget.is_some() => billing.clone(),
+                _ => continue,
+            };
+
+            let usage = monthly_usage(db, profile, &month, &billing)?;
+            if usage.budget_exceeded
+                && self
+                    .inner
+                    .lock()
+                    .unwrap()
+                    .warned
+                    .insert((profile.clone(), month.clone()))
+            {
+                exceeded.push(usage);
+            }
+        }
+        Ok(exceeded)
+    }
+}
+
+#[derive(Debug)]
+pub struct MeteringInterceptor {
+    meter: OperationMeter,
+    profile: String,
+}
+
+impl Intercept for MeteringInterceptor {
+    fn name(&self) -> &'static str {
+        "MeteringInterceptor"
+    }
+
+    // Runs once per attempt, so retried requests are counted like R2 bills them.
+    fn read_before_transmit(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(metadata) = cfg.load::<Metadata>() {
+            self.meter.record(&self.profile, metadata.name());
+        }
+        Ok(())
+    }
+}
+
+/// R2's billing class of an S3 operation: reads are Class B, deletes and
+/// aborts are free, and everything else (writes, lists, multipart) is Class A.
+pub fn operation_class(operation: &str) -> OperationClass {
+    if operation.starts_with("Delete") || operation == "AbortMultipartUpload" {
+        OperationClass::Free
+    } else if operation.starts_with("Get") || operation.starts_with("Head") {
+        OperationClass::ClassB
+    } else {
+        OperationClass::ClassA
+    }
+}
+
+/// Operation counts of `profile` in `month` (`YYYY-MM`) and their estimated
+/// cost. Storage is estimated from the cached bucket stats, if any.
+pub fn monthly_usage(db: &Database, profile: &str, month: &str, billing: &BillingConfig) -> Result<OperationUsage> {
+    let mut usage = OperationUsage {
+        profile: profile.to_string(),
+        month: month.to_string(),
+        monthly_budget: billing.monthly_budget,
+        ..Default::default()
+    };
+
+    for (operation, count) in db.get_operation_counts(profile, month)? {
+        let class = operation_class(&operation);
+        match class {
+            OperationClass::ClassA => usage.class_a_ops += count,
+            OperationClass::ClassB => usage.class_b_ops += count,
+            OperationClass::Free => usage.free_ops += count,
+        }
+        usage.operations.push(OperationCount { operation, class, count });
+    }
+    usage.storage_bytes = db.get_bucket_stats(profile)?.map(|stats| stats.total_bytes);
+
+    let billable = |count: u64, free: u64| count.saturating_sub(free) as f64;
+    usage.class_a_cost = billable(usage.class_a_ops, billing.class_a_free_ops) / 1_000_000.0
+        * billing.class_a_price_per_million;
+    usage.class_b_cost = billable(usage.class_b_ops, billing.class_b_free_ops) / 1_000_000.0
+        * billing.class_b_price_per_million;
+    usage.storage_cost = usage
+        .storage_bytes
+        .map(|bytes| {
+            let gb = bytes as f64 / (1024.0 * 1024.0 * 1024.0);
+            (gb - billing.storage_free_gb).max(0.0) * billing.storage_price_per_gb_month
+        })
+        .unwrap_or(0.0);
+    usage.estimated_cost = usage.class_a_cost + usage.class_b_cost + usage.storage_cost;
+    usage.budget_exceeded = billing
+        .monthly_budget
+        .map(|budget| usage.estimated_cost > 0.0 && usage.estimated_cost >= budget)
+        .unwrap_or(false);
+
+    Ok(usage)
+}
+
+/// Periodically writes the buffered counts to the database and notifies the
+/// frontend when a profile crosses its monthly budget.
+pub fn spawn(meter: OperationMeter, db: Arc<Mutex<Option<Database>>>, app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+
+            let exceeded = {
+                let db_guard = db.lock().unwrap();
+                // While locked the counts stay buffered until unlock.
+                let Some(db) = db_guard.as_ref() else { continue };
+                meter
+                    .flush(db)
+                    .and_then(|profiles| meter.newly_over_budget(db, &profiles))
+            };
+
+            match exceeded {
+                Ok(exceeded) => {
+                    for usage in exceeded {
+                        println!(
+                            "[OperationMeter] Estimated cost ${:.2} for {} in {} reached the budget of ${:.2}",
+                            usage.estimated_cost,
+                            usage.profile,
+                            usage.month,
+                            usage.monthly_budget.unwrap_or_default()
+                        );
+                        if let Err(e) = app.emit(BUDGET_EXCEEDED_EVENT, usage) {
+                            println!("[OperationMeter] Failed to emit budget warning: {}", e);
+                        }
+                    }
+                }
+                Err(e) => println!("[OperationMeter] Failed to save operation counts: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_operations_like_r2_bills_them() {
+        for operation in ["PutObject", "ListObjectsV2", "CreateMultipartUpload", "UploadPart", "CopyObject"] {
+            assert_eq!(operation_class(operation), OperationClass::ClassA, "{}", operation);
+        }
+        for operation in ["GetObject", "HeadObject", "HeadBucket"] {
+            assert_eq!(operation_class(operation), OperationClass::ClassB, "{}", operation);
+        }
+        for operation in ["DeleteObject", "DeleteObjects", "AbortMultipartUpload"] {
+            assert_eq!(operation_class(operation), OperationClass::Free, "{}", operation);
+        }
+    }
+
+    #[test]
+    fn flush_moves_counts_to_the_database() {
+        let db = Database::new(std::path::PathBuf::from(":memory:"), None).unwrap();
+        let meter = OperationMeter::new();
+        meter.record("main", "PutObject");
+        meter.record("main", "PutObject");
+        meter.record("other", "GetObject");
+
+        let mut profiles = meter.flush(&db).unwrap();
+        profiles.sort();
+        assert_eq!(profiles, vec!["main", "other"]);
+        assert!(meter.flush(&db).unwrap().is_empty());
+
+        let month = bucket_stats::month_of(Database::get_current_timestamp());
+        assert_eq!(db.get_operation_counts("main", &month).unwrap(), vec![("PutObject".to_string(), 2)]);
+    }
+}
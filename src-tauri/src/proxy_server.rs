@@ -1,28 +1,49 @@
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::{Arc, Mutex};
 
 use axum::{
-    extract::{Multipart, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::post,
+    extract::{Multipart, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
     Json, Router,
 };
+use serde::Deserialize;
 use tokio::sync::oneshot;
-
-use crate::database::Database;
-use crate::r2_uploader::R2Uploader;
-use crate::types::{UploadRecord, UploadResult};
 use uuid::Uuid;
 
+use crate::client_encryption;
+use crate::database::{self, Database};
+use crate::uploader_manager::UploaderManager;
+use crate::types::{AppSettings, PresignRequest, PresignResult, UploadResult, UploadSource};
+use crate::upload_service::{self, UploadOptions};
+
+/// Decrypts client-side encrypted uploads in the browser; see `client_encryption`.
+const VIEW_PAGE: &str = include_str!("view_page.html");
+/// How long a viewer link handed out by the app keeps working.
+const VIEW_TOKEN_TTL_SECS: i64 = 60 * 60;
+const UNAUTHORIZED_ERROR: &str = "Missing or wrong proxy token; send it as \"Authorization: Bearer <token>\"";
+
 #[derive(Clone)]
 struct ProxyState {
-    uploader: Arc<Mutex<Option<R2Uploader>>>,
+    uploader: UploaderManager,
     db: Arc<Mutex<Option<Database>>>,
+    settings: Arc<Mutex<AppSettings>>,
+    token: Arc<str>,
+    view_tokens: ViewTokens,
 }
 
+/// Read-only tokens for the viewer page, with the Unix time they expire.
+type ViewTokens = Arc<Mutex<HashMap<String, i64>>>;
+
 pub struct ProxyServer {
     port: u16,
+    /// Required as `Authorization: Bearer <token>` by the endpoints that
+    /// write to the bucket. Regenerated every time the proxy starts, so only
+    /// programs the user handed it to can upload.
+    token: Option<String>,
+    view_tokens: ViewTokens,
     shutdown_tx: Option<oneshot::Sender<()>>,
     handle: Option<tauri::async_runtime::JoinHandle<()>>,
 }
@@ -31,6 +52,8 @@ impl ProxyServer {
     pub fn new(port: u16) -> Self {
         Self {
             port,
+            token: None,
+            view_tokens: ViewTokens::default(),
             shutdown_tx: None,
             handle: None,
         }
@@ -44,21 +67,40 @@ impl ProxyServer {
         self.handle.is_some()
     }
 
+    /// The session token while the proxy is running.
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// A token for one viewer link, only good for reading encrypted uploads
+    /// through `/view` and only for `VIEW_TOKEN_TTL_SECS`.
+    pub fn issue_view_token(&self) -> Option<String> {
+        if !self.is_running() {
+            return None;
+        }
+        let now = Database::get_current_timestamp();
+        let token = Uuid::new_v4().simple().to_string();
+        let mut view_tokens = self.view_tokens.lock().unwrap();
+        view_tokens.retain(|_, expires_at| *expires_at > now);
+        view_tokens.insert(token.clone(), now + VIEW_TOKEN_TTL_SECS);
+        Some(token)
+    }
+
     pub fn set_port(&mut self, port: u16) {
         self.port = port;
     }
 
     pub async fn start(
         &mut self,
-        uploader: Arc<Mutex<Option<R2Uploader>>>,
+        uploader: UploaderManager,
         db: Arc<Mutex<Option<Database>>>,
+        settings: Arc<Mutex<AppSettings>>,
     ) -> Result<(), String> {
         if self.is_running() {
             return Ok(());
         }
 
-        let has_uploader = uploader.lock().unwrap().is_some();
-        if !has_uploader {
+        if !uploader.is_configured() {
             return Err("R2 configuration not set".to_string());
         }
 
@@ -68,8 +110,20 @@ impl ProxyServer {
             .map_err(|e| format!("Failed to bind API proxy on {}: {}", addr, e))?;
 
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
-        let state = ProxyState { uploader, db };
-        let app = Router::new().route("/upload", post(upload_handler)).with_state(state);
+        let token = Uuid::new_v4().simple().to_string();
+        let state = ProxyState {
+            uploader,
+            db,
+            settings,
+            token: token.as_str().into(),
+            view_tokens: self.view_tokens.clone(),
+        };
+        let app = Router::new()
+            .route("/upload", post(upload_handler))
+            .route("/presign", post(presign_handler))
+            .route("/view", get(view_page_handler))
+            .route("/view/object", get(view_object_handler))
+            .with_state(state);
 
         let server = axum::serve(listener, app).with_graceful_shutdown(async {
             let _ = shutdown_rx.await;
@@ -81,12 +135,15 @@ impl ProxyServer {
             }
         });
 
+        self.token = Some(token);
         self.shutdown_tx = Some(shutdown_tx);
         self.handle = Some(handle);
         Ok(())
     }
 
     pub fn stop(&mut self) -> Result<(), String> {
+        self.token = None;
+        self.view_tokens.lock().unwrap().clear();
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.send(());
         }
@@ -101,10 +158,37 @@ impl ProxyServer {
     }
 }
 
+#[derive(Deserialize)]
+struct UploadQuery {
+    /// Overrides the profile's default TTL; `0` keeps the upload forever.
+    ttl_secs: Option<u64>,
+    /// Name of the calling program, recorded with the upload. Defaults to
+    /// the User-Agent.
+    client: Option<String>,
+    /// Comma-separated tags for the upload, e.g. `blog,2026`.
+    tags: Option<String>,
+    /// Alt text and caption used in the snippets generated for the upload.
+    alt: Option<String>,
+    caption: Option<String>,
+}
+
 async fn upload_handler(
     State(state): State<ProxyState>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
     mut multipart: Multipart,
 ) -> impl IntoResponse {
+    if !authorized(&state.token, &headers) {
+        let result = UploadResult {
+            success: false,
+            url: None,
+            error: Some(UNAUTHORIZED_ERROR.to_string()),
+            from_cache: false,
+            record: None,
+        };
+        return (StatusCode::UNAUTHORIZED, Json(result));
+    }
+
     let mut filename: Option<String> = None;
     let mut content_type: Option<String> = None;
     let mut file_data: Option<Vec<u8>> = None;
@@ -119,6 +203,7 @@ async fn upload_handler(
                     url: None,
                     error: Some(format!("Invalid multipart data: {}", err)),
                     from_cache: false,
+                    record: None,
                 };
                 return (StatusCode::BAD_REQUEST, Json(result));
             }
@@ -137,6 +222,7 @@ async fn upload_handler(
                         url: None,
                         error: Some(format!("Failed to read upload data: {}", err)),
                         from_cache: false,
+                        record: None,
                     };
                     return (StatusCode::BAD_REQUEST, Json(result));
                 }
@@ -153,6 +239,7 @@ async fn upload_handler(
                 url: None,
                 error: Some("No file found in multipart payload".to_string()),
                 from_cache: false,
+                record: None,
             };
             return (StatusCode::BAD_REQUEST, Json(result));
         }
@@ -161,61 +248,58 @@ async fn upload_handler(
     let filename = filename.unwrap_or_else(|| "upload.bin".to_string());
     let content_type = content_type.unwrap_or_else(|| "application/octet-stream".to_string());
 
-    let uploader = {
-        let guard = state.uploader.lock().unwrap();
-        match guard.as_ref() {
-            Some(uploader) => uploader.clone(),
-            None => {
-                let result = UploadResult {
-                    success: false,
-                    url: None,
-                    error: Some("R2 configuration not set".to_string()),
-                    from_cache: false,
-                };
-                return (StatusCode::SERVICE_UNAVAILABLE, Json(result));
-            }
+    let uploader = match state.uploader.uploader().await {
+        Ok(uploader) => uploader,
+        Err(err) => {
+            let result = UploadResult {
+                success: false,
+                url: None,
+                error: Some(err),
+                from_cache: false,
+                record: None,
+            };
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(result));
         }
     };
 
-    let file_hash = R2Uploader::calculate_hash(&file_data);
-    {
-        let db_guard = state.db.lock().unwrap();
-        if let Some(ref db) = *db_guard {
-            if let Ok(Some(existing_record)) = db.find_by_hash(&file_hash) {
-                let result = UploadResult {
-                    success: true,
-                    url: Some(existing_record.url),
-                    error: None,
-                    from_cache: true,
-                };
-                return (StatusCode::OK, Json(result));
-            }
-        }
-    }
-
-    match uploader.upload_file(file_data.clone(), &filename, &content_type).await {
-        Ok(url) => {
-            let record = UploadRecord {
-                id: Uuid::new_v4().to_string(),
-                original_filename: filename,
-                file_hash,
-                file_size: file_data.len() as u64,
-                url: url.clone(),
-                upload_time: Database::get_current_timestamp(),
-            };
-
-            let db_guard = state.db.lock().unwrap();
-            if let Some(ref db) = *db_guard {
-                if let Err(err) = db.insert_upload_record(&record) {
-                    eprintln!("[ProxyServer] Failed to save upload record: {}", err);
-                }
-            }
+    let verify_remote = state.settings.lock().unwrap().verify_cache_with_head;
+    let options = UploadOptions {
+        expires_at: upload_service::resolve_expiry(&uploader, query.ttl_secs),
+        source: UploadSource::Proxy,
+        client: query.client.filter(|client| !client.is_empty()).or_else(|| {
+            headers
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        }),
+        original_hash: None,
+        tags: query
+            .tags
+            .as_deref()
+            .map(|tags| database::normalize_tags(tags.split(',')))
+            .unwrap_or_default(),
+        alt_text: query.alt,
+        caption: query.caption,
+    };
 
+    match upload_service::upload_with_dedupe(
+        &uploader,
+        &state.db,
+        verify_remote,
+        file_data,
+        &filename,
+        &content_type,
+        &options,
+    )
+    .await
+    {
+        Ok(outcome) => {
             let result = UploadResult {
                 success: true,
-                url: Some(url),
+                url: Some(outcome.url),
                 error: None,
-                from_cache: false,
+                from_cache: outcome.from_cache,
+                record: Some(outcome.record),
             };
             (StatusCode::OK, Json(result))
         }
@@ -225,8 +309,204 @@ async fn upload_handler(
                 url: None,
                 error: Some(err.to_string()),
                 from_cache: false,
+                record: None,
             };
             (StatusCode::INTERNAL_SERVER_ERROR, Json(result))
         }
     }
 }
+
+/// Presigns a PUT under a key generated by the app, so callers can't
+/// overwrite existing objects.
+async fn presign_handler(
+    State(state): State<ProxyState>,
+    headers: HeaderMap,
+    Json(request): Json<PresignRequest>,
+) -> impl IntoResponse {
+    if !authorized(&state.token, &headers) {
+        let result = PresignResult {
+            success: false,
+            upload: None,
+            error: Some(UNAUTHORIZED_ERROR.to_string()),
+        };
+        return (StatusCode::UNAUTHORIZED, Json(result));
+    }
+    if request.key.is_some() {
+        let result = PresignResult {
+            success: false,
+            upload: None,
+            error: Some("The proxy generates object keys; send a filename instead of a key".to_string()),
+        };
+        return (StatusCode::BAD_REQUEST, Json(result));
+    }
+
+    let uploader = match state.uploader.uploader().await {
+        Ok(uploader) => uploader,
+        Err(err) => {
+            let result = PresignResult {
+                success: false,
+                upload: None,
+                error: Some(err),
+            };
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(result));
+        }
+    };
+
+    match uploader.presign_upload(&request).await {
+        Ok(upload) => {
+            let result = PresignResult {
+                success: true,
+                upload: Some(upload),
+                error: None,
+            };
+            (StatusCode::OK, Json(result))
+        }
+        Err(err) => {
+            let result = PresignResult {
+                success: false,
+                upload: None,
+                error: Some(err.to_string()),
+            };
+            (StatusCode::BAD_REQUEST, Json(result))
+        }
+    }
+}
+
+/// Whether the request carries the session token. Compared in constant time
+/// so the token can't be guessed byte by byte.
+fn authorized(expected: &str, headers: &HeaderMap) -> bool {
+    bearer_token(headers).is_some_and(|token| tokens_match(token, expected))
+}
+
+/// Whether `token` is an unexpired viewer token or the session token.
+fn view_authorized(session: &str, view_tokens: &ViewTokens, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return false;
+    };
+    let now = Database::get_current_timestamp();
+    tokens_match(token, session)
+        || view_tokens
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(view_token, expires_at)| *expires_at > now && tokens_match(token, view_token))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+fn tokens_match(token: &str, expected: &str) -> bool {
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[derive(Deserialize)]
+struct ViewPageQuery {
+    token: Option<String>,
+}
+
+/// The viewer page, opened from a link carrying a viewer token in `token`.
+async fn view_page_handler(State(state): State<ProxyState>, Query(query): Query<ViewPageQuery>) -> Response {
+    if !view_authorized(&state.token, &state.view_tokens, query.token.as_deref()) {
+        return (StatusCode::UNAUTHORIZED, "This viewer link has expired; open it from the app again").into_response();
+    }
+    ([(header::CACHE_CONTROL, "no-store")], Html(VIEW_PAGE)).into_response()
+}
+
+#[derive(Deserialize)]
+struct ViewObjectQuery {
+    url: String,
+}
+
+/// Returns the raw ciphertext behind a share link for the viewer page. Only
+/// client-side encrypted objects in the configured bucket are served, to
+/// holders of a viewer token, so the proxy can't be used to read the bucket
+/// or fetch arbitrary URLs.
+async fn view_object_handler(
+    State(state): State<ProxyState>,
+    Query(query): Query<ViewObjectQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if !view_authorized(&state.token, &state.view_tokens, bearer_token(&headers)) {
+        return (StatusCode::UNAUTHORIZED, "This viewer link has expired; open it from the app again").into_response();
+    }
+
+    let uploader = match state.uploader.uploader().await {
+        Ok(uploader) => uploader,
+        Err(err) => return (StatusCode::SERVICE_UNAVAILABLE, err).into_response(),
+    };
+
+    let key = match uploader.key_from_url(&query.url) {
+        Some(key) => key,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "The link doesn't point to the configured bucket".to_string(),
+            )
+                .into_response()
+        }
+    };
+
+    match uploader.download_object(&key).await {
+        Ok(data) if !data.starts_with(client_encryption::MAGIC) => (
+            StatusCode::FORBIDDEN,
+            "Only client-side encrypted uploads can be viewed here".to_string(),
+        )
+            .into_response(),
+        Ok(data) => (
+            [
+                (header::CONTENT_TYPE, "application/octet-stream"),
+                (header::CACHE_CONTROL, "no-store"),
+            ],
+            data,
+        )
+            .into_response(),
+        Err(err) => {
+            eprintln!("[ProxyServer] Failed to download {}: {}", key, err);
+            (StatusCode::BAD_GATEWAY, format!("Failed to download the object: {}", err)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(authorization).unwrap());
+        headers
+    }
+
+    #[test]
+    fn requires_the_session_token() {
+        assert!(authorized("secret", &headers("Bearer secret")));
+        assert!(!authorized("secret", &headers("Bearer secreT")));
+        assert!(!authorized("secret", &headers("Bearer secret2")));
+        assert!(!authorized("secret", &headers("secret")));
+        assert!(!authorized("secret", &HeaderMap::new()));
+    }
+
+    #[test]
+    fn accepts_unexpired_view_tokens() {
+        let now = Database::get_current_timestamp();
+        let view_tokens = ViewTokens::default();
+        view_tokens.lock().unwrap().insert("fresh".to_string(), now + 60);
+        view_tokens.lock().unwrap().insert("stale".to_string(), now - 1);
+
+        assert!(view_authorized("secret", &view_tokens, Some("fresh")));
+        assert!(view_authorized("secret", &view_tokens, Some("secret")));
+        assert!(!view_authorized("secret", &view_tokens, Some("stale")));
+        assert!(!view_authorized("secret", &view_tokens, Some("other")));
+        assert!(!view_authorized("secret", &view_tokens, None));
+    }
+}
@@ -1,69 +1,125 @@
-use crate::types::R2Config;
+use crate::credentials;
+use crate::database::Database;
+use crate::http_client;
+use crate::operation_meter::OperationMeter;
+use crate::types::{
+    BucketListRequest, BucketListing, CorsRuleConfig, LifecycleRuleConfig, PresignRequest, PresignedUpload, R2Config,
+    RemoteObject, SseConfig, UploadRecord,
+};
 use anyhow::Result;
+use aws_config::environment::region::EnvironmentVariableRegionProvider;
 use aws_config::meta::region::RegionProviderChain;
-use aws_sdk_s3::{config::Region, primitives::ByteStream, Client};
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{
+    AbortIncompleteMultipartUpload, BucketLifecycleConfiguration, CorsConfiguration, CorsRule, ExpirationStatus,
+    LifecycleExpiration, LifecycleRule, LifecycleRuleFilter, ServerSideEncryption,
+};
+use aws_sdk_s3::{config::Region, presigning::PresigningConfig, primitives::ByteStream, Client};
+use base64::{engine::general_purpose, Engine as _};
+use md5::{Digest, Md5};
 use sha256::digest;
+use std::collections::HashMap;
+use std::time::Duration;
 use uuid::Uuid;
 
+/// Default lifetime of a presigned upload URL.
+const DEFAULT_PRESIGN_EXPIRY_SECS: u64 = 15 * 60;
+/// Page size used when the caller does not ask for a specific one.
+const DEFAULT_LIST_PAGE_SIZE: i32 = 200;
+/// S3 never returns more than 1000 keys per ListObjectsV2 page.
+const MAX_LIST_PAGE_SIZE: i32 = 1000;
+/// SigV4 presigned URLs cannot be valid for longer than seven days.
+const MAX_PRESIGN_EXPIRY_SECS: u64 = 7 * 24 * 60 * 60;
+/// ID of the lifecycle rule managed by `apply_expiry_lifecycle_rule`.
+const EXPIRY_LIFECYCLE_RULE_ID: &str = "moli-tutu-expiry";
+
 #[derive(Clone)]
 pub struct R2Uploader {
     client: Client,
     config: R2Config,
+    customer_key: Option<CustomerKeyHeaders>,
+}
+
+/// SSE-C request headers, sent on every write and read of an object.
+#[derive(Clone)]
+struct CustomerKeyHeaders {
+    key: String,
+    key_md5: String,
+}
+
+impl CustomerKeyHeaders {
+    const ALGORITHM: &'static str = "AES256";
+
+    fn from_config(sse: &SseConfig) -> Result<Option<Self>> {
+        let key = match sse {
+            SseConfig::CustomerKey { key } => key.trim(),
+            _ => return Ok(None),
+        };
+
+        let raw = general_purpose::STANDARD
+            .decode(key)
+            .map_err(|_| anyhow::anyhow!("SSE-C key must be base64-encoded"))?;
+        if raw.len() != 32 {
+            return Err(anyhow::anyhow!("SSE-C key must be 256 bits (32 bytes), got {} bytes", raw.len()));
+        }
+
+        Ok(Some(Self {
+            key: key.to_string(),
+            key_md5: general_purpose::STANDARD.encode(Md5::digest(&raw)),
+        }))
+    }
 }
 
 impl R2Uploader {
-    pub async fn new(config: R2Config) -> Result<Self> {
+    /// Builds the S3 client without contacting the endpoint; use
+    /// `head_bucket_status` to check that it is reachable. Every request the
+    /// client sends is counted by `meter`.
+    pub async fn new(config: R2Config, meter: &OperationMeter) -> Result<Self> {
         println!("[R2Uploader] Creating new uploader with endpoint: {}", config.endpoint);
         println!("[R2Uploader] Bucket: {}", config.bucket_name);
         println!("[R2Uploader] Public URL base: {}", config.public_url_base);
+        println!("[R2Uploader] Credential source: {:?}", config.credential_source);
         println!("[R2Uploader] Access Key ID: {}...", &config.access_key_id[..std::cmp::min(8, config.access_key_id.len())]);
         
         // Validate configuration format
         Self::validate_config(&config)?;
+        let customer_key = CustomerKeyHeaders::from_config(&config.server_side_encryption)?;
+        println!("[R2Uploader] Server-side encryption: {}", match config.server_side_encryption {
+            SseConfig::None => "bucket default",
+            SseConfig::S3 => "SSE-S3",
+            SseConfig::CustomerKey { .. } => "SSE-C",
+        });
         
-        let region_provider = RegionProviderChain::default_provider().or_else(Region::new("auto"));
+        // Only look at AWS_REGION; the full default chain can fall through to
+        // the EC2 metadata service, which stalls for seconds when offline.
+        let region_provider = RegionProviderChain::first_try(EnvironmentVariableRegionProvider::new())
+            .or_else(Region::new("auto"));
         
         println!("[R2Uploader] Building AWS config...");
-        let aws_config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
             .endpoint_url(&config.endpoint)
-            .credentials_provider(aws_credential_types::Credentials::new(
-                &config.access_key_id,
-                &config.secret_access_key,
-                None,
-                None,
-                "cloudflare-r2",
-            ))
-            .region(region_provider)
-            .load()
-            .await;
+            .credentials_provider(credentials::credentials_provider(&config))
+            .region(region_provider);
+        if !config.network.is_default() {
+            println!("[R2Uploader] Using custom network settings");
+            loader = loader.http_client(http_client::sdk_http_client(&config.network)?);
+        }
+        let aws_config = loader.load().await;
 
         println!("[R2Uploader] Creating S3 client...");
-        let client = Client::new(&aws_config);
-        
-        // Test bucket access by listing objects (optional)
-        println!("[R2Uploader] Testing bucket access...");
-        match client.list_objects_v2().bucket(&config.bucket_name).max_keys(1).send().await {
-            Ok(_) => println!("[R2Uploader] Bucket access test successful"),
-            Err(e) => {
-                println!("[R2Uploader] Warning: Bucket access test failed: {:?}", e);
-                println!("[R2Uploader] This might indicate configuration issues");
-            }
-        }
+        let s3_config = aws_sdk_s3::config::Builder::from(&aws_config)
+            .interceptor(meter.interceptor(&config))
+            .build();
+        let client = Client::from_conf(s3_config);
         
         println!("[R2Uploader] R2Uploader initialized successfully");
-        Ok(Self { client, config })
+        Ok(Self { client, config, customer_key })
     }
     
     fn validate_config(config: &R2Config) -> Result<()> {
         println!("[R2Uploader] Validating configuration...");
         
-        if config.access_key_id.is_empty() {
-            return Err(anyhow::anyhow!("Access Key ID is empty"));
-        }
-        
-        if config.secret_access_key.is_empty() {
-            return Err(anyhow::anyhow!("Secret Access Key is empty"));
-        }
+        credentials::validate_credential_source(config)?;
         
         if config.bucket_name.is_empty() {
             return Err(anyhow::anyhow!("Bucket name is empty"));
@@ -84,43 +140,57 @@ impl R2Uploader {
         Ok(())
     }
 
+    /// Uploads under a generated key and returns the key; `public_url` gives
+    /// its URL. Expiring uploads go below the profile's expiry prefix when one
+    /// is configured.
     pub async fn upload_file(
         &self,
         file_data: Vec<u8>,
         filename: &str,
         content_type: &str,
+        expiring: bool,
     ) -> Result<String> {
         println!("[R2Uploader] Starting file upload: {} ({} bytes, type: {})", filename, file_data.len(), content_type);
         
         let file_hash = digest(&file_data);
         println!("[R2Uploader] File hash: {}", file_hash);
         
-        let file_extension = std::path::Path::new(filename)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("jpg");
-        
-        let key = format!("{}.{}", Uuid::new_v4(), file_extension);
+        let key = match self.expiry_prefix().filter(|_| expiring) {
+            Some(prefix) => format!("{}/{}", prefix, Self::generate_key(filename)),
+            None => Self::generate_key(filename),
+        };
         println!("[R2Uploader] Generated key: {}", key);
         println!("[R2Uploader] Target bucket: {}", self.config.bucket_name);
         
+        self.put_object(&key, file_data, content_type).await?;
+        println!("[R2Uploader] Generated public URL: {}", self.public_url(&key));
+        if self.uses_customer_key() {
+            println!("[R2Uploader] Warning: object is encrypted with SSE-C, its public URL won't serve it");
+        }
+        Ok(key)
+    }
+
+    /// Writes `file_data` to an exact key in the bucket.
+    pub async fn put_object(&self, key: &str, file_data: Vec<u8>, content_type: &str) -> Result<()> {
         println!("[R2Uploader] Sending put_object request to S3...");
         let response = self
             .client
             .put_object()
             .bucket(&self.config.bucket_name)
-            .key(&key)
+            .key(key)
             .body(ByteStream::from(file_data))
             .content_type(content_type)
+            .set_server_side_encryption(self.server_side_encryption())
+            .set_sse_customer_algorithm(self.sse_customer_algorithm())
+            .set_sse_customer_key(self.sse_customer_key())
+            .set_sse_customer_key_md5(self.sse_customer_key_md5())
             .send()
             .await;
             
         match response {
             Ok(resp) => {
                 println!("[R2Uploader] Upload successful! Response: {:?}", resp);
-                let url = format!("{}/{}", self.config.public_url_base.trim_end_matches('/'), key);
-                println!("[R2Uploader] Generated public URL: {}", url);
-                Ok(url)
+                Ok(())
             },
             Err(e) => {
                 println!("[R2Uploader] Upload failed: {:?}", e);
@@ -129,9 +199,699 @@ impl R2Uploader {
         }
     }
 
+    pub async fn delete_object(&self, key: &str) -> Result<()> {
+        println!("[R2Uploader] Deleting object: {}", key);
+        self.client
+            .delete_object()
+            .bucket(&self.config.bucket_name)
+            .key(key)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the HTTP status of a HeadBucket request. Only fails when no
+    /// response was received at all (DNS, TLS, timeouts...).
+    pub async fn head_bucket_status(&self) -> Result<u16> {
+        match self.client.head_bucket().bucket(&self.config.bucket_name).send().await {
+            Ok(_) => Ok(200),
+            Err(e) => match e.raw_response() {
+                Some(response) => Ok(response.status().as_u16()),
+                None => Err(e.into()),
+            },
+        }
+    }
+
+    /// Creates a presigned PUT request that lets another client upload
+    /// directly to the bucket without access to our credentials. Profiles
+    /// using SSE-C are refused: the upload would have to carry the customer
+    /// key in its headers, handing it to whoever gets the URL.
+    pub async fn presign_upload(&self, request: &PresignRequest) -> Result<PresignedUpload> {
+        if self.uses_customer_key() {
+            return Err(anyhow::anyhow!(
+                "Presigned uploads are not available when SSE-C is enabled, as they would expose the customer key"
+            ));
+        }
+
+        let key = match request.key.as_deref() {
+            Some(key) => {
+                Self::validate_key(key)?;
+                key.to_string()
+            }
+            None => Self::generate_key(request.filename.as_deref().unwrap_or("")),
+        };
+
+        let expires_in = request
+            .expires_in_secs
+            .unwrap_or(DEFAULT_PRESIGN_EXPIRY_SECS)
+            .clamp(1, MAX_PRESIGN_EXPIRY_SECS);
+        println!("[R2Uploader] Presigning PUT for key: {} (expires in {}s)", key, expires_in);
+
+        let presigning_config = PresigningConfig::expires_in(Duration::from_secs(expires_in))?;
+        let presigned = self
+            .client
+            .put_object()
+            .bucket(&self.config.bucket_name)
+            .key(&key)
+            .content_type(&request.content_type)
+            .set_server_side_encryption(self.server_side_encryption())
+            .presigned(presigning_config)
+            .await?;
+
+        let headers: HashMap<String, String> = presigned
+            .headers()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        Ok(PresignedUpload {
+            method: presigned.method().to_string(),
+            upload_url: presigned.uri().to_string(),
+            headers,
+            public_url: self.public_url(&key),
+            key,
+            expires_at: Database::get_current_timestamp() + expires_in as i64,
+        })
+    }
+
+    /// Lists one page of the bucket, grouping keys below `prefix` into
+    /// folders on `delimiter` like a file browser would.
+    pub async fn list_objects(&self, request: &BucketListRequest) -> Result<BucketListing> {
+        let prefix = request.prefix.clone().unwrap_or_default();
+        let delimiter = request.delimiter.clone().unwrap_or_else(|| "/".to_string());
+        let max_keys = request
+            .max_keys
+            .unwrap_or(DEFAULT_LIST_PAGE_SIZE)
+            .clamp(1, MAX_LIST_PAGE_SIZE);
+        println!(
+            "[R2Uploader] Listing objects: prefix={:?}, delimiter={:?}, max_keys={}",
+            prefix, delimiter, max_keys
+        );
+
+        let mut builder = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.config.bucket_name)
+            .max_keys(max_keys)
+            .set_continuation_token(request.continuation_token.clone());
+        if !prefix.is_empty() {
+            builder = builder.prefix(&prefix);
+        }
+        if !delimiter.is_empty() {
+            builder = builder.delimiter(&delimiter);
+        }
+
+        let response = builder.send().await?;
+
+        let objects = response
+            .contents()
+            .iter()
+            .filter_map(|object| {
+                let key = object.key()?;
+                Some(RemoteObject {
+                    key: key.to_string(),
+                    size: object.size().unwrap_or(0).max(0) as u64,
+                    last_modified: object.last_modified().map(|time| time.secs()),
+                    etag: object.e_tag().map(|etag| etag.to_string()),
+                    content_type: mime_guess::from_path(key).first_or_octet_stream().to_string(),
+                    url: self.public_url(key),
+                })
+            })
+            .collect();
+
+        let folders = response
+            .common_prefixes()
+            .iter()
+            .filter_map(|common_prefix| common_prefix.prefix().map(|p| p.to_string()))
+            .collect();
+
+        Ok(BucketListing {
+            prefix,
+            objects,
+            folders,
+            next_continuation_token: response.next_continuation_token().map(|t| t.to_string()),
+            is_truncated: response.is_truncated().unwrap_or(false),
+        })
+    }
+
+    /// Fetches authoritative metadata for a single object, including the
+    /// stored content type that listings can only guess from the key.
+    pub async fn head_object(&self, key: &str) -> Result<RemoteObject> {
+        println!("[R2Uploader] Fetching object metadata: {}", key);
+        let response = self
+            .client
+            .head_object()
+            .bucket(&self.config.bucket_name)
+            .key(key)
+            .set_sse_customer_algorithm(self.sse_customer_algorithm())
+            .set_sse_customer_key(self.sse_customer_key())
+            .set_sse_customer_key_md5(self.sse_customer_key_md5())
+            .send()
+            .await?;
+
+        Ok(RemoteObject {
+            key: key.to_string(),
+            size: response.content_length().unwrap_or(0).max(0) as u64,
+            last_modified: response.last_modified().map(|time| time.secs()),
+            etag: response.e_tag().map(|etag| etag.to_string()),
+            content_type: response
+                .content_type()
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| mime_guess::from_path(key).first_or_octet_stream().to_string()),
+            url: self.public_url(key),
+        })
+    }
+
+    /// Lists every object below `prefix`, following continuation tokens.
+    pub async fn list_all_objects(&self, prefix: Option<&str>) -> Result<Vec<RemoteObject>> {
+        let mut objects = Vec::new();
+        let mut request = BucketListRequest {
+            prefix: prefix.map(|p| p.to_string()),
+            delimiter: Some(String::new()),
+            continuation_token: None,
+            max_keys: Some(MAX_LIST_PAGE_SIZE),
+        };
+
+        loop {
+            let page = self.list_objects(&request).await?;
+            objects.extend(page.objects);
+            match page.next_continuation_token {
+                Some(token) if page.is_truncated => request.continuation_token = Some(token),
+                _ => break,
+            }
+        }
+
+        println!("[R2Uploader] Listed {} objects in total", objects.len());
+        Ok(objects)
+    }
+
+    /// Checks whether `key` still exists. Errors other than "not found" are
+    /// returned so callers can tell a missing object from a failed check.
+    pub async fn object_exists(&self, key: &str) -> Result<bool> {
+        let response = self
+            .client
+            .head_object()
+            .bucket(&self.config.bucket_name)
+            .key(key)
+            .set_sse_customer_algorithm(self.sse_customer_algorithm())
+            .set_sse_customer_key(self.sse_customer_key())
+            .set_sse_customer_key_md5(self.sse_customer_key_md5())
+            .send()
+            .await;
+
+        match response {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().map(|err| err.is_not_found()).unwrap_or(false) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn download_object(&self, key: &str) -> Result<Vec<u8>> {
+        println!("[R2Uploader] Downloading object: {}", key);
+        let response = self
+            .client
+            .get_object()
+            .bucket(&self.config.bucket_name)
+            .key(key)
+            .set_sse_customer_algorithm(self.sse_customer_algorithm())
+            .set_sse_customer_key(self.sse_customer_key())
+            .set_sse_customer_key_md5(self.sse_customer_key_md5())
+            .send()
+            .await?;
+        let data = response.body.collect().await?.into_bytes();
+        Ok(data.to_vec())
+    }
+
+    /// Recovers the object key from a public URL produced by this uploader.
+    /// Any fragment, such as the key of an encrypted share link, is ignored.
+    pub fn key_from_url(&self, url: &str) -> Option<String> {
+        let base = format!("{}/", self.config.public_url_base.trim_end_matches('/'));
+        let url = url.split('#').next().unwrap_or(url);
+        url.strip_prefix(&base)
+            .filter(|key| !key.is_empty())
+            .map(|key| key.to_string())
+    }
+
+    /// Key of `record`'s object in this uploader's bucket, or `None` if it
+    /// was uploaded with another profile. The key always comes from the
+    /// record's public URL; a stored object key that names another object is
+    /// not trusted, since deletions act on whatever this returns.
+    pub fn record_key(&self, record: &UploadRecord) -> Option<String> {
+        if record.profile.as_ref().is_some_and(|profile| *profile != self.config.profile_key()) {
+            return None;
+        }
+        let key = self.key_from_url(&record.url)?;
+        match &record.object_key {
+            Some(object_key) if *object_key != key => None,
+            _ => Some(key),
+        }
+    }
+
+    pub fn config(&self) -> &R2Config {
+        &self.config
+    }
+
+    /// The profile's expiry prefix without surrounding slashes.
+    pub fn expiry_prefix(&self) -> Option<String> {
+        self.config
+            .expiry
+            .prefix
+            .as_deref()
+            .map(|prefix| prefix.trim_matches('/').to_string())
+            .filter(|prefix| !prefix.is_empty())
+    }
+
+    pub fn is_under_expiry_prefix(&self, key: &str) -> bool {
+        self.expiry_prefix()
+            .map(|prefix| key.starts_with(&format!("{}/", prefix)))
+            .unwrap_or(false)
+    }
+
+    /// Adds or replaces a bucket lifecycle rule that deletes objects below
+    /// the expiry prefix after `days`, keeping any other rules in place.
+    /// Everything under the prefix is affected, whatever its own TTL.
+    pub async fn apply_expiry_lifecycle_rule(&self, days: i32) -> Result<()> {
+        let prefix = self
+            .expiry_prefix()
+            .ok_or_else(|| anyhow::anyhow!("No expiry prefix configured"))?;
+        println!("[R2Uploader] Applying lifecycle rule: {}/ expires after {} days", prefix, days);
+
+        self.put_lifecycle_rule(&LifecycleRuleConfig {
+            id: EXPIRY_LIFECYCLE_RULE_ID.to_string(),
+            enabled: true,
+            prefix: Some(format!("{}/", prefix)),
+            expiration_days: Some(days),
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn get_cors_rules(&self) -> Result<Vec<CorsRuleConfig>> {
+        let response = match self
+            .client
+            .get_bucket_cors()
+            .bucket(&self.config.bucket_name)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) if e.as_service_error().and_then(|err| err.code()) == Some("NoSuchCORSConfiguration") => {
+                return Ok(Vec::new())
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(response
+            .cors_rules()
+            .iter()
+            .map(|rule| CorsRuleConfig {
+                id: rule.id().map(|id| id.to_string()),
+                allowed_origins: rule.allowed_origins().to_vec(),
+                allowed_methods: rule.allowed_methods().to_vec(),
+                allowed_headers: rule.allowed_headers().to_vec(),
+                expose_headers: rule.expose_headers().to_vec(),
+                max_age_seconds: rule.max_age_seconds(),
+            })
+            .collect())
+    }
+
+    /// Replaces the bucket's CORS configuration; no rules removes it.
+    pub async fn set_cors_rules(&self, rules: &[CorsRuleConfig]) -> Result<()> {
+        println!("[R2Uploader] Setting {} CORS rules", rules.len());
+        if rules.is_empty() {
+            self.client
+                .delete_bucket_cors()
+                .bucket(&self.config.bucket_name)
+                .send()
+                .await?;
+            return Ok(());
+        }
+
+        let cors_rules = rules
+            .iter()
+            .map(|rule| {
+                CorsRule::builder()
+                    .set_id(rule.id.clone())
+                    .set_allowed_origins(Some(rule.allowed_origins.clone()))
+                    .set_allowed_methods(Some(rule.allowed_methods.clone()))
+                    .set_allowed_headers(Some(rule.allowed_headers.clone()))
+                    .set_expose_headers(Some(rule.expose_headers.clone()))
+                    .set_max_age_seconds(rule.max_age_seconds)
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.client
+            .put_bucket_cors()
+            .bucket(&self.config.bucket_name)
+            .cors_configuration(CorsConfiguration::builder().set_cors_rules(Some(cors_rules)).build()?)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_lifecycle_rules(&self) -> Result<Vec<LifecycleRuleConfig>> {
+        Ok(self
+            .raw_lifecycle_rules()
+            .await?
+            .iter()
+            .map(|rule| {
+                LifecycleRuleConfig {
+                    id: rule.id().unwrap_or_default().to_string(),
+                    enabled: *rule.status() == ExpirationStatus::Enabled,
+                    prefix: lifecycle_rule_prefix(rule),
+                    expiration_days: rule.expiration().and_then(|expiration| expiration.days()),
+                    abort_incomplete_multipart_days: rule
+                        .abort_incomplete_multipart_upload()
+                        .and_then(|abort| abort.days_after_initiation()),
+                    has_other_actions: !rule.transitions().is_empty()
+                        || !rule.noncurrent_version_transitions().is_empty()
+                        || rule.noncurrent_version_expiration().is_some()
+                        || rule.expiration().map(|expiration| expiration.date().is_some()).unwrap_or(false)
+                        || rule.filter().is_some_and(has_other_filters),
+                }
+            })
+            .collect())
+    }
+
+    /// Adds `rule`, or updates the rule with the same ID. Other rules are
+    /// sent back exactly as the bucket returned them.
+    pub async fn put_lifecycle_rule(&self, rule: &LifecycleRuleConfig) -> Result<()> {
+        if rule.id.trim().is_empty() {
+            return Err(anyhow::anyhow!("Lifecycle rule ID is empty"));
+        }
+        if rule.expiration_days.is_none() && rule.abort_incomplete_multipart_days.is_none() {
+            return Err(anyhow::anyhow!("Lifecycle rule has no action"));
+        }
+
+        let mut rules = self.raw_lifecycle_rules().await?;
+        let merged = merge_lifecycle_rule(rules.iter().find(|existing| existing.id() == Some(rule.id.as_str())), rule)?;
+        rules.retain(|existing| existing.id() != Some(rule.id.as_str()));
+        rules.push(merged);
+        self.put_raw_lifecycle_rules(rules).await
+    }
+
+    /// Removes the rule with `id`; returns false if there was none.
+    pub async fn delete_lifecycle_rule(&self, id: &str) -> Result<bool> {
+        let mut rules = self.raw_lifecycle_rules().await?;
+        let count = rules.len();
+        rules.retain(|rule| rule.id() != Some(id));
+        if rules.len() == count {
+            return Ok(false);
+        }
+        self.put_raw_lifecycle_rules(rules).await?;
+        Ok(true)
+    }
+
+    async fn raw_lifecycle_rules(&self) -> Result<Vec<LifecycleRule>> {
+        match self
+            .client
+            .get_bucket_lifecycle_configuration()
+            .bucket(&self.config.bucket_name)
+            .send()
+            .await
+        {
+            Ok(response) => Ok(response.rules().to_vec()),
+            Err(e) if e.as_service_error().and_then(|err| err.code()) == Some("NoSuchLifecycleConfiguration") => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// An empty list deletes the lifecycle configuration, since S3 rejects
+    /// a configuration without rules.
+    async fn put_raw_lifecycle_rules(&self, rules: Vec<LifecycleRule>) -> Result<()> {
+        println!("[R2Uploader] Saving {} lifecycle rules", rules.len());
+        if rules.is_empty() {
+            self.client
+                .delete_bucket_lifecycle()
+                .bucket(&self.config.bucket_name)
+                .send()
+                .await?;
+            return Ok(());
+        }
+
+        self.client
+            .put_bucket_lifecycle_configuration()
+            .bucket(&self.config.bucket_name)
+            .lifecycle_configuration(
+                BucketLifecycleConfiguration::builder()
+                    .set_rules(Some(rules))
+                    .build()?,
+            )
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Objects written with a customer key can only be read by sending the
+    /// key, so their public URLs don't work.
+    pub fn uses_customer_key(&self) -> bool {
+        self.customer_key.is_some()
+    }
+
+    /// A random base64-encoded 256-bit key for SSE-C.
+    pub fn generate_customer_key() -> String {
+        use aes_gcm::aead::{rand_core::RngCore, OsRng};
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        general_purpose::STANDARD.encode(key)
+    }
+
+    fn server_side_encryption(&self) -> Option<ServerSideEncryption> {
+        match self.config.server_side_encryption {
+            SseConfig::S3 => Some(ServerSideEncryption::Aes256),
+            _ => None,
+        }
+    }
+
+    fn sse_customer_algorithm(&self) -> Option<String> {
+        self.customer_key.as_ref().map(|_| CustomerKeyHeaders::ALGORITHM.to_string())
+    }
+
+    fn sse_customer_key(&self) -> Option<String> {
+        self.customer_key.as_ref().map(|headers| headers.key.clone())
+    }
+
+    fn sse_customer_key_md5(&self) -> Option<String> {
+        self.customer_key.as_ref().map(|headers| headers.key_md5.clone())
+    }
+
+    pub fn public_url(&self, key: &str) -> String {
+        format!("{}/{}", self.config.public_url_base.trim_end_matches('/'), key)
+    }
+
+    fn generate_key(filename: &str) -> String {
+        let file_extension = std::path::Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("jpg");
+
+        format!("{}.{}", Uuid::new_v4(), file_extension)
+    }
+
+    fn validate_key(key: &str) -> Result<()> {
+        if key.is_empty() || key.len() > 1024 {
+            return Err(anyhow::anyhow!("Object key must be between 1 and 1024 bytes"));
+        }
+
+        if key.starts_with('/') || key.split('/').any(|segment| segment == "..") {
+            return Err(anyhow::anyhow!("Object key must be a relative path without '..' segments"));
+        }
+
+        Ok(())
+    }
+
     pub fn calculate_hash(data: &[u8]) -> String {
         let hash = digest(data);
         println!("[R2Uploader] Calculated hash for {} bytes: {}", data.len(), hash);
         hash
     }
-}
\ No newline at end of file
+}
+
+/// Prefix a lifecycle rule applies to, from its filter or the legacy field.
+fn lifecycle_rule_prefix(rule: &LifecycleRule) -> Option<String> {
+    #[allow(deprecated)]
+    rule.filter()
+        .and_then(|filter| filter.prefix())
+        .or(rule.prefix())
+        .filter(|prefix| !prefix.is_empty())
+        .map(|prefix| prefix.to_string())
+}
+
+/// Whether `filter` selects objects by more than a prefix.
+fn has_other_filters(filter: &LifecycleRuleFilter) -> bool {
+    filter.tag().is_some()
+        || filter.and().is_some()
+        || filter.object_size_greater_than().is_some()
+        || filter.object_size_less_than().is_some()
+}
+
+/// Applies the fields the app edits to `existing`, keeping what it can't
+/// show: transitions, noncurrent version actions, an expiration date while
+/// no days are set, and tag or size filters. A rule with such filters can't
+/// be moved to another prefix, since that would drop them.
+fn merge_lifecycle_rule(existing: Option<&LifecycleRule>, rule: &LifecycleRuleConfig) -> Result<LifecycleRule> {
+    let mut merged = match existing {
+        Some(existing) => existing.clone(),
+        None => LifecycleRule::builder()
+            .id(&rule.id)
+            .status(ExpirationStatus::Enabled)
+            .build()?,
+    };
+
+    merged.status = if rule.enabled { ExpirationStatus::Enabled } else { ExpirationStatus::Disabled };
+    let keeps_date = rule.expiration_days.is_none()
+        && merged.expiration().is_some_and(|expiration| expiration.date().is_some());
+    if !keeps_date {
+        merged.expiration = rule
+            .expiration_days
+            .map(|days| LifecycleExpiration::builder().days(days).build());
+    }
+    merged.abort_incomplete_multipart_upload = rule
+        .abort_incomplete_multipart_days
+        .map(|days| AbortIncompleteMultipartUpload::builder().days_after_initiation(days).build());
+
+    if merged.filter().is_some_and(has_other_filters) {
+        if rule.prefix != lifecycle_rule_prefix(&merged) {
+            return Err(anyhow::anyhow!(
+                "Lifecycle rule '{}' also filters on tags or object size; change its prefix in the bucket's dashboard",
+                rule.id
+            ));
+        }
+    } else {
+        merged.filter = Some(LifecycleRuleFilter::builder().prefix(rule.prefix.clone().unwrap_or_default()).build());
+        #[allow(deprecated)]
+        {
+            merged.prefix = None;
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{UploadSource, UploadStatus};
+    use aws_sdk_s3::types::{Tag, Transition, TransitionStorageClass};
+
+    async fn uploader(server_side_encryption: SseConfig) -> R2Uploader {
+        let config: R2Config = serde_json::from_value(serde_json::json!({
+            "access_key_id": "AKIDEXAMPLE",
+            "secret_access_key": "secret",
+            "endpoint": "https://account.r2.cloudflarestorage.com",
+            "bucket_name": "images",
+            "public_url_base": "https://img.example.com",
+        }))
+        .unwrap();
+        let config = R2Config { server_side_encryption, ..config };
+        R2Uploader::new(config, &OperationMeter::new()).await.unwrap()
+    }
+
+    fn request() -> PresignRequest {
+        serde_json::from_value(serde_json::json!({
+            "filename": "photo.png",
+            "content_type": "image/png",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn record_key_must_match_the_public_url() {
+        let uploader = uploader(SseConfig::None).await;
+        let record = |url: &str, profile: Option<String>, object_key: Option<&str>| UploadRecord {
+            id: "upload".to_string(),
+            original_filename: "photo.png".to_string(),
+            file_hash: "hash".to_string(),
+            file_size: 100,
+            url: url.to_string(),
+            upload_time: 0,
+            status: UploadStatus::Active,
+            expires_at: None,
+            content_type: None,
+            profile,
+            source: UploadSource::App,
+            bucket: None,
+            object_key: object_key.map(str::to_string),
+            width: None,
+            height: None,
+            client: None,
+            original_hash: None,
+            tags: Vec::new(),
+            notes: None,
+            alt_text: None,
+            caption: None,
+        };
+        let profile = Some(uploader.config().profile_key());
+        let url = "https://img.example.com/2024/photo.png";
+
+        let key = |record: UploadRecord| uploader.record_key(&record);
+        assert_eq!(key(record(url, profile.clone(), Some("2024/photo.png"))).as_deref(), Some("2024/photo.png"));
+        assert_eq!(key(record(url, None, None)).as_deref(), Some("2024/photo.png"));
+        assert_eq!(key(record(url, profile.clone(), Some("private/backup.db"))), None);
+        assert_eq!(key(record("https://elsewhere.example.com/photo.png", profile, None)), None);
+        assert_eq!(key(record(url, Some("other/bucket".to_string()), None)), None);
+    }
+
+    #[test]
+    fn lifecycle_edits_keep_what_the_app_cannot_show() {
+        let existing = LifecycleRule::builder()
+            .id("archive")
+            .status(ExpirationStatus::Enabled)
+            .filter(LifecycleRuleFilter::builder().prefix("logs/").build())
+            .transitions(Transition::builder().days(30).storage_class(TransitionStorageClass::Glacier).build())
+            .expiration(LifecycleExpiration::builder().days(365).build())
+            .build()
+            .unwrap();
+        let edit = LifecycleRuleConfig {
+            id: "archive".to_string(),
+            enabled: false,
+            prefix: Some("old-logs/".to_string()),
+            expiration_days: Some(90),
+            abort_incomplete_multipart_days: Some(1),
+            has_other_actions: true,
+        };
+
+        let merged = merge_lifecycle_rule(Some(&existing), &edit).unwrap();
+        assert_eq!(merged.transitions(), existing.transitions());
+        assert_eq!(*merged.status(), ExpirationStatus::Disabled);
+        assert_eq!(lifecycle_rule_prefix(&merged).as_deref(), Some("old-logs/"));
+        assert_eq!(merged.expiration().and_then(|expiration| expiration.days()), Some(90));
+        assert_eq!(
+            merged.abort_incomplete_multipart_upload().and_then(|abort| abort.days_after_initiation()),
+            Some(1)
+        );
+
+        let tagged = LifecycleRule::builder()
+            .id("archive")
+            .status(ExpirationStatus::Enabled)
+            .filter(
+                LifecycleRuleFilter::builder()
+                    .tag(Tag::builder().key("tier").value("cold").build().unwrap())
+                    .build(),
+            )
+            .expiration(LifecycleExpiration::builder().days(365).build())
+            .build()
+            .unwrap();
+        assert!(merge_lifecycle_rule(Some(&tagged), &edit).is_err());
+        let same_prefix = LifecycleRuleConfig { prefix: None, ..edit };
+        let merged = merge_lifecycle_rule(Some(&tagged), &same_prefix).unwrap();
+        assert_eq!(merged.filter(), tagged.filter());
+    }
+
+    #[tokio::test]
+    async fn never_presigns_with_the_customer_key() {
+        let key = R2Uploader::generate_customer_key();
+        let sse_c = uploader(SseConfig::CustomerKey { key: key.clone() }).await;
+        let key_md5 = sse_c.sse_customer_key_md5().unwrap();
+
+        let err = sse_c.presign_upload(&request()).await.unwrap_err().to_string();
+        assert!(!err.contains(&key) && !err.contains(&key_md5));
+
+        let presigned = uploader(SseConfig::S3).await.presign_upload(&request()).await.unwrap();
+        let serialized = serde_json::to_string(&presigned).unwrap();
+        assert!(!serialized.contains(&key) && !serialized.contains(&key_md5));
+        assert!(presigned
+            .headers
+            .keys()
+            .all(|name| !name.starts_with("x-amz-server-side-encryption-customer")));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::types::{UploadRecord, UploadSnippets};
+
+/// Markdown and HTML embeds of `record`. The alt text falls back to the
+/// caption, and a caption is shown below the image.
+pub fn snippets(record: &UploadRecord) -> UploadSnippets {
+    let alt = record
+        .alt_text
+        .as_deref()
+        .or(record.caption.as_deref())
+        .unwrap_or_default();
+
+    UploadSnippets {
+        url: record.url.clone(),
+        markdown: markdown(&record.url, alt, record.caption.as_deref()),
+        html: html(record, alt),
+    }
+}
+
+fn markdown(url: &str, alt: &str, caption: Option<&str>) -> String {
+    let image = format!("![{}]({})", escape_markdown(alt), encode_url(url));
+    match caption {
+        Some(caption) => format!("{}\n\n*{}*", image, escape_markdown(caption)),
+        None => image,
+    }
+}
+
+fn html(record: &UploadRecord, alt: &str) -> String {
+    let mut image = format!(
+        "<img src=\"{}\" alt=\"{}\"",
+        escape_html(&encode_url(&record.url)),
+        escape_html(&collapse_whitespace(alt))
+    );
+    if let (Some(width), Some(height)) = (record.width, record.height) {
+        image.push_str(&format!(" width=\"{}\" height=\"{}\"", width, height));
+    }
+    image.push('>');
+
+    match record.caption.as_deref() {
+        Some(caption) => format!(
+            "<figure>\n  {}\n  <figcaption>{}</figcaption>\n</figure>",
+            image,
+            escape_html(&collapse_whitespace(caption))
+        ),
+        None => image,
+    }
+}
+
+/// Escapes the characters that would end or restyle inline Markdown text,
+/// and those that would start raw HTML or an entity.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in collapse_whitespace(text).chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\\' | '[' | ']' | '*' | '_' | '`' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Joins lines and runs of spaces so text fits on one line or attribute.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Percent-encodes the characters that would end a Markdown link target or
+/// aren't allowed unescaped in a URL attribute.
+fn encode_url(url: &str) -> String {
+    url.replace(' ', "%20").replace('(', "%28").replace(')', "%29")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{UploadSource, UploadStatus};
+
+    fn record(alt_text: Option<&str>, caption: Option<&str>) -> UploadRecord {
+        UploadRecord {
+            id: "upload".to_string(),
+            original_filename: "photo.png".to_string(),
+            file_hash: "hash".to_string(),
+            file_size: 1,
+            url: "https://img.example.com/my photo (1).png".to_string(),
+            upload_time: 0,
+            status: UploadStatus::Active,
+            expires_at: None,
+            content_type: None,
+            profile: None,
+            source: UploadSource::App,
+            bucket: None,
+            object_key: None,
+            width: Some(800),
+            height: Some(600),
+            client: None,
+            original_hash: None,
+            tags: Vec::new(),
+            notes: None,
+            alt_text: alt_text.map(str::to_string),
+            caption: caption.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn escapes_markdown_and_html() {
+        let snippets = snippets(&record(Some("a [link](x) *bold*\nnext"), Some("<b>\"Tom & Jerry\"</b>")));
+        assert_eq!(
+            snippets.markdown,
+            "![a \\[link\\](x) \\*bold\\* next](https://img.example.com/my%20photo%20%281%29.png)\n\n\
+             *&lt;b&gt;\"Tom &amp; Jerry\"&lt;/b&gt;*"
+        );
+        assert_eq!(
+            snippets.html,
+            "<figure>\n  <img src=\"https://img.example.com/my%20photo%20%281%29.png\" \
+             alt=\"a [link](x) *bold* next\" width=\"800\" height=\"600\">\n  \
+             <figcaption>&lt;b&gt;&quot;Tom &amp; Jerry&quot;&lt;/b&gt;</figcaption>\n</figure>"
+        );
+    }
+
+    #[test]
+    fn alt_text_falls_back_to_the_caption() {
+        assert!(snippets(&record(None, Some("Sunset"))).markdown.starts_with("![Sunset]("));
+        let plain = snippets(&record(None, None));
+        assert_eq!(plain.markdown, "![](https://img.example.com/my%20photo%20%281%29.png)");
+        assert!(!plain.html.contains("figure"));
+    }
+}
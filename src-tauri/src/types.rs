@@ -1,9 +1,16 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     pub api_proxy_enabled: bool,
     pub api_proxy_port: u16,
+    /// Confirm with a HEAD request that a deduplicated object still exists
+    /// before returning its cached URL.
+    #[serde(default)]
+    pub verify_cache_with_head: bool,
+    #[serde(default)]
+    pub history_retention: RetentionPolicy,
 }
 
 impl Default for AppSettings {
@@ -11,27 +18,300 @@ impl Default for AppSettings {
         Self {
             api_proxy_enabled: false,
             api_proxy_port: 38123,
+            verify_cache_with_head: false,
+            history_retention: RetentionPolicy::default(),
         }
     }
 }
 
+/// Which history records `prune_upload_history` removes. Records whose
+/// object is still waiting to expire are always kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    #[default]
+    Unlimited,
+    /// Keeps the newest `max_records` records.
+    Count { max_records: usize },
+    /// Keeps records uploaded within the last `max_age_days` days.
+    Age { max_age_days: u64 },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct R2Config {
+    #[serde(default)]
     pub access_key_id: String,
+    #[serde(default)]
     pub secret_access_key: String,
+    /// Session token for temporary inline credentials.
+    #[serde(default)]
+    pub session_token: Option<String>,
+    #[serde(default)]
+    pub credential_source: CredentialSource,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub server_side_encryption: SseConfig,
+    #[serde(default)]
+    pub expiry: ExpiryConfig,
+    #[serde(default)]
+    pub billing: BillingConfig,
     pub endpoint: String,
     pub bucket_name: String,
     pub public_url_base: String,
 }
 
+impl R2Config {
+    /// Identifies the bucket a profile writes to; history, stats and usage
+    /// counters are keyed by it.
+    pub fn profile_key(&self) -> String {
+        format!("{}/{}", self.endpoint.trim_end_matches('/'), self.bucket_name)
+    }
+}
+
+/// Where the S3 client gets its credentials from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CredentialSource {
+    /// `access_key_id` / `secret_access_key` (and `session_token`) from the config.
+    #[default]
+    Inline,
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+    Environment,
+    /// A named profile from `~/.aws/credentials` / `~/.aws/config`.
+    AwsProfile { profile_name: String },
+    /// An external command printing credentials in the `credential_process` JSON format.
+    CredentialProcess { command: String },
+}
+
+/// Server-side encryption requested for objects written by this profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SseConfig {
+    /// Whatever the bucket does by default.
+    #[default]
+    None,
+    /// Provider-managed keys (`x-amz-server-side-encryption: AES256`).
+    S3,
+    /// SSE-C with a base64-encoded 256-bit key we hold. Every read of the
+    /// object must send the same key, so it can't be served publicly.
+    CustomerKey { key: String },
+}
+
+/// Default lifetime of uploads made with this profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiryConfig {
+    /// Applied to uploads that don't ask for their own TTL; `None` keeps
+    /// them forever.
+    #[serde(default)]
+    pub default_ttl_secs: Option<u64>,
+    /// Expiring uploads are stored below this prefix so a bucket lifecycle
+    /// rule can delete them even while the app isn't running.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+/// Price table used to estimate this profile's monthly cost, in USD.
+/// Defaults to Cloudflare R2's published prices and free tier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BillingConfig {
+    pub class_a_price_per_million: f64,
+    pub class_b_price_per_million: f64,
+    pub class_a_free_ops: u64,
+    pub class_b_free_ops: u64,
+    pub storage_price_per_gb_month: f64,
+    pub storage_free_gb: f64,
+    /// Estimated monthly cost that triggers a warning.
+    pub monthly_budget: Option<f64>,
+}
+
+impl Default for BillingConfig {
+    fn default() -> Self {
+        Self {
+            class_a_price_per_million: 4.5,
+            class_b_price_per_million: 0.36,
+            class_a_free_ops: 1_000_000,
+            class_b_free_ops: 10_000_000,
+            storage_price_per_gb_month: 0.015,
+            storage_free_gb: 10.0,
+            monthly_budget: None,
+        }
+    }
+}
+
+/// How the S3 client reaches the endpoint. The defaults connect directly
+/// and trust only the bundled root certificates.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    #[serde(default)]
+    pub proxy: Option<OutboundProxy>,
+    /// Extra trusted roots, each either a path to a PEM file or inline PEM text.
+    #[serde(default)]
+    pub ca_certificates: Vec<String>,
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub read_timeout_secs: Option<u64>,
+}
+
+impl NetworkConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboundProxy {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy URL.
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Hosts, domains (`.example.com`) or CIDR ranges that bypass the proxy.
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadRecord {
     pub id: String,
     pub original_filename: String,
+    /// SHA-256 of the bytes stored in the bucket.
     pub file_hash: String,
     pub file_size: u64,
     pub url: String,
     pub upload_time: i64,
+    #[serde(default)]
+    pub status: UploadStatus,
+    /// Unix time after which the object is deleted from the bucket.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Content type of the original file.
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// `R2Config::profile_key` of the profile the file was uploaded with.
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub source: UploadSource,
+    /// Bucket and key of the stored object. Unknown for records saved
+    /// before they were kept.
+    #[serde(default)]
+    pub bucket: Option<String>,
+    #[serde(default)]
+    pub object_key: Option<String>,
+    /// Pixel size of the original image, if it could be read.
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Program that sent the upload: the app itself, or the `client` given to
+    /// the API proxy (falling back to its User-Agent).
+    #[serde(default)]
+    pub client: Option<String>,
+    /// SHA-256 of the file before processing. Differs from `file_hash` when
+    /// the upload was encrypted, or compressed before it reached the app.
+    #[serde(default)]
+    pub original_hash: Option<String>,
+    /// Sorted by name; managed with `Database::set_upload_tags`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Describes the image for screen readers in generated snippets.
+    #[serde(default)]
+    pub alt_text: Option<String>,
+    /// Shown with the image in generated snippets.
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+/// How a history record came to be.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadSource {
+    /// Uploaded from the app window some other way (e.g. from a URL or the
+    /// image editor), or before the way in was recorded.
+    #[default]
+    App,
+    /// Pasted from the clipboard.
+    Clipboard,
+    /// Dropped onto the app window.
+    Drag,
+    /// Picked with the file dialog.
+    File,
+    /// Uploaded through the local API proxy.
+    Proxy,
+    /// Imported from objects found in the bucket.
+    BucketSync,
+    /// Imported from the gallery of another uploader.
+    #[serde(rename = "picgo")]
+    PicGo,
+    #[serde(rename = "piclist")]
+    PicList,
+    #[serde(rename = "upic")]
+    UPic,
+}
+
+impl UploadSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadSource::App => "app",
+            UploadSource::Clipboard => "clipboard",
+            UploadSource::Drag => "drag",
+            UploadSource::File => "file",
+            UploadSource::Proxy => "proxy",
+            UploadSource::BucketSync => "bucket_sync",
+            UploadSource::PicGo => "picgo",
+            UploadSource::PicList => "piclist",
+            UploadSource::UPic => "upic",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "clipboard" => UploadSource::Clipboard,
+            "drag" => UploadSource::Drag,
+            "file" => UploadSource::File,
+            "proxy" => UploadSource::Proxy,
+            "bucket_sync" => UploadSource::BucketSync,
+            "picgo" => UploadSource::PicGo,
+            "piclist" => UploadSource::PicList,
+            "upic" => UploadSource::UPic,
+            _ => UploadSource::App,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    #[default]
+    Active,
+    /// The object is no longer present in the bucket.
+    Missing,
+    /// The object reached its expiry time and was deleted by the app.
+    Expired,
+}
+
+impl UploadStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadStatus::Active => "active",
+            UploadStatus::Missing => "missing",
+            UploadStatus::Expired => "expired",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "missing" => UploadStatus::Missing,
+            "expired" => UploadStatus::Expired,
+            _ => UploadStatus::Active,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -40,6 +320,35 @@ pub struct UploadResult {
     pub url: Option<String>,
     pub error: Option<String>,
     pub from_cache: bool,
+    /// History entry of the upload, the earlier one on a cache hit.
+    pub record: Option<UploadRecord>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncryptedUploadResult {
+    pub success: bool,
+    /// Public URL of the ciphertext with the key in its fragment.
+    pub share_url: Option<String>,
+    pub object_url: Option<String>,
+    /// Local viewer link, when the API proxy is running.
+    pub view_url: Option<String>,
+    pub error: Option<String>,
+    pub record: Option<UploadRecord>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ExpiryReport {
+    /// Objects deleted from the bucket and marked expired.
+    pub expired: usize,
+    /// Records marked expired whose object was already gone.
+    pub already_missing: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecurityStatus {
+    pub encryption_enabled: bool,
+    pub locked: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -47,4 +356,456 @@ pub struct ApiProxyStatus {
     pub enabled: bool,
     pub running: bool,
     pub port: u16,
+    /// Session token clients must send; set while the proxy is running.
+    pub token: Option<String>,
+}
+
+/// Lifecycle of the shared S3 client, which is built in the background so
+/// startup never waits on the network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum UploaderStatus {
+    Unconfigured,
+    Initializing,
+    Ready,
+    Error {
+        message: String,
+        /// Consecutive failed attempts so far.
+        attempts: u32,
+        /// Seconds until the next automatic retry, if one is scheduled.
+        retry_in_secs: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PresignRequest {
+    /// Exact object key to sign; a random key is generated when omitted.
+    pub key: Option<String>,
+    /// Used only to pick the extension of a generated key.
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PresignedUpload {
+    pub method: String,
+    pub upload_url: String,
+    /// Headers the client must send with the PUT for the signature to match.
+    pub headers: HashMap<String, String>,
+    pub key: String,
+    pub public_url: String,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PresignResult {
+    pub success: bool,
+    pub upload: Option<PresignedUpload>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BucketListRequest {
+    pub prefix: Option<String>,
+    /// Defaults to "/" for folder-style navigation; pass "" for a flat listing.
+    pub delimiter: Option<String>,
+    pub continuation_token: Option<String>,
+    pub max_keys: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistorySort {
+    #[default]
+    Newest,
+    Oldest,
+    Largest,
+    Smallest,
+    Name,
+}
+
+/// Narrows the history; unset fields match everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryFilter {
+    /// Full-text query over filename, URL, tags and notes. Every word must
+    /// match the start of a word in one of them.
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Unix time, inclusive.
+    #[serde(default)]
+    pub uploaded_after: Option<i64>,
+    /// Unix time, exclusive.
+    #[serde(default)]
+    pub uploaded_before: Option<i64>,
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    /// Exact type, or a prefix ending in `/` such as `image/`.
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub source: Option<UploadSource>,
+    #[serde(default)]
+    pub status: Option<UploadStatus>,
+    /// Records carrying every one of these tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Id of an album the records belong to.
+    #[serde(default)]
+    pub album: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryPageRequest {
+    #[serde(default)]
+    pub filter: HistoryFilter,
+    #[serde(default)]
+    pub sort: HistorySort,
+    /// `next_cursor` of the previous page; must be used with the same sort.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryPage {
+    pub records: Vec<UploadRecord>,
+    /// Absent on the last page.
+    pub next_cursor: Option<String>,
+    /// Number of records matching the filter.
+    pub total: usize,
+}
+
+/// Another image uploader whose history can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalHistoryApp {
+    PicGo,
+    PicList,
+    UPic,
+}
+
+impl ExternalHistoryApp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExternalHistoryApp::PicGo => "PicGo",
+            ExternalHistoryApp::PicList => "PicList",
+            ExternalHistoryApp::UPic => "uPic",
+        }
+    }
+
+    pub fn source(&self) -> UploadSource {
+        match self {
+            ExternalHistoryApp::PicGo => UploadSource::PicGo,
+            ExternalHistoryApp::PicList => UploadSource::PicList,
+            ExternalHistoryApp::UPic => UploadSource::UPic,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryExportFormat {
+    Json,
+    Csv,
+    /// A gallery of image embeds; can't be imported again.
+    Markdown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryExport {
+    pub content: String,
+    /// Suggested name of the exported file.
+    pub filename: String,
+    pub record_count: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistoryImportReport {
+    /// Records that weren't in the history yet.
+    pub added: usize,
+    /// Records already present (same URL, or same hash in the same profile)
+    /// that gained tags, notes, alt text or a caption from the import.
+    pub merged: usize,
+    /// Records already present with nothing to add.
+    pub unchanged: usize,
+    pub errors: Vec<String>,
+}
+
+/// An image compressed in the app. Both files are kept by `CompressionStore`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompressionRecord {
+    pub id: String,
+    pub original_name: String,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub compression_ratio: f64,
+    pub compressed_at: i64,
+    /// Compressor settings as sent by the frontend.
+    pub config: serde_json::Value,
+    pub source: UploadSource,
+    /// Of the compressed file.
+    pub content_type: Option<String>,
+    /// Entries moved from the webview's storage may have lost the original.
+    pub has_original: bool,
+    pub original_hash: Option<String>,
+    pub compressed_hash: String,
+    /// Upload of the compressed file, linked when it is uploaded.
+    pub upload_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaveCompressionRequest {
+    pub original_name: String,
+    pub compression_ratio: f64,
+    pub config: serde_json::Value,
+    #[serde(default)]
+    pub source: UploadSource,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Only needed when the original isn't saved.
+    #[serde(default)]
+    pub original_size: Option<u64>,
+    /// Keeps the time of entries moved from the webview's storage.
+    #[serde(default)]
+    pub compressed_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompressionFiles {
+    pub record: CompressionRecord,
+    pub original_data: Option<Vec<u8>>,
+    pub compressed_data: Vec<u8>,
+}
+
+/// Ready-to-paste embeds of an upload.
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadSnippets {
+    pub url: String,
+    pub markdown: String,
+    pub html: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagCount {
+    pub name: String,
+    /// Number of records carrying the tag.
+    pub count: usize,
+}
+
+/// A named group of uploads. Its records are listed through the history
+/// with `HistoryFilter::album`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Album {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: i64,
+    pub upload_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<i64>,
+    pub etag: Option<String>,
+    pub content_type: String,
+    pub url: String,
+}
+
+/// Aggregated usage of one bucket, cached per profile.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BucketStats {
+    pub profile: String,
+    pub bucket_name: String,
+    pub object_count: u64,
+    pub total_bytes: u64,
+    pub free_tier_storage_bytes: u64,
+    /// Grouped by the first path segment; `""` holds objects at the root.
+    pub by_prefix: Vec<UsageGroup>,
+    /// Grouped by `YYYY-MM` of the last modification, oldest first.
+    pub by_month: Vec<UsageGroup>,
+    pub by_content_type: Vec<UsageGroup>,
+    pub largest_objects: Vec<RemoteObject>,
+    pub refreshed_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageGroup {
+    pub name: String,
+    pub object_count: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationClass {
+    ClassA,
+    ClassB,
+    Free,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationCount {
+    /// S3 operation name, e.g. `PutObject`.
+    pub operation: String,
+    pub class: OperationClass,
+    pub count: u64,
+}
+
+/// S3 requests sent for one profile in one month and what they cost.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OperationUsage {
+    pub profile: String,
+    /// `YYYY-MM`, UTC.
+    pub month: String,
+    pub operations: Vec<OperationCount>,
+    pub class_a_ops: u64,
+    pub class_b_ops: u64,
+    pub free_ops: u64,
+    /// Bucket size from the last stats refresh.
+    pub storage_bytes: Option<u64>,
+    pub class_a_cost: f64,
+    pub class_b_cost: f64,
+    pub storage_cost: f64,
+    pub estimated_cost: f64,
+    pub monthly_budget: Option<f64>,
+    pub budget_exceeded: bool,
+}
+
+/// One bucket CORS rule, as sent to `PutBucketCors`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorsRuleConfig {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    #[serde(default)]
+    pub max_age_seconds: Option<i32>,
+}
+
+/// The parts of a bucket lifecycle rule the app can edit.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleRuleConfig {
+    pub id: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Applies to the whole bucket when empty.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub expiration_days: Option<i32>,
+    #[serde(default)]
+    pub abort_incomplete_multipart_days: Option<i32>,
+    /// Set on rules read from the bucket that also do something the app
+    /// can't show (transitions, tag filters, ...); those are kept when the
+    /// rule is edited.
+    #[serde(default)]
+    pub has_other_actions: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorsPreset {
+    /// GET/HEAD from any origin, enough for previews and `<canvas>` use.
+    PublicRead,
+    /// Also allows PUT, for browser uploads through presigned URLs.
+    BrowserUpload,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecyclePreset {
+    /// Aborts multipart uploads left unfinished for a day.
+    AbortIncompleteUploads,
+    /// Removes probe objects a failed connection test may leave behind.
+    CleanupProbeObjects,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BucketListing {
+    pub prefix: String,
+    pub objects: Vec<RemoteObject>,
+    pub folders: Vec<String>,
+    pub next_continuation_token: Option<String>,
+    pub is_truncated: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncHashMode {
+    /// Use the object's ETag as its hash; cheap but not comparable with
+    /// content hashes of files uploaded through the app.
+    #[default]
+    Etag,
+    /// Download each untracked object and hash its content.
+    Download,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SyncOptions {
+    #[serde(default)]
+    pub import_untracked: bool,
+    #[serde(default)]
+    pub hash_mode: SyncHashMode,
+    /// Restrict the reconciliation to keys below this prefix.
+    pub prefix: Option<String>,
+    /// Report differences without touching the database.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub scanned_objects: usize,
+    pub tracked_records: usize,
+    pub matched: usize,
+    /// Records whose object has disappeared from the bucket.
+    pub missing: Vec<UploadRecord>,
+    /// Previously missing records whose object is back.
+    pub restored: Vec<UploadRecord>,
+    /// Bucket objects with no history record.
+    pub untracked: Vec<RemoteObject>,
+    pub imported: Vec<UploadRecord>,
+    /// Records whose URL does not belong to the current public URL base.
+    pub skipped_records: usize,
+    pub errors: Vec<String>,
+    pub started_at: i64,
+    pub finished_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionTestReport {
+    pub success: bool,
+    pub checks: Vec<ConnectionCheck>,
 }
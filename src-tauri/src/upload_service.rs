@@ -0,0 +1,348 @@
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::client_encryption;
+use crate::database::{self, Database};
+use crate::r2_uploader::R2Uploader;
+use crate::types::{UploadRecord, UploadSource, UploadStatus};
+
+/// Per-upload settings, recorded with the history entry.
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    /// From `resolve_expiry`.
+    pub expires_at: Option<i64>,
+    pub source: UploadSource,
+    /// See `UploadRecord::client`.
+    pub client: Option<String>,
+    /// Hash of the file before the caller processed it (e.g. compressed it);
+    /// defaults to the hash of the data handed to the upload.
+    pub original_hash: Option<String>,
+    /// Added to the record, including an earlier one reused from the cache.
+    pub tags: Vec<String>,
+    /// Replace those of an earlier record reused from the cache when set.
+    pub alt_text: Option<String>,
+    pub caption: Option<String>,
+}
+
+pub struct UploadOutcome {
+    pub url: String,
+    pub from_cache: bool,
+    pub record: UploadRecord,
+}
+
+pub struct EncryptedUploadOutcome {
+    pub object_url: String,
+    pub share_url: String,
+    pub record: UploadRecord,
+}
+
+/// Client identifier recorded for uploads made from the app window.
+pub fn app_client() -> String {
+    format!("moli-tutu/{}", env!("CARGO_PKG_VERSION"))
+}
+
+enum CacheLookup {
+    Hit(UploadRecord),
+    /// A record exists but its URL can no longer be served from the current
+    /// profile; the record is reused for the fresh upload.
+    Stale(UploadRecord),
+    Miss,
+}
+
+/// Turns a requested TTL into an expiry time, falling back to the profile's
+/// default. A TTL of zero means the upload never expires.
+pub fn resolve_expiry(uploader: &R2Uploader, ttl_secs: Option<u64>) -> Option<i64> {
+    ttl_secs
+        .or(uploader.config().expiry.default_ttl_secs)
+        .filter(|ttl| *ttl > 0)
+        .map(|ttl| Database::get_current_timestamp().saturating_add(ttl.min(i64::MAX as u64) as i64))
+}
+
+/// Uploads `file_data` unless an identical file was already uploaded to the
+/// current profile and is still reachable, in which case its URL is reused.
+pub async fn upload_with_dedupe(
+    uploader: &R2Uploader,
+    db: &Arc<Mutex<Option<Database>>>,
+    verify_remote: bool,
+    file_data: Vec<u8>,
+    filename: &str,
+    content_type: &str,
+    options: &UploadOptions,
+) -> Result<UploadOutcome> {
+    let expires_at = options.expires_at;
+    let file_hash = R2Uploader::calculate_hash(&file_data);
+
+    let stale_record = match lookup_cached(uploader, db, &file_hash, verify_remote, expires_at).await {
+        CacheLookup::Hit(mut record) => {
+            println!("[UploadService] Cache hit, returning cached URL: {}", record.url);
+            let db_guard = db.lock().unwrap();
+            if let Some(ref db) = *db_guard {
+                if !outlives(record.expires_at, expires_at) {
+                    // Keep the existing object around for as long as this upload asked for
+                    match db.set_upload_expiry(&record.id, expires_at) {
+                        Ok(_) => record.expires_at = expires_at,
+                        Err(e) => println!("[UploadService] Failed to extend expiry: {}", e),
+                    }
+                }
+                if !options.tags.is_empty() {
+                    match db.add_upload_tags(&record.id, &options.tags) {
+                        Ok(_) => record.tags = merged_tags(&record.tags, &options.tags),
+                        Err(e) => println!("[UploadService] Failed to tag cached upload: {}", e),
+                    }
+                }
+                if let Some(alt_text) = &options.alt_text {
+                    match db.set_upload_alt_text(&record.id, Some(alt_text)) {
+                        Ok(_) => record.alt_text = text(Some(alt_text)),
+                        Err(e) => println!("[UploadService] Failed to set alt text: {}", e),
+                    }
+                }
+                if let Some(caption) = &options.caption {
+                    match db.set_upload_caption(&record.id, Some(caption)) {
+                        Ok(_) => record.caption = text(Some(caption)),
+                        Err(e) => println!("[UploadService] Failed to set caption: {}", e),
+                    }
+                }
+                link_compressions(db, &record);
+            }
+            drop(db_guard);
+            return Ok(UploadOutcome {
+                url: record.url.clone(),
+                from_cache: true,
+                record,
+            });
+        }
+        CacheLookup::Stale(record) => {
+            println!("[UploadService] Cached URL is stale, uploading again: {}", record.url);
+            Some(record)
+        }
+        CacheLookup::Miss => None,
+    };
+
+    let file_size = file_data.len() as u64;
+    let dimensions = image_dimensions(&file_data);
+    let key = uploader
+        .upload_file(file_data, filename, content_type, expires_at.is_some())
+        .await?;
+    let url = uploader.public_url(&key);
+
+    // Re-uploading keeps the record's id, tags, notes, alt text and caption.
+    let (id, tags, notes, alt_text, caption) = match stale_record {
+        Some(record) => (
+            record.id,
+            merged_tags(&record.tags, &options.tags),
+            record.notes,
+            text(options.alt_text.as_deref()).or(record.alt_text),
+            text(options.caption.as_deref()).or(record.caption),
+        ),
+        None => (
+            Uuid::new_v4().to_string(),
+            database::normalize_tags(&options.tags),
+            None,
+            text(options.alt_text.as_deref()),
+            text(options.caption.as_deref()),
+        ),
+    };
+    let record = UploadRecord {
+        id,
+        original_filename: filename.to_string(),
+        file_hash: file_hash.clone(),
+        file_size,
+        url: url.clone(),
+        upload_time: Database::get_current_timestamp(),
+        status: UploadStatus::Active,
+        expires_at,
+        content_type: Some(content_type.to_string()),
+        profile: Some(uploader.config().profile_key()),
+        source: options.source,
+        bucket: Some(uploader.config().bucket_name.clone()),
+        object_key: Some(key),
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+        client: options.client.clone(),
+        original_hash: Some(options.original_hash.clone().unwrap_or(file_hash)),
+        tags,
+        notes,
+        alt_text,
+        caption,
+    };
+
+    let db_guard = db.lock().unwrap();
+    if let Some(ref db) = *db_guard {
+        match db.insert_upload_record(&record) {
+            Ok(_) => {
+                println!("[UploadService] Upload record saved successfully");
+                link_compressions(db, &record);
+            }
+            Err(e) => println!("[UploadService] Failed to save upload record: {}", e),
+        }
+    }
+
+    Ok(UploadOutcome {
+        url,
+        from_cache: false,
+        record,
+    })
+}
+
+/// Encrypts `file_data` locally and uploads only the ciphertext. Every call
+/// uses a fresh key, so encrypted uploads are never deduplicated; the history
+/// keeps the share link, key included, so it can be copied again later.
+pub async fn upload_encrypted(
+    uploader: &R2Uploader,
+    db: &Arc<Mutex<Option<Database>>>,
+    file_data: Vec<u8>,
+    filename: &str,
+    content_type: &str,
+    options: &UploadOptions,
+) -> Result<EncryptedUploadOutcome> {
+    let expires_at = options.expires_at;
+    let payload = client_encryption::encrypt_payload(&file_data, content_type)?;
+    println!(
+        "[UploadService] Encrypted {} bytes into {} bytes of ciphertext",
+        file_data.len(),
+        payload.ciphertext.len()
+    );
+
+    let original_hash = options
+        .original_hash
+        .clone()
+        .unwrap_or_else(|| R2Uploader::calculate_hash(&file_data));
+    let dimensions = image_dimensions(&file_data);
+    let file_hash = R2Uploader::calculate_hash(&payload.ciphertext);
+    let file_size = payload.ciphertext.len() as u64;
+    // Neither the name nor the type of the original file reaches the bucket.
+    let key = uploader
+        .upload_file(payload.ciphertext, "encrypted.bin", "application/octet-stream", expires_at.is_some())
+        .await?;
+    let object_url = uploader.public_url(&key);
+    let share_url = client_encryption::share_url(&object_url, &payload.key);
+
+    let record = UploadRecord {
+        id: Uuid::new_v4().to_string(),
+        original_filename: filename.to_string(),
+        file_hash,
+        file_size,
+        url: share_url.clone(),
+        upload_time: Database::get_current_timestamp(),
+        status: UploadStatus::Active,
+        expires_at,
+        content_type: Some(content_type.to_string()),
+        profile: Some(uploader.config().profile_key()),
+        source: options.source,
+        bucket: Some(uploader.config().bucket_name.clone()),
+        object_key: Some(key),
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+        client: options.client.clone(),
+        original_hash: Some(original_hash),
+        tags: database::normalize_tags(&options.tags),
+        notes: None,
+        alt_text: text(options.alt_text.as_deref()),
+        caption: text(options.caption.as_deref()),
+    };
+
+    let db_guard = db.lock().unwrap();
+    if let Some(ref db) = *db_guard {
+        match db.insert_upload_record(&record) {
+            Ok(_) => println!("[UploadService] Encrypted upload record saved successfully"),
+            Err(e) => println!("[UploadService] Failed to save upload record: {}", e),
+        }
+    }
+
+    Ok(EncryptedUploadOutcome {
+        object_url,
+        share_url,
+        record,
+    })
+}
+
+async fn lookup_cached(
+    uploader: &R2Uploader,
+    db: &Arc<Mutex<Option<Database>>>,
+    file_hash: &str,
+    verify_remote: bool,
+    expires_at: Option<i64>,
+) -> CacheLookup {
+    let profile = uploader.config().profile_key();
+    let record = {
+        let db_guard = db.lock().unwrap();
+        match db_guard.as_ref().map(|db| db.find_by_hash(file_hash, &profile)) {
+            Some(Ok(Some(record))) => record,
+            _ => return CacheLookup::Miss,
+        }
+    };
+
+    if record.status != UploadStatus::Active {
+        return CacheLookup::Stale(record);
+    }
+
+    // The profile's public domain may have changed since the record was saved.
+    let key = match uploader.key_from_url(&record.url) {
+        Some(key) => key,
+        None => return CacheLookup::Stale(record),
+    };
+
+    // An object below the expiry prefix is removed by the bucket's lifecycle
+    // rule on its own schedule, so it can't serve a longer-lived upload.
+    if !outlives(record.expires_at, expires_at) && uploader.is_under_expiry_prefix(&key) {
+        return CacheLookup::Stale(record);
+    }
+
+    if verify_remote {
+        match uploader.object_exists(&key).await {
+            Ok(true) => {}
+            Ok(false) => return CacheLookup::Stale(record),
+            Err(e) => {
+                // Don't re-upload just because the check itself failed.
+                println!("[UploadService] Could not verify cached object {}: {}", key, e);
+            }
+        }
+    }
+
+    CacheLookup::Hit(record)
+}
+
+/// Links the compression history entries that produced this file to its
+/// upload, whichever way it was uploaded.
+fn link_compressions(db: &Database, record: &UploadRecord) {
+    match db.link_compressions(&record.file_hash, &record.id) {
+        Ok(0) => {}
+        Ok(linked) => println!("[UploadService] Linked {} compressions to upload {}", linked, record.id),
+        Err(e) => println!("[UploadService] Failed to link compressions: {}", e),
+    }
+}
+
+fn merged_tags(existing: &[String], added: &[String]) -> Vec<String> {
+    let mut tags = database::normalize_tags(existing.iter().chain(added));
+    tags.sort_by_key(|tag| tag.to_lowercase());
+    tags
+}
+
+/// Trimmed text, or `None` if blank, as the database stores it.
+fn text(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Reads the pixel size from the image header, without decoding the image.
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    image::io::Reader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Whether an upload expiring at `existing` lasts at least until `wanted`.
+fn outlives(existing: Option<i64>, wanted: Option<i64>) -> bool {
+    match (existing, wanted) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(existing), Some(wanted)) => existing >= wanted,
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tokio::sync::{watch, Notify};
+
+use crate::operation_meter::OperationMeter;
+use crate::r2_uploader::R2Uploader;
+use crate::types::{R2Config, UploaderStatus};
+
+const RETRY_BASE_SECS: u64 = 5;
+const RETRY_MAX_SECS: u64 = 5 * 60;
+/// How long a caller waits for an in-flight initialization before giving up.
+const READY_WAIT: Duration = Duration::from_secs(15);
+
+#[derive(Clone)]
+enum UploaderState {
+    Unconfigured,
+    Initializing,
+    Ready(Box<R2Uploader>),
+    Error {
+        message: String,
+        attempts: u32,
+        retry_at: Option<Instant>,
+    },
+}
+
+struct Shared {
+    /// Bumped whenever the config changes so a stale background task can't
+    /// overwrite the state of a newer one.
+    generation: Mutex<u64>,
+    state: watch::Sender<UploaderState>,
+    retry_now: Notify,
+    meter: OperationMeter,
+}
+
+impl Shared {
+    fn set_state(&self, generation: u64, state: UploaderState) -> bool {
+        let current = self.generation.lock().unwrap();
+        if *current != generation {
+            return false;
+        }
+        self.state.send_replace(state);
+        true
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        *self.generation.lock().unwrap() == generation
+    }
+}
+
+/// Owns the shared S3 client. The client is built and checked in the
+/// background, and an unreachable endpoint is retried with backoff, so
+/// neither startup nor commands block on a slow or missing network.
+#[derive(Clone)]
+pub struct UploaderManager {
+    shared: Arc<Shared>,
+}
+
+impl UploaderManager {
+    pub fn new(meter: OperationMeter) -> Self {
+        let (state, _) = watch::channel(UploaderState::Unconfigured);
+        Self {
+            shared: Arc::new(Shared {
+                generation: Mutex::new(0),
+                state,
+                retry_now: Notify::new(),
+                meter,
+            }),
+        }
+    }
+
+    /// Starts initializing a client for `config` in the background.
+    pub fn start(&self, config: R2Config) {
+        let generation = self.begin();
+        tauri::async_runtime::spawn(run(self.shared.clone(), generation, config, None));
+    }
+
+    /// Builds the client right away so configuration errors reach the
+    /// caller, then checks that the endpoint is reachable in the background.
+    pub async fn configure(&self, config: R2Config) -> Result<()> {
+        let uploader = R2Uploader::new(config.clone(), &self.shared.meter).await?;
+        let generation = self.begin();
+        tauri::async_runtime::spawn(run(self.shared.clone(), generation, config, Some(uploader)));
+        Ok(())
+    }
+
+    /// Drops the client and stops any pending retries.
+    pub fn reset(&self) {
+        let mut generation = self.shared.generation.lock().unwrap();
+        *generation += 1;
+        self.shared.state.send_replace(UploaderState::Unconfigured);
+        self.shared.retry_now.notify_waiters();
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !matches!(*self.shared.state.borrow(), UploaderState::Unconfigured)
+    }
+
+    pub fn status(&self) -> UploaderStatus {
+        match &*self.shared.state.borrow() {
+            UploaderState::Unconfigured => UploaderStatus::Unconfigured,
+            UploaderState::Initializing => UploaderStatus::Initializing,
+            UploaderState::Ready(_) => UploaderStatus::Ready,
+            UploaderState::Error {
+                message,
+                attempts,
+                retry_at,
+            } => UploaderStatus::Error {
+                message: message.clone(),
+                attempts: *attempts,
+                retry_in_secs: retry_at
+                    .map(|at| at.saturating_duration_since(Instant::now()).as_secs()),
+            },
+        }
+    }
+
+    /// Returns the client, waiting briefly for an in-flight initialization.
+    /// A client waiting to retry is retried immediately instead.
+    pub async fn uploader(&self) -> Result<R2Uploader, String> {
+        let mut receiver = self.shared.state.subscribe();
+
+        let failed_attempts = match &*receiver.borrow() {
+            UploaderState::Error {
+                attempts,
+                retry_at: Some(_),
+                ..
+            } => Some(*attempts),
+            _ => None,
+        };
+        if failed_attempts.is_some() {
+            println!("[UploaderManager] Retrying storage connection on demand");
+            self.shared.retry_now.notify_one();
+        }
+
+        let settled = tokio::time::timeout(
+            READY_WAIT,
+            receiver.wait_for(|state| match state {
+                UploaderState::Initializing => false,
+                UploaderState::Error { attempts, .. } => Some(*attempts) != failed_attempts,
+                _ => true,
+            }),
+        )
+        .await;
+
+        let state = match settled {
+            Ok(Ok(state)) => state.clone(),
+            _ => self.shared.state.borrow().clone(),
+        };
+        match state {
+            UploaderState::Ready(uploader) => Ok(*uploader),
+            UploaderState::Unconfigured => Err("R2 configuration not set".to_string()),
+            UploaderState::Initializing => {
+                Err("Storage client is still initializing, try again shortly".to_string())
+            }
+            UploaderState::Error { message, .. } => Err(message),
+        }
+    }
+
+    fn begin(&self) -> u64 {
+        let mut generation = self.shared.generation.lock().unwrap();
+        *generation += 1;
+        self.shared.state.send_replace(UploaderState::Initializing);
+        self.shared.retry_now.notify_waiters();
+        *generation
+    }
+}
+
+async fn run(shared: Arc<Shared>, generation: u64, config: R2Config, mut prebuilt: Option<R2Uploader>) {
+    let mut attempts = 0;
+    loop {
+        if attempts > 0 && !shared.set_state(generation, UploaderState::Initializing) {
+            return;
+        }
+
+        let built = match prebuilt.take() {
+            Some(uploader) => Ok(uploader),
+            None => R2Uploader::new(config.clone(), &shared.meter).await,
+        };
+        let uploader = match built {
+            Ok(uploader) => uploader,
+            Err(e) => {
+                // An invalid configuration won't fix itself; wait for a new one.
+                println!("[UploaderManager] Failed to build storage client: {}", e);
+                shared.set_state(
+                    generation,
+                    UploaderState::Error {
+                        message: e.to_string(),
+                        attempts: attempts + 1,
+                        retry_at: None,
+                    },
+                );
+                return;
+            }
+        };
+
+        // Any HTTP response, even 403, means the endpoint is reachable;
+        // permission problems surface on the actual request.
+        match uploader.head_bucket_status().await {
+            Ok(status) => {
+                println!("[UploaderManager] Storage endpoint reachable (HeadBucket HTTP {})", status);
+                shared.set_state(generation, UploaderState::Ready(Box::new(uploader)));
+                return;
+            }
+            Err(e) => {
+                attempts += 1;
+                let delay = retry_delay(attempts);
+                println!(
+                    "[UploaderManager] Storage endpoint unreachable (attempt {}), retrying in {}s: {:#}",
+                    attempts, delay.as_secs(), e
+                );
+                let state = UploaderState::Error {
+                    message: format!("Storage endpoint unreachable: {:#}", e),
+                    attempts,
+                    retry_at: Some(Instant::now() + delay),
+                };
+                if !shared.set_state(generation, state) {
+                    return;
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = shared.retry_now.notified() => {}
+                }
+                if !shared.is_current(generation) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    let secs = RETRY_BASE_SECS.saturating_mul(1 << attempts.saturating_sub(1).min(6));
+    Duration::from_secs(secs.min(RETRY_MAX_SECS))
+}
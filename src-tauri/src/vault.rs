@@ -0,0 +1,209 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Prefix marking a config value encrypted with the master key.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+/// Starts a file encrypted with the master key.
+const ENCRYPTED_FILE_MAGIC: &[u8; 4] = b"MLV1";
+/// Known plaintext used to check a passphrase without touching real data.
+const VERIFIER_PLAINTEXT: &str = "moli-tutu-vault";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    salt: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    verifier: String,
+}
+
+/// Keys derived from the master passphrase: one for config secrets, one
+/// for the SQLCipher history database.
+#[derive(Clone)]
+pub struct MasterKey {
+    secrets_key: [u8; 32],
+    database_key: [u8; 32],
+}
+
+impl MasterKey {
+    fn derive(passphrase: &str, salt: &[u8], params: Params) -> Result<Self> {
+        let mut output = [0u8; 64];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut output)
+            .map_err(|e| anyhow::anyhow!("Failed to derive key: {}", e))?;
+
+        let mut secrets_key = [0u8; 32];
+        let mut database_key = [0u8; 32];
+        secrets_key.copy_from_slice(&output[..32]);
+        database_key.copy_from_slice(&output[32..]);
+        Ok(Self {
+            secrets_key,
+            database_key,
+        })
+    }
+
+    pub fn encrypt_string(&self, plaintext: &str) -> Result<String> {
+        let payload = self.seal(plaintext.as_bytes())?;
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, general_purpose::STANDARD.encode(payload)))
+    }
+
+    pub fn decrypt_string(&self, value: &str) -> Result<String> {
+        let encoded = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or_else(|| anyhow::anyhow!("Value is not encrypted"))?;
+        let payload = general_purpose::STANDARD.decode(encoded)?;
+        Ok(String::from_utf8(self.open(&payload)?)?)
+    }
+
+    /// Encrypts the contents of a file kept in the app data dir.
+    pub fn encrypt_file(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut file = ENCRYPTED_FILE_MAGIC.to_vec();
+        file.extend_from_slice(&self.seal(data)?);
+        Ok(file)
+    }
+
+    pub fn decrypt_file(&self, file: &[u8]) -> Result<Vec<u8>> {
+        let payload = file
+            .strip_prefix(ENCRYPTED_FILE_MAGIC.as_slice())
+            .ok_or_else(|| anyhow::anyhow!("File is not encrypted"))?;
+        self.open(payload)
+    }
+
+    /// `nonce || AES-256-GCM(plaintext)` under the secrets key.
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.secrets_key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt secret"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(payload)
+    }
+
+    fn open(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        if payload.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("Encrypted value is truncated"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.secrets_key));
+        cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt secret"))
+    }
+
+    /// Raw key in the `x'...'` form understood by SQLCipher's `PRAGMA key`.
+    pub fn database_key(&self) -> String {
+        let hex: String = self.database_key.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("x'{}'", hex)
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+pub fn is_encrypted_file(file: &[u8]) -> bool {
+    file.starts_with(ENCRYPTED_FILE_MAGIC)
+}
+
+/// The `vault.json` file holding the KDF salt and a passphrase verifier.
+/// Its presence means encryption at rest is enabled.
+pub struct Vault {
+    path: PathBuf,
+}
+
+impl Vault {
+    pub fn new(app_dir: PathBuf) -> Self {
+        Self {
+            path: app_dir.join("vault.json"),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Creates a fresh vault for `passphrase`, replacing any existing one.
+    pub fn create(&self, passphrase: &str) -> Result<MasterKey> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(anyhow::anyhow!(
+                "Passphrase must be at least {} characters",
+                MIN_PASSPHRASE_LEN
+            ));
+        }
+
+        let salt: [u8; SALT_LEN] = rand_bytes();
+        let params = Params::default();
+        let key = MasterKey::derive(passphrase, &salt, params.clone())?;
+
+        let file = VaultFile {
+            version: 1,
+            salt: general_purpose::STANDARD.encode(salt),
+            m_cost: params.m_cost(),
+            t_cost: params.t_cost(),
+            p_cost: params.p_cost(),
+            verifier: key.encrypt_string(VERIFIER_PLAINTEXT)?,
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&file)?)?;
+        Ok(key)
+    }
+
+    pub fn unlock(&self, passphrase: &str) -> Result<MasterKey> {
+        let content = fs::read_to_string(&self.path)?;
+        let file: VaultFile = serde_json::from_str(&content)?;
+        if file.version != 1 {
+            return Err(anyhow::anyhow!("Unsupported vault version {}", file.version));
+        }
+
+        let salt = general_purpose::STANDARD.decode(&file.salt)?;
+        let params = Params::new(file.m_cost, file.t_cost, file.p_cost, None)
+            .map_err(|e| anyhow::anyhow!("Invalid vault parameters: {}", e))?;
+        let key = MasterKey::derive(passphrase, &salt, params)?;
+
+        match key.decrypt_string(&file.verifier) {
+            Ok(plaintext) if plaintext == VERIFIER_PLAINTEXT => Ok(key),
+            _ => Err(anyhow::anyhow!("Incorrect passphrase")),
+        }
+    }
+
+    /// Raw vault file contents, for rolling back a failed passphrase change.
+    pub fn snapshot(&self) -> Result<String> {
+        Ok(fs::read_to_string(&self.path)?)
+    }
+
+    pub fn restore(&self, snapshot: &str) -> Result<()> {
+        fs::write(&self.path, snapshot)?;
+        Ok(())
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+fn rand_bytes<const N: usize>() -> [u8; N] {
+    use aes_gcm::aead::rand_core::RngCore;
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
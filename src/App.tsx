@@ -1,15 +1,21 @@
 import { useState, useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
 import { CheckCircle, XCircle, AlertCircle } from "lucide-react";
 import { Sidebar } from "./components/Sidebar";
 import MainContent from "./components/MainContent";
-import { UploadResult, UploadRecord } from "./types";
+import { UnlockDialog } from "./components/UnlockDialog";
+import { OperationUsage, UploadResult, UploadRecord } from "./types";
 import { ImageHostingAPI } from "./api";
 import { useLanguage } from "./contexts/LanguageContext";
 
+// 上传页只展示最近的记录
+const RECENT_HISTORY_LIMIT = 10;
+
 function App() {
   const { t } = useLanguage();
   const [activeTab, setActiveTab] = useState<'upload' | 'history' | 'config' | 'compress' | 'compress-history'>('upload');
   const [configExists, setConfigExists] = useState(false);
+  const [locked, setLocked] = useState(false);
   const [notification, setNotification] = useState<{
     message: string;
     type: 'success' | 'error' | 'info';
@@ -48,36 +54,72 @@ function App() {
     }
   }, []);
 
+  // 检查配置和加载历史记录
+  const loadInitialData = async () => {
+    // 异步检查配置
+    try {
+      const config = await ImageHostingAPI.getR2Config();
+      setConfigExists(!!config);
+    } catch (error) {
+      console.error('Failed to check config:', error);
+    }
+
+    // 异步加载上传历史
+    try {
+      const history = await ImageHostingAPI.getUploadHistory(RECENT_HISTORY_LIMIT);
+      setUploadHistory(history);
+    } catch (error) {
+      console.error('Failed to load upload history:', error);
+    }
+  };
+
   // 异步检查配置和加载历史记录
   useEffect(() => {
     // 使用 setTimeout 让页面先渲染，然后异步执行校验
     const asyncInit = () => {
       setTimeout(async () => {
-        // 异步检查配置
+        // 启用加密时需先解锁
         try {
-          const config = await ImageHostingAPI.getR2Config();
-          setConfigExists(!!config);
+          const status = await ImageHostingAPI.getSecurityStatus();
+          if (status.locked) {
+            setLocked(true);
+            return;
+          }
         } catch (error) {
-          console.error('Failed to check config:', error);
+          console.error('Failed to get security status:', error);
         }
 
-        // 异步加载上传历史
-        try {
-          const history = await ImageHostingAPI.getUploadHistory();
-          setUploadHistory(history);
-        } catch (error) {
-          console.error('Failed to load upload history:', error);
-        }
+        await loadInitialData();
       }, 0); // 使用 0ms 延迟，让页面先渲染
     };
 
     asyncInit();
   }, []);
 
+  // 本月预估费用超出预算时提醒
+  useEffect(() => {
+    const unlisten = listen<OperationUsage>("operation-budget-exceeded", (event) => {
+      const usage = event.payload;
+      showNotification(
+        `${t.notification.budgetExceeded}: $${usage.estimated_cost.toFixed(2)} / $${(usage.monthly_budget ?? 0).toFixed(2)} (${usage.month})`,
+        'error'
+      );
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [t]);
+
+  const handleUnlocked = () => {
+    setLocked(false);
+    loadInitialData();
+    setRefreshTrigger(prev => prev + 1);
+  };
+
   const handleUploadSuccess = (_result: UploadResult) => {
     showNotification(t.notification.uploadSuccess, 'success');
     // 重新加载历史记录
-    ImageHostingAPI.getUploadHistory().then(setUploadHistory);
+    ImageHostingAPI.getUploadHistory(RECENT_HISTORY_LIMIT).then(setUploadHistory);
     // 触发历史记录组件刷新
     setRefreshTrigger(prev => prev + 1);
   };
@@ -123,6 +165,8 @@ function App() {
           />
         </div>
 
+        {locked && <UnlockDialog onUnlocked={handleUnlocked} />}
+
         {/* Notifications */}
         {notification && (
           <div className="toast toast-top toast-end z-50 mt-12 mr-4">
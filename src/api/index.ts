@@ -1,5 +1,42 @@
 import { invoke } from "@tauri-apps/api/core";
-import { ApiProxyStatus, R2Config, UploadRecord, UploadResult } from "../types";
+import {
+  Album,
+  ApiProxyStatus,
+  AppSettings,
+  BucketListRequest,
+  BucketListing,
+  BucketStats,
+  ConnectionTestReport,
+  CorsPreset,
+  CorsRuleConfig,
+  EncryptedUploadResult,
+  ExpiryReport,
+  ExternalHistoryApp,
+  HistoryExport,
+  HistoryExportFormat,
+  HistoryFilter,
+  HistoryImportReport,
+  HistoryPage,
+  HistoryPageRequest,
+  LifecyclePreset,
+  LifecycleRuleConfig,
+  OperationUsage,
+  PresignRequest,
+  PresignedUpload,
+  R2Config,
+  RemoteObject,
+  RetentionPolicy,
+  SecurityStatus,
+  SyncOptions,
+  SyncReport,
+  TagCount,
+  UploadRecord,
+  UploadResult,
+  UploadSnippets,
+  UploadSource,
+  UploaderStatus,
+} from "../types";
+import { CompressRecord, CompressionFiles, SaveCompressionRequest } from "../types/compress";
 
 export class ImageHostingAPI {
   static async saveR2Config(config: R2Config): Promise<void> {
@@ -32,6 +69,142 @@ export class ImageHostingAPI {
     }
   }
 
+  static async generateSseCustomerKey(): Promise<string> {
+    console.log('[API] Generating SSE-C customer key...');
+    try {
+      const result = await invoke("generate_sse_customer_key");
+      return result as string;
+    } catch (error) {
+      console.error('[API] Failed to generate SSE-C customer key:', error);
+      throw error;
+    }
+  }
+
+  static async testStorageConnection(config?: R2Config): Promise<ConnectionTestReport> {
+    console.log('[API] Testing storage connection:', config ? { endpoint: config.endpoint, bucket: config.bucket_name } : 'saved config');
+    try {
+      const result = await invoke("test_storage_connection", { config });
+      const report = result as ConnectionTestReport;
+      console.log('[API] Storage connection test completed:', { success: report.success });
+      return report;
+    } catch (error) {
+      console.error('[API] Failed to test storage connection:', error);
+      throw error;
+    }
+  }
+
+  static async getUploaderStatus(): Promise<UploaderStatus> {
+    try {
+      const result = await invoke("get_uploader_status");
+      return result as UploaderStatus;
+    } catch (error) {
+      console.error('[API] Failed to get uploader status:', error);
+      throw error;
+    }
+  }
+
+  static async purgeExpiredUploads(): Promise<ExpiryReport> {
+    console.log('[API] Purging expired uploads...');
+    try {
+      const result = await invoke("purge_expired_uploads");
+      return result as ExpiryReport;
+    } catch (error) {
+      console.error('[API] Failed to purge expired uploads:', error);
+      throw error;
+    }
+  }
+
+  /** Returns the number of days the lifecycle rule was set to. */
+  static async applyExpiryLifecycleRule(days?: number): Promise<number> {
+    console.log('[API] Applying expiry lifecycle rule:', days);
+    try {
+      const result = await invoke("apply_expiry_lifecycle_rule", { days });
+      return result as number;
+    } catch (error) {
+      console.error('[API] Failed to apply expiry lifecycle rule:', error);
+      throw error;
+    }
+  }
+
+  static async getBucketCors(): Promise<CorsRuleConfig[]> {
+    console.log('[API] Getting bucket CORS rules...');
+    try {
+      const result = await invoke("get_bucket_cors");
+      return result as CorsRuleConfig[];
+    } catch (error) {
+      console.error('[API] Failed to get bucket CORS rules:', error);
+      throw error;
+    }
+  }
+
+  /** Replaces the bucket's CORS rules; an empty list removes them. */
+  static async setBucketCors(rules: CorsRuleConfig[]): Promise<void> {
+    console.log('[API] Setting bucket CORS rules:', rules.length);
+    try {
+      await invoke("set_bucket_cors", { rules });
+    } catch (error) {
+      console.error('[API] Failed to set bucket CORS rules:', error);
+      throw error;
+    }
+  }
+
+  /** origins defaults to any origin. */
+  static async applyCorsPreset(preset: CorsPreset, origins?: string[]): Promise<CorsRuleConfig[]> {
+    console.log('[API] Applying CORS preset:', preset);
+    try {
+      const result = await invoke("apply_cors_preset", { preset, origins });
+      return result as CorsRuleConfig[];
+    } catch (error) {
+      console.error('[API] Failed to apply CORS preset:', error);
+      throw error;
+    }
+  }
+
+  static async getLifecycleRules(): Promise<LifecycleRuleConfig[]> {
+    console.log('[API] Getting lifecycle rules...');
+    try {
+      const result = await invoke("get_lifecycle_rules");
+      return result as LifecycleRuleConfig[];
+    } catch (error) {
+      console.error('[API] Failed to get lifecycle rules:', error);
+      throw error;
+    }
+  }
+
+  /** Adds the rule, replacing any rule with the same ID. */
+  static async putLifecycleRule(rule: LifecycleRuleConfig): Promise<void> {
+    console.log('[API] Saving lifecycle rule:', rule.id);
+    try {
+      await invoke("put_lifecycle_rule", { rule });
+    } catch (error) {
+      console.error('[API] Failed to save lifecycle rule:', error);
+      throw error;
+    }
+  }
+
+  /** Returns false if no rule had this ID. */
+  static async deleteLifecycleRule(id: string): Promise<boolean> {
+    console.log('[API] Deleting lifecycle rule:', id);
+    try {
+      const result = await invoke("delete_lifecycle_rule", { id });
+      return result as boolean;
+    } catch (error) {
+      console.error('[API] Failed to delete lifecycle rule:', error);
+      throw error;
+    }
+  }
+
+  static async applyLifecyclePreset(preset: LifecyclePreset): Promise<LifecycleRuleConfig> {
+    console.log('[API] Applying lifecycle preset:', preset);
+    try {
+      const result = await invoke("apply_lifecycle_preset", { preset });
+      return result as LifecycleRuleConfig;
+    } catch (error) {
+      console.error('[API] Failed to apply lifecycle preset:', error);
+      throw error;
+    }
+  }
+
   static async getApiProxyStatus(): Promise<ApiProxyStatus> {
     console.log('[API] Getting API proxy status...');
     try {
@@ -54,12 +227,131 @@ export class ImageHostingAPI {
     }
   }
 
+  static async getAppSettings(): Promise<AppSettings> {
+    console.log('[API] Getting app settings...');
+    try {
+      const result = await invoke("get_app_settings");
+      return result as AppSettings;
+    } catch (error) {
+      console.error('[API] Failed to get app settings:', error);
+      throw error;
+    }
+  }
+
+  static async setCacheVerification(verifyWithHead: boolean): Promise<AppSettings> {
+    console.log('[API] Setting cache verification:', { verifyWithHead });
+    try {
+      const result = await invoke("set_cache_verification", { verifyWithHead });
+      return result as AppSettings;
+    } catch (error) {
+      console.error('[API] Failed to set cache verification:', error);
+      throw error;
+    }
+  }
+
+  static async getSecurityStatus(): Promise<SecurityStatus> {
+    try {
+      const result = await invoke("get_security_status");
+      return result as SecurityStatus;
+    } catch (error) {
+      console.error('[API] Failed to get security status:', error);
+      throw error;
+    }
+  }
+
+  static async unlockApp(passphrase: string): Promise<SecurityStatus> {
+    console.log('[API] Unlocking app...');
+    try {
+      const result = await invoke("unlock_app", { passphrase });
+      return result as SecurityStatus;
+    } catch (error) {
+      console.error('[API] Failed to unlock app:', error);
+      throw error;
+    }
+  }
+
+  static async lockApp(): Promise<SecurityStatus> {
+    console.log('[API] Locking app...');
+    try {
+      const result = await invoke("lock_app");
+      return result as SecurityStatus;
+    } catch (error) {
+      console.error('[API] Failed to lock app:', error);
+      throw error;
+    }
+  }
+
+  static async enableEncryption(passphrase: string): Promise<SecurityStatus> {
+    console.log('[API] Enabling encryption at rest...');
+    try {
+      const result = await invoke("enable_encryption", { passphrase });
+      return result as SecurityStatus;
+    } catch (error) {
+      console.error('[API] Failed to enable encryption:', error);
+      throw error;
+    }
+  }
+
+  static async changeMasterPassphrase(currentPassphrase: string, newPassphrase: string): Promise<SecurityStatus> {
+    console.log('[API] Changing master passphrase...');
+    try {
+      const result = await invoke("change_master_passphrase", { currentPassphrase, newPassphrase });
+      return result as SecurityStatus;
+    } catch (error) {
+      console.error('[API] Failed to change master passphrase:', error);
+      throw error;
+    }
+  }
+
+  static async disableEncryption(passphrase: string): Promise<SecurityStatus> {
+    console.log('[API] Disabling encryption at rest...');
+    try {
+      const result = await invoke("disable_encryption", { passphrase });
+      return result as SecurityStatus;
+    } catch (error) {
+      console.error('[API] Failed to disable encryption:', error);
+      throw error;
+    }
+  }
+
+  static async uploadEncryptedImage(
+    fileData: Uint8Array,
+    filename: string,
+    ttlSecs?: number,
+    source?: UploadSource
+  ): Promise<EncryptedUploadResult> {
+    console.log('[API] Starting upload_encrypted_image invoke:', { filename, dataSize: fileData.length, source });
+    try {
+      const result = await invoke("upload_encrypted_image", {
+        fileData: Array.from(fileData),
+        filename,
+        ttlSecs,
+        source
+      });
+      return result as EncryptedUploadResult;
+    } catch (error) {
+      console.error('[API] Encrypted upload invoke failed:', error);
+      return {
+        success: false,
+        share_url: null,
+        object_url: null,
+        view_url: null,
+        error: error instanceof Error ? error.message : 'Upload failed'
+      };
+    }
+  }
+
+  /** ttlSecs overrides the profile's default TTL; 0 keeps the upload forever. */
   static async uploadImage(
     fileData: Uint8Array,
-    filename: string
+    filename: string,
+    ttlSecs?: number,
+    source?: UploadSource,
+    originalHash?: string
   ): Promise<UploadResult> {
     console.log('[API] Starting upload_image invoke:', {
       filename,
+      source,
       dataSize: fileData.length,
       arrayLength: Array.from(fileData).length
     });
@@ -68,7 +360,10 @@ export class ImageHostingAPI {
       const startTime = Date.now();
       const result = await invoke("upload_image", { 
         fileData: Array.from(fileData), 
-        filename 
+        filename,
+        ttlSecs,
+        source,
+        originalHash
       });
       const endTime = Date.now();
       
@@ -100,10 +395,103 @@ export class ImageHostingAPI {
     }
   }
 
-  static async getUploadHistory(): Promise<UploadRecord[]> {
+  static async createPresignedUpload(request: PresignRequest): Promise<PresignedUpload> {
+    console.log('[API] Creating presigned upload:', { key: request.key, contentType: request.content_type });
+    try {
+      const result = await invoke("create_presigned_upload", { request });
+      return result as PresignedUpload;
+    } catch (error) {
+      console.error('[API] Failed to create presigned upload:', error);
+      throw error;
+    }
+  }
+
+  static async listBucketObjects(request?: BucketListRequest): Promise<BucketListing> {
+    console.log('[API] Listing bucket objects:', request);
+    try {
+      const result = await invoke("list_bucket_objects", { request });
+      const listing = result as BucketListing;
+      console.log('[API] Bucket listing retrieved:', `${listing.objects.length} objects, ${listing.folders.length} folders`);
+      return listing;
+    } catch (error) {
+      console.error('[API] Failed to list bucket objects:', error);
+      throw error;
+    }
+  }
+
+  static async getRemoteObject(key: string): Promise<RemoteObject> {
+    console.log('[API] Getting remote object metadata:', key);
+    try {
+      const result = await invoke("get_remote_object", { key });
+      return result as RemoteObject;
+    } catch (error) {
+      console.error('[API] Failed to get remote object metadata:', error);
+      throw error;
+    }
+  }
+
+  static async syncHistoryWithBucket(options?: SyncOptions): Promise<SyncReport> {
+    console.log('[API] Syncing history with bucket:', options);
+    try {
+      const result = await invoke("sync_history_with_bucket", { options });
+      const report = result as SyncReport;
+      console.log('[API] History sync completed:', {
+        matched: report.matched,
+        missing: report.missing.length,
+        imported: report.imported.length
+      });
+      return report;
+    } catch (error) {
+      console.error('[API] Failed to sync history with bucket:', error);
+      throw error;
+    }
+  }
+
+  /** Returns the cached stats of the configured bucket, or null if never computed. */
+  static async getBucketStats(): Promise<BucketStats | null> {
+    console.log('[API] Getting cached bucket stats...');
+    try {
+      const result = await invoke("get_bucket_stats");
+      return result as BucketStats | null;
+    } catch (error) {
+      console.error('[API] Failed to get bucket stats:', error);
+      throw error;
+    }
+  }
+
+  static async refreshBucketStats(): Promise<BucketStats> {
+    console.log('[API] Refreshing bucket stats...');
+    try {
+      const result = await invoke("refresh_bucket_stats");
+      const stats = result as BucketStats;
+      console.log('[API] Bucket stats refreshed:', {
+        objects: stats.object_count,
+        bytes: stats.total_bytes
+      });
+      return stats;
+    } catch (error) {
+      console.error('[API] Failed to refresh bucket stats:', error);
+      throw error;
+    }
+  }
+
+  /** month is YYYY-MM (UTC); defaults to the current month. */
+  static async getOperationUsage(month?: string): Promise<OperationUsage> {
+    console.log('[API] Getting operation usage:', month ?? 'current month');
+    try {
+      const result = await invoke("get_operation_usage", { month });
+      return result as OperationUsage;
+    } catch (error) {
+      console.error('[API] Failed to get operation usage:', error);
+      throw error;
+    }
+  }
+
+  /** Newest `limit` records; the whole history when omitted. */
+  static async getUploadHistory(limit?: number): Promise<UploadRecord[]> {
     console.log('[API] Getting upload history...');
     try {
-      const result = await invoke("get_upload_history");
+      const result = await invoke("get_upload_history", { limit });
       const records = result as UploadRecord[] || [];
       console.log('[API] Upload history retrieved:', `${records.length} records`);
       return records;
@@ -113,6 +501,311 @@ export class ImageHostingAPI {
     }
   }
 
+  static async getUploadHistoryPage(request?: HistoryPageRequest): Promise<HistoryPage> {
+    console.log('[API] Getting upload history page:', request);
+    try {
+      const result = await invoke("get_upload_history_page", { request });
+      return result as HistoryPage;
+    } catch (error) {
+      console.error('[API] Failed to get upload history page:', error);
+      throw error;
+    }
+  }
+
+  /** Every upload of the same file, one per profile it was uploaded to. */
+  static async getContentUploads(fileHash: string): Promise<UploadRecord[]> {
+    console.log('[API] Getting uploads of content:', fileHash);
+    try {
+      const result = await invoke("get_content_uploads", { fileHash });
+      return result as UploadRecord[];
+    } catch (error) {
+      console.error('[API] Failed to get content uploads:', error);
+      throw error;
+    }
+  }
+
+  static async listTags(): Promise<TagCount[]> {
+    console.log('[API] Listing tags');
+    try {
+      const result = await invoke("list_tags");
+      return result as TagCount[];
+    } catch (error) {
+      console.error('[API] Failed to list tags:', error);
+      throw error;
+    }
+  }
+
+  /** Replaces the tags of a record; returns them as saved. */
+  static async setUploadTags(id: string, tags: string[]): Promise<string[]> {
+    console.log('[API] Setting upload tags:', { id, tags });
+    try {
+      const result = await invoke("set_upload_tags", { id, tags });
+      return result as string[];
+    } catch (error) {
+      console.error('[API] Failed to set upload tags:', error);
+      throw error;
+    }
+  }
+
+  /** Returns the number of records affected. */
+  static async renameTag(from: string, to: string): Promise<number> {
+    console.log('[API] Renaming tag:', { from, to });
+    try {
+      const result = await invoke("rename_tag", { from, to });
+      return result as number;
+    } catch (error) {
+      console.error('[API] Failed to rename tag:', error);
+      throw error;
+    }
+  }
+
+  /** Returns the number of records affected. */
+  static async deleteTag(tag: string): Promise<number> {
+    console.log('[API] Deleting tag:', tag);
+    try {
+      const result = await invoke("delete_tag", { tag });
+      return result as number;
+    } catch (error) {
+      console.error('[API] Failed to delete tag:', error);
+      throw error;
+    }
+  }
+
+  /** Empty notes clear them. */
+  static async setUploadNotes(id: string, notes: string | null): Promise<void> {
+    console.log('[API] Setting upload notes:', id);
+    try {
+      await invoke("set_upload_notes", { id, notes });
+    } catch (error) {
+      console.error('[API] Failed to set upload notes:', error);
+      throw error;
+    }
+  }
+
+  /** Empty alt text clears it. */
+  static async setUploadAltText(id: string, altText: string | null): Promise<void> {
+    console.log('[API] Setting upload alt text:', id);
+    try {
+      await invoke("set_upload_alt_text", { id, altText });
+    } catch (error) {
+      console.error('[API] Failed to set upload alt text:', error);
+      throw error;
+    }
+  }
+
+  /** An empty caption clears it. */
+  static async setUploadCaption(id: string, caption: string | null): Promise<void> {
+    console.log('[API] Setting upload caption:', id);
+    try {
+      await invoke("set_upload_caption", { id, caption });
+    } catch (error) {
+      console.error('[API] Failed to set upload caption:', error);
+      throw error;
+    }
+  }
+
+  /** Without a filter the whole history is exported. */
+  static async exportUploadHistory(format: HistoryExportFormat, filter?: HistoryFilter): Promise<HistoryExport> {
+    console.log('[API] Exporting upload history:', format);
+    try {
+      const result = await invoke("export_upload_history", { format, filter });
+      return result as HistoryExport;
+    } catch (error) {
+      console.error('[API] Failed to export upload history:', error);
+      throw error;
+    }
+  }
+
+  /** Markdown exports can't be imported. */
+  static async importUploadHistory(content: string, format: HistoryExportFormat): Promise<HistoryImportReport> {
+    console.log('[API] Importing upload history:', format);
+    try {
+      const result = await invoke("import_upload_history", { content, format });
+      return result as HistoryImportReport;
+    } catch (error) {
+      console.error('[API] Failed to import upload history:', error);
+      throw error;
+    }
+  }
+
+  /** Content of PicGo/PicList `data.json` or a uPic history export. */
+  static async importExternalHistory(app: ExternalHistoryApp, content: string): Promise<HistoryImportReport> {
+    console.log('[API] Importing external history:', app);
+    try {
+      const result = await invoke("import_external_history", { app, content });
+      return result as HistoryImportReport;
+    } catch (error) {
+      console.error('[API] Failed to import external history:', error);
+      throw error;
+    }
+  }
+
+  /** Without the original, request.original_size should be set. */
+  static async saveCompression(
+    request: SaveCompressionRequest,
+    originalData: Uint8Array | null,
+    compressedData: Uint8Array
+  ): Promise<CompressRecord> {
+    console.log('[API] Saving compression:', request.original_name);
+    try {
+      const result = await invoke("save_compression", {
+        request,
+        originalData: originalData ? Array.from(originalData) : null,
+        compressedData: Array.from(compressedData),
+      });
+      return result as CompressRecord;
+    } catch (error) {
+      console.error('[API] Failed to save compression:', error);
+      throw error;
+    }
+  }
+
+  static async listCompressions(): Promise<CompressRecord[]> {
+    console.log('[API] Listing compressions');
+    try {
+      const result = await invoke("list_compressions");
+      return result as CompressRecord[];
+    } catch (error) {
+      console.error('[API] Failed to list compressions:', error);
+      throw error;
+    }
+  }
+
+  static async restoreCompression(id: string): Promise<CompressionFiles> {
+    console.log('[API] Restoring compression:', id);
+    try {
+      const result = await invoke("restore_compression", { id });
+      return result as CompressionFiles;
+    } catch (error) {
+      console.error('[API] Failed to restore compression:', error);
+      throw error;
+    }
+  }
+
+  static async deleteCompression(id: string): Promise<void> {
+    console.log('[API] Deleting compression:', id);
+    try {
+      await invoke("delete_compression", { id });
+    } catch (error) {
+      console.error('[API] Failed to delete compression:', error);
+      throw error;
+    }
+  }
+
+  /** Returns the number of records removed. */
+  static async clearCompressions(): Promise<number> {
+    console.log('[API] Clearing compressions');
+    try {
+      const result = await invoke("clear_compressions");
+      return result as number;
+    } catch (error) {
+      console.error('[API] Failed to clear compressions:', error);
+      throw error;
+    }
+  }
+
+  static async getUploadSnippets(id: string): Promise<UploadSnippets> {
+    console.log('[API] Getting upload snippets:', id);
+    try {
+      const result = await invoke("get_upload_snippets", { id });
+      return result as UploadSnippets;
+    } catch (error) {
+      console.error('[API] Failed to get upload snippets:', error);
+      throw error;
+    }
+  }
+
+  static async listAlbums(): Promise<Album[]> {
+    console.log('[API] Listing albums');
+    try {
+      const result = await invoke("list_albums");
+      return result as Album[];
+    } catch (error) {
+      console.error('[API] Failed to list albums:', error);
+      throw error;
+    }
+  }
+
+  static async createAlbum(name: string, description?: string): Promise<Album> {
+    console.log('[API] Creating album:', name);
+    try {
+      const result = await invoke("create_album", { name, description });
+      return result as Album;
+    } catch (error) {
+      console.error('[API] Failed to create album:', error);
+      throw error;
+    }
+  }
+
+  static async updateAlbum(id: string, name: string, description?: string): Promise<Album> {
+    console.log('[API] Updating album:', { id, name });
+    try {
+      const result = await invoke("update_album", { id, name, description });
+      return result as Album;
+    } catch (error) {
+      console.error('[API] Failed to update album:', error);
+      throw error;
+    }
+  }
+
+  /** The uploads in the album stay in the history. */
+  static async deleteAlbum(id: string): Promise<void> {
+    console.log('[API] Deleting album:', id);
+    try {
+      await invoke("delete_album", { id });
+    } catch (error) {
+      console.error('[API] Failed to delete album:', error);
+      throw error;
+    }
+  }
+
+  /** Returns the number of uploads added. */
+  static async addToAlbum(albumId: string, uploadIds: string[]): Promise<number> {
+    console.log('[API] Adding uploads to album:', { albumId, count: uploadIds.length });
+    try {
+      const result = await invoke("add_to_album", { albumId, uploadIds });
+      return result as number;
+    } catch (error) {
+      console.error('[API] Failed to add uploads to album:', error);
+      throw error;
+    }
+  }
+
+  /** Returns the number of uploads removed. */
+  static async removeFromAlbum(albumId: string, uploadIds: string[]): Promise<number> {
+    console.log('[API] Removing uploads from album:', { albumId, count: uploadIds.length });
+    try {
+      const result = await invoke("remove_from_album", { albumId, uploadIds });
+      return result as number;
+    } catch (error) {
+      console.error('[API] Failed to remove uploads from album:', error);
+      throw error;
+    }
+  }
+
+  /** Saves the policy and prunes right away; returns the number of records removed. */
+  static async setHistoryRetention(policy: RetentionPolicy): Promise<number> {
+    console.log('[API] Setting history retention:', policy);
+    try {
+      const result = await invoke("set_history_retention", { policy });
+      return result as number;
+    } catch (error) {
+      console.error('[API] Failed to set history retention:', error);
+      throw error;
+    }
+  }
+
+  static async pruneUploadHistory(): Promise<number> {
+    console.log('[API] Pruning upload history...');
+    try {
+      const result = await invoke("prune_upload_history");
+      return result as number;
+    } catch (error) {
+      console.error('[API] Failed to prune upload history:', error);
+      throw error;
+    }
+  }
+
   static async clearUploadHistory(): Promise<void> {
     console.log('[API] Clearing upload history...');
     try {
@@ -159,6 +852,14 @@ export class ImageHostingAPI {
     }
   }
 
+  /** Hex SHA-256, in the same form as UploadRecord.file_hash. */
+  static async hashFile(file: Blob): Promise<string> {
+    const digest = await crypto.subtle.digest('SHA-256', await file.arrayBuffer());
+    return Array.from(new Uint8Array(digest))
+      .map((byte) => byte.toString(16).padStart(2, '0'))
+      .join('');
+  }
+
   static async convertFileToUint8Array(file: File): Promise<Uint8Array> {
     console.log('[API] Converting file to Uint8Array:', {
       name: file.name,
@@ -5,8 +5,7 @@ import {
   getCompressRecords,
   deleteCompressRecord,
   restoreImagesFromRecord,
-  clearAllCompressRecords,
-  testLocalStorage
+  clearAllCompressRecords
 } from "../utils/compressStorage";
 import { useLanguage } from "../contexts/LanguageContext";
 
@@ -25,34 +24,19 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
   const [deleteConfirmRecord, setDeleteConfirmRecord] = useState<CompressRecord | null>(null);
   const [showClearAllConfirm, setShowClearAllConfirm] = useState(false);
 
-  // 从本地存储加载压缩记录
+  // 从数据库加载压缩记录
   useEffect(() => {
-    const loadRecords = () => {
-      // 测试 localStorage
-      const isStorageWorking = testLocalStorage();
-      if (!isStorageWorking) {
-        console.error('localStorage 不可用！');
-        alert('localStorage 不可用，请检查浏览器设置');
-        return;
+    const loadRecords = async () => {
+      try {
+        const storedRecords = await getCompressRecords();
+        console.log('加载压缩记录:', storedRecords.length, '条记录');
+        setRecords(storedRecords);
+      } catch (error) {
+        console.error('加载压缩记录失败:', error);
       }
-
-      const storedRecords = getCompressRecords();
-      console.log('加载压缩记录:', storedRecords.length, '条记录');
-      setRecords(storedRecords);
     };
 
     loadRecords();
-
-    // 监听存储变化
-    const handleStorageChange = (e: StorageEvent) => {
-      if (e.key === 'compress_history') {
-        console.log('检测到存储变化，重新加载记录');
-        loadRecords();
-      }
-    };
-
-    window.addEventListener('storage', handleStorageChange);
-    return () => window.removeEventListener('storage', handleStorageChange);
   }, []);
 
   const formatFileSize = (bytes: number) => {
@@ -167,7 +151,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
   };
 
   const handlePreview = async (record: CompressRecord) => {
-    console.log('开始预览记录:', record.id, record.originalName);
+    console.log('开始预览记录:', record.id, record.original_name);
     setSelectedRecord(record);
 
     // 先清理之前的图片URL
@@ -196,7 +180,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
   };
 
   const handleEditRecord = (record: CompressRecord) => {
-    console.log('尝试编辑记录:', record.id, record.originalName);
+    console.log('尝试编辑记录:', record.id, record.original_name);
     // 调用父组件的回调，跳转到编辑页面
     if (onPreviewRecord) {
       console.log('调用父组件回调');
@@ -208,14 +192,14 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
   };
 
   const handleDownload = async (record: CompressRecord) => {
-    console.log('尝试下载压缩图片:', record.originalName);
+    console.log('尝试下载压缩图片:', record.original_name);
     try {
       const images = await restoreImagesFromRecord(record);
       if (images) {
         const extension = record.config.format === 'mozjpeg' ? 'jpg' :
           record.config.format === 'oxipng' ? 'png' :
             record.config.format;
-        const filename = `${record.originalName.split('.')[0]}_compressed.${extension}`;
+        const filename = `${record.original_name.split('.')[0]}_compressed.${extension}`;
 
         const blobUrl = URL.createObjectURL(images.compressedBlob);
         const link = document.createElement('a');
@@ -292,7 +276,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
           </div>
           <div className="stat-title">{t.compressHistory.avgCompressionRate}</div>
           <div className="stat-value text-success">
-            {(records.reduce((sum, record) => sum + record.compressionRatio, 0) / records.length).toFixed(1)}%
+            {(records.reduce((sum, record) => sum + record.compression_ratio, 0) / records.length).toFixed(1)}%
           </div>
         </div>
 
@@ -305,7 +289,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
           <div className="stat-title">{t.compressHistory.spaceSaved}</div>
           <div className="stat-value text-info">
             {formatFileSize(
-              records.reduce((sum, record) => sum + (record.originalSize - record.compressedSize), 0)
+              records.reduce((sum, record) => sum + (record.original_size - record.compressed_size), 0)
             )}
           </div>
         </div>
@@ -326,17 +310,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
                     onClick={() => {
                       console.log('调试信息:');
                       console.log('- 当前记录数:', records.length);
-                      console.log('- localStorage 测试:', testLocalStorage());
-                      const rawData = localStorage.getItem('compress_history');
-                      console.log('- localStorage 原始数据:', rawData);
-                      if (rawData) {
-                        try {
-                          const parsed = JSON.parse(rawData);
-                          console.log('- 解析后的数据:', parsed);
-                        } catch (e) {
-                          console.log('- 解析错误:', e);
-                        }
-                      }
+                      console.log('- 记录数据:', records);
                     }}
                     className="btn btn-ghost btn-xs text-info"
                     title={t.common.debug}
@@ -379,21 +353,24 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
                     </div>
                   </div>
                   <div className="flex-1 px-4 flex flex-col justify-center min-w-0">
-                    <div className="font-medium text-base-content truncate" title={record.originalName}>
-                      {record.originalName}
+                    <div className="font-medium text-base-content truncate" title={record.original_name}>
+                      {record.original_name}
                     </div>
+                    {record.upload_id && (
+                      <div className="text-xs text-success">{t.compressHistory.uploaded}</div>
+                    )}
                   </div>
                   <div className="w-24 text-right text-sm text-base-content/70 flex items-center justify-end">
-                    {formatFileSize(record.originalSize)}
+                    {formatFileSize(record.original_size)}
                   </div>
                   <div className="w-24 text-right text-sm text-base-content/70 flex items-center justify-end">
-                    {formatFileSize(record.compressedSize)}
+                    {formatFileSize(record.compressed_size)}
                   </div>
                   <div className="w-20 flex items-center justify-center">
-                    <div className={`badge ${record.compressionRatio >= 70 ? 'badge-success' :
-                      record.compressionRatio >= 50 ? 'badge-warning' : 'badge-error'
+                    <div className={`badge ${record.compression_ratio >= 70 ? 'badge-success' :
+                      record.compression_ratio >= 50 ? 'badge-warning' : 'badge-error'
                       } badge-sm`}>
-                      ↓ {record.compressionRatio.toFixed(1)}%
+                      ↓ {record.compression_ratio.toFixed(1)}%
                     </div>
                   </div>
                   <div className="w-16 flex items-center justify-center">
@@ -405,7 +382,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
                     {record.config.format !== 'oxipng' ? `${record.config.quality}%` : '-'}
                   </div>
                   <div className="w-32 text-right px-4 text-sm text-base-content/70 flex items-center justify-end">
-                    {formatDate(record.compressTime)}
+                    {formatDate(record.compressed_at * 1000)}
                   </div>
                   <div className="w-32 flex items-center justify-center gap-1">
                     <button
@@ -481,7 +458,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
           <div className="modal-box">
             <h3 className="font-bold text-lg mb-4">{t.compressHistory.confirmDelete}</h3>
             <p className="mb-4">
-              {t.compressHistory.confirmDeleteMessage} <span className="font-semibold">{deleteConfirmRecord.originalName}</span>?
+              {t.compressHistory.confirmDeleteMessage} <span className="font-semibold">{deleteConfirmRecord.original_name}</span>?
             </p>
             <p className="text-sm text-base-content/60 mb-6">
               {t.compressHistory.deleteWarning}
@@ -510,7 +487,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
       {selectedRecord && (
         <div className="modal modal-open">
           <div className="modal-box max-w-4xl">
-            <h3 className="font-bold text-lg mb-4">{t.compressHistory.compressionDetails} - {selectedRecord.originalName}</h3>
+            <h3 className="font-bold text-lg mb-4">{t.compressHistory.compressionDetails} - {selectedRecord.original_name}</h3>
 
             <div className="grid grid-cols-1 md:grid-cols-2 gap-6">
               {/* 压缩信息 */}
@@ -521,15 +498,15 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
                     <div className="space-y-2 text-sm">
                       <div className="flex justify-between">
                         <span>{t.compressHistory.originalSize}:</span>
-                        <span>{formatFileSize(selectedRecord.originalSize)}</span>
+                        <span>{formatFileSize(selectedRecord.original_size)}</span>
                       </div>
                       <div className="flex justify-between">
                         <span>{t.compressHistory.compressedSize}:</span>
-                        <span>{formatFileSize(selectedRecord.compressedSize)}</span>
+                        <span>{formatFileSize(selectedRecord.compressed_size)}</span>
                       </div>
                       <div className="flex justify-between">
                         <span>{t.compressHistory.compressionRate}:</span>
-                        <span className="text-success">↓ {selectedRecord.compressionRatio.toFixed(1)}%</span>
+                        <span className="text-success">↓ {selectedRecord.compression_ratio.toFixed(1)}%</span>
                       </div>
                       <div className="flex justify-between">
                         <span>{t.compressHistory.outputFormat}:</span>
@@ -543,7 +520,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
                       )}
                       <div className="flex justify-between">
                         <span>{t.compressHistory.compressTime}:</span>
-                        <span>{formatDate(selectedRecord.compressTime)}</span>
+                        <span>{formatDate(selectedRecord.compressed_at * 1000)}</span>
                       </div>
                     </div>
                   </div>
@@ -568,7 +545,7 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
                               />
                             </div>
                             <p className="text-xs text-base-content/60 mt-1">
-                              {formatFileSize(selectedRecord.originalSize)}
+                              {formatFileSize(selectedRecord.original_size)}
                             </p>
                           </div>
                           <div className="text-center">
@@ -581,8 +558,8 @@ export const CompressHistory: React.FC<CompressHistoryProps> = ({ onPreviewRecor
                               />
                             </div>
                             <p className="text-xs text-base-content/60 mt-1">
-                              {formatFileSize(selectedRecord.compressedSize)}
-                              <span className="text-success ml-1">(-{selectedRecord.compressionRatio.toFixed(1)}%)</span>
+                              {formatFileSize(selectedRecord.compressed_size)}
+                              <span className="text-success ml-1">(-{selectedRecord.compression_ratio.toFixed(1)}%)</span>
                             </p>
                           </div>
                         </div>
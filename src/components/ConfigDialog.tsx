@@ -1,6 +1,6 @@
 import React, { useState, useEffect } from "react";
 import { Settings } from "lucide-react";
-import { R2Config } from "../types";
+import { CredentialSource, ExpiryConfig, NetworkConfig, R2Config, SseConfig } from "../types";
 import { ImageHostingAPI } from "../api";
 import { useLanguage } from "../contexts/LanguageContext";
 
@@ -15,6 +15,8 @@ export const ConfigDialog: React.FC<ConfigDialogProps> = ({ onConfigSaved }) =>
   const [config, setConfig] = useState<R2Config>({
     access_key_id: "",
     secret_access_key: "",
+    session_token: "",
+    credential_source: { type: "inline" },
     endpoint: "",
     bucket_name: "",
     public_url_base: "",
@@ -37,8 +39,23 @@ export const ConfigDialog: React.FC<ConfigDialogProps> = ({ onConfigSaved }) =>
     }
   };
 
+  const credentialSource: CredentialSource = config.credential_source ?? { type: "inline" };
+
+  const hasCredentials = () => {
+    switch (credentialSource.type) {
+      case "inline":
+        return !!config.access_key_id && !!config.secret_access_key;
+      case "aws_profile":
+        return !!credentialSource.profile_name;
+      case "credential_process":
+        return !!credentialSource.command;
+      default:
+        return true;
+    }
+  };
+
   const handleSave = async () => {
-    if (!config.access_key_id || !config.secret_access_key || !config.endpoint || !config.bucket_name || !config.public_url_base) {
+    if (!hasCredentials() || (sse.type === "customer_key" && !sse.key) || !config.endpoint || !config.bucket_name || !config.public_url_base) {
       alert(t.config.fillAllFields);
       return;
     }
@@ -59,6 +76,53 @@ export const ConfigDialog: React.FC<ConfigDialogProps> = ({ onConfigSaved }) =>
     setConfig(prev => ({ ...prev, [field]: value }));
   };
 
+  const sse: SseConfig = config.server_side_encryption ?? { type: "none" };
+
+  const handleSseTypeChange = (type: SseConfig["type"]) => {
+    const next: SseConfig = type === "customer_key" ? { type, key: "" } : { type };
+    setConfig(prev => ({ ...prev, server_side_encryption: next }));
+  };
+
+  const handleGenerateCustomerKey = async () => {
+    try {
+      const key = await ImageHostingAPI.generateSseCustomerKey();
+      setConfig(prev => ({ ...prev, server_side_encryption: { type: "customer_key", key } }));
+    } catch (error) {
+      console.error("Failed to generate SSE-C key:", error);
+    }
+  };
+
+  const expiry: ExpiryConfig = config.expiry ?? {};
+  const DAY_SECS = 24 * 60 * 60;
+
+  const updateExpiry = (changes: Partial<ExpiryConfig>) => {
+    setConfig(prev => ({ ...prev, expiry: { ...expiry, ...changes } }));
+  };
+
+  const network: NetworkConfig = config.network ?? { ca_certificates: [] };
+
+  const updateNetwork = (changes: Partial<NetworkConfig>) => {
+    setConfig(prev => ({ ...prev, network: { ...network, ...changes } }));
+  };
+
+  const updateProxy = (field: "url" | "username" | "password", value: string) => {
+    const proxy = { url: "", no_proxy: [], ...network.proxy, [field]: value };
+    updateNetwork({ proxy: proxy.url || proxy.username || proxy.password ? proxy : null });
+  };
+
+  const parseTimeout = (value: string) => {
+    const secs = parseInt(value, 10);
+    return Number.isFinite(secs) && secs > 0 ? secs : null;
+  };
+
+  const handleSourceTypeChange = (type: CredentialSource["type"]) => {
+    const source: CredentialSource =
+      type === "aws_profile" ? { type, profile_name: "" }
+      : type === "credential_process" ? { type, command: "" }
+      : { type };
+    setConfig(prev => ({ ...prev, credential_source: source }));
+  };
+
   return (
     <>
       {/* 触发按钮 */}
@@ -89,31 +153,100 @@ export const ConfigDialog: React.FC<ConfigDialogProps> = ({ onConfigSaved }) =>
           <div className="space-y-4">
             <div className="form-control">
               <label className="label">
-                <span className="label-text font-semibold text-base-content">{t.config.accessKeyId}</span>
-                <span className="badge badge-error badge-sm">{t.common.required}</span>
+                <span className="label-text font-semibold text-base-content">{t.config.credentialSource}</span>
               </label>
-              <input
-                type="text"
-                value={config.access_key_id}
-                onChange={(e) => handleInputChange("access_key_id", e.target.value)}
-                placeholder={t.config.enterAccessKeyId}
-                className="input input-bordered w-full"
-              />
+              <select
+                value={credentialSource.type}
+                onChange={(e) => handleSourceTypeChange(e.target.value as CredentialSource["type"])}
+                className="select select-bordered w-full"
+              >
+                <option value="inline">{t.config.credentialSourceInline}</option>
+                <option value="environment">{t.config.credentialSourceEnvironment}</option>
+                <option value="aws_profile">{t.config.credentialSourceAwsProfile}</option>
+                <option value="credential_process">{t.config.credentialSourceProcess}</option>
+              </select>
             </div>
 
-            <div className="form-control">
-              <label className="label">
-                <span className="label-text font-semibold text-base-content">{t.config.secretAccessKey}</span>
-                <span className="badge badge-error badge-sm">{t.common.required}</span>
-              </label>
-              <input
-                type="password"
-                value={config.secret_access_key}
-                onChange={(e) => handleInputChange("secret_access_key", e.target.value)}
-                placeholder={t.config.enterSecretAccessKey}
-                className="input input-bordered w-full"
-              />
-            </div>
+            {credentialSource.type === "inline" && (
+              <>
+                <div className="form-control">
+                  <label className="label">
+                    <span className="label-text font-semibold text-base-content">{t.config.accessKeyId}</span>
+                    <span className="badge badge-error badge-sm">{t.common.required}</span>
+                  </label>
+                  <input
+                    type="text"
+                    value={config.access_key_id}
+                    onChange={(e) => handleInputChange("access_key_id", e.target.value)}
+                    placeholder={t.config.enterAccessKeyId}
+                    className="input input-bordered w-full"
+                  />
+                </div>
+
+                <div className="form-control">
+                  <label className="label">
+                    <span className="label-text font-semibold text-base-content">{t.config.secretAccessKey}</span>
+                    <span className="badge badge-error badge-sm">{t.common.required}</span>
+                  </label>
+                  <input
+                    type="password"
+                    value={config.secret_access_key}
+                    onChange={(e) => handleInputChange("secret_access_key", e.target.value)}
+                    placeholder={t.config.enterSecretAccessKey}
+                    className="input input-bordered w-full"
+                  />
+                </div>
+
+                <div className="form-control">
+                  <label className="label">
+                    <span className="label-text font-semibold text-base-content">{t.config.sessionToken}</span>
+                  </label>
+                  <input
+                    type="password"
+                    value={config.session_token ?? ""}
+                    onChange={(e) => handleInputChange("session_token", e.target.value)}
+                    placeholder={t.config.enterSessionToken}
+                    className="input input-bordered w-full"
+                  />
+                </div>
+              </>
+            )}
+
+            {credentialSource.type === "environment" && (
+              <p className="text-sm text-base-content/70">{t.config.environmentCredentialsHint}</p>
+            )}
+
+            {credentialSource.type === "aws_profile" && (
+              <div className="form-control">
+                <label className="label">
+                  <span className="label-text font-semibold text-base-content">{t.config.awsProfileName}</span>
+                  <span className="badge badge-error badge-sm">{t.common.required}</span>
+                </label>
+                <input
+                  type="text"
+                  value={credentialSource.profile_name}
+                  onChange={(e) => setConfig(prev => ({ ...prev, credential_source: { type: "aws_profile", profile_name: e.target.value } }))}
+                  placeholder={t.config.enterAwsProfileName}
+                  className="input input-bordered w-full"
+                />
+              </div>
+            )}
+
+            {credentialSource.type === "credential_process" && (
+              <div className="form-control">
+                <label className="label">
+                  <span className="label-text font-semibold text-base-content">{t.config.credentialProcessCommand}</span>
+                  <span className="badge badge-error badge-sm">{t.common.required}</span>
+                </label>
+                <input
+                  type="text"
+                  value={credentialSource.command}
+                  onChange={(e) => setConfig(prev => ({ ...prev, credential_source: { type: "credential_process", command: e.target.value } }))}
+                  placeholder={t.config.enterCredentialProcessCommand}
+                  className="input input-bordered w-full"
+                />
+              </div>
+            )}
 
             <div className="form-control">
               <label className="label">
@@ -156,6 +289,196 @@ export const ConfigDialog: React.FC<ConfigDialogProps> = ({ onConfigSaved }) =>
                 className="input input-bordered w-full"
               />
             </div>
+            <div className="form-control">
+              <label className="label">
+                <span className="label-text font-semibold text-base-content">{t.config.serverSideEncryption}</span>
+              </label>
+              <select
+                value={sse.type}
+                onChange={(e) => handleSseTypeChange(e.target.value as SseConfig["type"])}
+                className="select select-bordered w-full"
+              >
+                <option value="none">{t.config.sseNone}</option>
+                <option value="s3">{t.config.sseS3}</option>
+                <option value="customer_key">{t.config.sseCustomerKey}</option>
+              </select>
+            </div>
+
+            {sse.type === "customer_key" && (
+              <div className="form-control">
+                <label className="label">
+                  <span className="label-text font-semibold text-base-content">{t.config.sseCustomerKeyValue}</span>
+                  <span className="badge badge-error badge-sm">{t.common.required}</span>
+                </label>
+                <div className="flex gap-2">
+                  <input
+                    type="password"
+                    value={sse.key}
+                    onChange={(e) => setConfig(prev => ({ ...prev, server_side_encryption: { type: "customer_key", key: e.target.value } }))}
+                    placeholder={t.config.enterSseCustomerKey}
+                    className="input input-bordered w-full"
+                  />
+                  <button type="button" className="btn btn-outline" onClick={handleGenerateCustomerKey}>
+                    {t.config.generateKey}
+                  </button>
+                </div>
+                <p className="text-xs text-warning mt-1">{t.config.sseCustomerKeyHint}</p>
+              </div>
+            )}
+
+            <div className="grid grid-cols-2 gap-2">
+              <div className="form-control">
+                <label className="label">
+                  <span className="label-text font-semibold text-base-content">{t.config.defaultTtlDays}</span>
+                </label>
+                <input
+                  type="number"
+                  min={0}
+                  value={expiry.default_ttl_secs ? expiry.default_ttl_secs / DAY_SECS : ""}
+                  onChange={(e) => {
+                    const days = parseFloat(e.target.value);
+                    updateExpiry({ default_ttl_secs: Number.isFinite(days) && days > 0 ? Math.round(days * DAY_SECS) : null });
+                  }}
+                  placeholder={t.config.enterDefaultTtlDays}
+                  className="input input-bordered w-full"
+                />
+              </div>
+              <div className="form-control">
+                <label className="label">
+                  <span className="label-text font-semibold text-base-content">{t.config.expiryPrefix}</span>
+                </label>
+                <input
+                  type="text"
+                  value={expiry.prefix ?? ""}
+                  onChange={(e) => updateExpiry({ prefix: e.target.value || null })}
+                  placeholder={t.config.enterExpiryPrefix}
+                  className="input input-bordered w-full"
+                />
+              </div>
+            </div>
+
+            <div className="form-control">
+              <label className="label">
+                <span className="label-text font-semibold text-base-content">{t.config.monthlyBudget}</span>
+              </label>
+              <input
+                type="number"
+                min={0}
+                step="0.01"
+                value={config.billing?.monthly_budget ?? ""}
+                onChange={(e) => {
+                  const budget = parseFloat(e.target.value);
+                  setConfig(prev => ({
+                    ...prev,
+                    billing: { ...prev.billing, monthly_budget: Number.isFinite(budget) && budget >= 0 ? budget : null }
+                  }));
+                }}
+                placeholder={t.config.enterMonthlyBudget}
+                className="input input-bordered w-full"
+              />
+            </div>
+
+            <div className="collapse collapse-arrow border border-base-300 bg-base-100">
+              <input type="checkbox" />
+              <div className="collapse-title font-semibold text-base-content">
+                {t.config.networkSettings}
+              </div>
+              <div className="collapse-content space-y-2">
+                <p className="text-sm text-base-content/70">{t.config.networkSettingsHint}</p>
+
+                <div className="form-control">
+                  <label className="label">
+                    <span className="label-text font-semibold text-base-content">{t.config.proxyUrl}</span>
+                  </label>
+                  <input
+                    type="text"
+                    value={network.proxy?.url ?? ""}
+                    onChange={(e) => updateProxy("url", e.target.value)}
+                    placeholder={t.config.enterProxyUrl}
+                    className="input input-bordered w-full"
+                  />
+                </div>
+
+                <div className="grid grid-cols-2 gap-2">
+                  <div className="form-control">
+                    <label className="label">
+                      <span className="label-text font-semibold text-base-content">{t.config.proxyUsername}</span>
+                    </label>
+                    <input
+                      type="text"
+                      value={network.proxy?.username ?? ""}
+                      onChange={(e) => updateProxy("username", e.target.value)}
+                      className="input input-bordered w-full"
+                    />
+                  </div>
+                  <div className="form-control">
+                    <label className="label">
+                      <span className="label-text font-semibold text-base-content">{t.config.proxyPassword}</span>
+                    </label>
+                    <input
+                      type="password"
+                      value={network.proxy?.password ?? ""}
+                      onChange={(e) => updateProxy("password", e.target.value)}
+                      className="input input-bordered w-full"
+                    />
+                  </div>
+                </div>
+
+                <div className="form-control">
+                  <label className="label">
+                    <span className="label-text font-semibold text-base-content">{t.config.noProxy}</span>
+                  </label>
+                  <input
+                    type="text"
+                    value={network.proxy?.no_proxy.join(",") ?? ""}
+                    onChange={(e) => network.proxy && updateNetwork({ proxy: { ...network.proxy, no_proxy: e.target.value.split(",") } })}
+                    placeholder={t.config.enterNoProxy}
+                    disabled={!network.proxy}
+                    className="input input-bordered w-full"
+                  />
+                </div>
+
+                <div className="form-control">
+                  <label className="label">
+                    <span className="label-text font-semibold text-base-content">{t.config.caCertificates}</span>
+                  </label>
+                  <textarea
+                    value={network.ca_certificates.join("\n")}
+                    onChange={(e) => updateNetwork({ ca_certificates: e.target.value.split("\n") })}
+                    placeholder={t.config.enterCaCertificates}
+                    className="textarea textarea-bordered w-full"
+                    rows={2}
+                  />
+                </div>
+
+                <div className="grid grid-cols-2 gap-2">
+                  <div className="form-control">
+                    <label className="label">
+                      <span className="label-text font-semibold text-base-content">{t.config.connectTimeout}</span>
+                    </label>
+                    <input
+                      type="number"
+                      min={1}
+                      value={network.connect_timeout_secs ?? ""}
+                      onChange={(e) => updateNetwork({ connect_timeout_secs: parseTimeout(e.target.value) })}
+                      className="input input-bordered w-full"
+                    />
+                  </div>
+                  <div className="form-control">
+                    <label className="label">
+                      <span className="label-text font-semibold text-base-content">{t.config.readTimeout}</span>
+                    </label>
+                    <input
+                      type="number"
+                      min={1}
+                      value={network.read_timeout_secs ?? ""}
+                      onChange={(e) => updateNetwork({ read_timeout_secs: parseTimeout(e.target.value) })}
+                      className="input input-bordered w-full"
+                    />
+                  </div>
+                </div>
+              </div>
+            </div>
           </div>
 
           <div className="modal-action">
@@ -62,18 +62,18 @@ export const ImageCompressor: React.FC<ImageCompressorProps> = ({
           if (images) {
             setOriginalImage(images.originalFile);
             setConfig(restoreRecord.config);
-            setFileSource(restoreRecord.sourceType || 'file'); // 设置文件来源
+            setFileSource(restoreRecord.source || 'file'); // 设置文件来源
             setCompressedResult({
-              originalSize: restoreRecord.originalSize,
-              compressedSize: restoreRecord.compressedSize,
-              compressionRatio: restoreRecord.compressionRatio,
+              originalSize: restoreRecord.original_size,
+              compressedSize: restoreRecord.compressed_size,
+              compressionRatio: restoreRecord.compression_ratio,
               originalBlob: images.originalFile,
               compressedBlob: images.compressedBlob,
               originalUrl: URL.createObjectURL(images.originalFile),
               compressedUrl: URL.createObjectURL(images.compressedBlob),
             });
             setHasProcessed(true);
-            console.log('已恢复压缩记录:', restoreRecord.originalName);
+            console.log('已恢复压缩记录:', restoreRecord.original_name);
           } else {
             console.warn('无法恢复图片数据');
             alert('无法恢复图片数据，请重新选择文件');
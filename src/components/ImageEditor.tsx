@@ -137,8 +137,15 @@ export const ImageEditor: React.FC<ImageEditorProps> = ({
       // 转换为 Uint8Array
       const fileData = await ImageHostingAPI.convertFileToUint8Array(compressedFile);
 
-      // 上传图片
-      const result = await ImageHostingAPI.uploadImage(fileData, compressedFile.name);
+      // 上传图片，同时记录压缩前原图的哈希
+      const originalHash = await ImageHostingAPI.hashFile(originalImage);
+      const result = await ImageHostingAPI.uploadImage(
+        fileData,
+        compressedFile.name,
+        undefined,
+        undefined,
+        originalHash
+      );
 
       if (result.success) {
         onUploadSuccess(result);
@@ -151,7 +158,7 @@ export const ImageEditor: React.FC<ImageEditorProps> = ({
     } finally {
       setIsUploading(false);
     }
-  }, [compressedResult, originalImage.name, onUploadSuccess, onUploadError, t]);
+  }, [compressedResult, originalImage, onUploadSuccess, onUploadError, t]);
 
   // 使用 useMemo 优化尺寸计算，避免闪烁
   const calculatedDimensions = useMemo(() => {
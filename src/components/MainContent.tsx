@@ -188,6 +188,15 @@ const MainContent: React.FC<MainContentProps> = ({
                 </div>
               </div>
 
+              {apiProxyStatus?.token && (
+                <div className="flex flex-col">
+                  <span className="text-sm text-gray-600 dark:text-gray-400">{t.config.apiProxyToken}</span>
+                  <span className="text-sm font-mono text-primary break-all select-all">
+                    {apiProxyStatus.token}
+                  </span>
+                </div>
+              )}
+
               <div className="text-xs text-gray-500 dark:text-gray-400">
                 {t.config.apiProxyHint}
               </div>
@@ -0,0 +1,79 @@
+import React, { useState } from "react";
+import { Lock } from "lucide-react";
+import { ImageHostingAPI } from "../api";
+import { useLanguage } from "../contexts/LanguageContext";
+
+interface UnlockDialogProps {
+  onUnlocked: () => void;
+}
+
+export const UnlockDialog: React.FC<UnlockDialogProps> = ({ onUnlocked }) => {
+  const { t } = useLanguage();
+  const [passphrase, setPassphrase] = useState("");
+  const [loading, setLoading] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const handleUnlock = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!passphrase) return;
+
+    setLoading(true);
+    setError(null);
+    try {
+      const status = await ImageHostingAPI.unlockApp(passphrase);
+      if (!status.locked) {
+        setPassphrase("");
+        onUnlocked();
+      }
+    } catch (err) {
+      setError(`${t.security.unlockFailed}: ${err instanceof Error ? err.message : String(err)}`);
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  return (
+    <dialog className="modal modal-open">
+      <form className="modal-box w-11/12 max-w-md" onSubmit={handleUnlock}>
+        <div className="flex items-center gap-3 mb-4">
+          <div className="avatar placeholder">
+            <div className="bg-primary text-primary-content rounded-full w-8">
+              <Lock className="w-4 h-4 text-primary-content" />
+            </div>
+          </div>
+          <h3 className="font-bold text-lg text-base-content">{t.security.unlockTitle}</h3>
+        </div>
+
+        <p className="text-sm text-base-content/70 mb-4">
+          {t.security.unlockDescription}
+        </p>
+
+        <div className="form-control">
+          <label className="label">
+            <span className="label-text font-semibold text-base-content">{t.security.passphrase}</span>
+          </label>
+          <input
+            type="password"
+            autoFocus
+            value={passphrase}
+            onChange={(e) => setPassphrase(e.target.value)}
+            placeholder={t.security.enterPassphrase}
+            className="input input-bordered w-full"
+          />
+        </div>
+
+        {error && <p className="text-sm text-error mt-2">{error}</p>}
+
+        <div className="modal-action">
+          <button
+            type="submit"
+            className={`btn btn-primary ${loading ? 'loading' : ''}`}
+            disabled={loading || !passphrase}
+          >
+            <span className="text-primary-content">{loading ? t.security.unlocking : t.security.unlock}</span>
+          </button>
+        </div>
+      </form>
+    </dialog>
+  );
+};
@@ -1,7 +1,7 @@
 import React, { useCallback, useState, useEffect } from "react";
 import { Image as ImageIcon, CloudUpload } from "lucide-react";
 import { ImageHostingAPI } from "../api";
-import { UploadResult } from "../types";
+import { UploadResult, UploadSource } from "../types";
 import { listen } from "@tauri-apps/api/event";
 import { useLanguage } from "../contexts/LanguageContext";
 
@@ -20,11 +20,32 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
   console.log("[UploadArea] Component initializing...");
   const [isDragOver, setIsDragOver] = useState(false);
   const [isUploading, setIsUploading] = useState(false);
+  const [encrypt, setEncrypt] = useState(false);
+  // undefined 表示使用配置中的默认有效期，0 表示永久保存
+  const [ttlSecs, setTtlSecs] = useState<number | undefined>(undefined);
 
   console.log("[UploadArea] Component state:", { isDragOver, isUploading });
 
+  // 加密上传时图片在本地加密，返回带密钥片段的分享链接
+  const uploadData = useCallback(
+    async (fileData: Uint8Array, filename: string, source: UploadSource): Promise<UploadResult> => {
+      if (!encrypt) {
+        return ImageHostingAPI.uploadImage(fileData, filename, ttlSecs, source);
+      }
+      const result = await ImageHostingAPI.uploadEncryptedImage(fileData, filename, ttlSecs, source);
+      return {
+        success: result.success,
+        url: result.share_url ?? undefined,
+        error: result.error ?? undefined,
+        from_cache: false,
+        record: result.record,
+      };
+    },
+    [encrypt, ttlSecs]
+  );
+
   const handleFiles = useCallback(
-    async (files: FileList) => {
+    async (files: FileList, source: UploadSource) => {
       console.log("[UploadArea] === HANDLE FILES CALLED ===");
       console.log("[UploadArea] Files received:", files);
       console.log("[UploadArea] Files length:", files.length);
@@ -59,7 +80,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
         console.log("[UploadArea] File converted, size:", fileData.length, "bytes");
 
         console.log("[UploadArea] Calling upload API...");
-        const result = await ImageHostingAPI.uploadImage(fileData, file.name);
+        const result = await uploadData(fileData, file.name, source);
         console.log("[UploadArea] Upload API response:", result);
 
         if (result.success) {
@@ -83,7 +104,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
         console.log("[UploadArea] Upload process completed, isUploading set to false");
       }
     },
-    [onUploadSuccess, onUploadError]
+    [onUploadSuccess, onUploadError, uploadData]
   );
 
   const handleDragEnter = useCallback((e: React.DragEvent) => {
@@ -162,7 +183,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
         for (let i = 0; i < files.length; i++) {
           console.log(`[UploadArea] File ${i}:`, files[i]);
         }
-        handleFiles(files);
+        handleFiles(files, 'drag');
       } else {
         console.log("[UploadArea] No files in drop event");
       }
@@ -176,7 +197,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
       const files = e.target.files;
       if (files && files.length > 0) {
         console.log(`[UploadArea] Files selected via input: ${files.length}`);
-        handleFiles(files);
+        handleFiles(files, 'file');
       }
       // Reset input value to allow selecting the same file again
       e.target.value = "";
@@ -205,7 +226,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
           const fileData = await ImageHostingAPI.readFileFromPath(filePath);
           if (fileData) {
             const fileName = filePath.split('/').pop() || 'dropped-file';
-            const result = await ImageHostingAPI.uploadImage(fileData, fileName);
+            const result = await uploadData(fileData, fileName, 'drag');
             if (result.success) {
               onUploadSuccess(result);
             } else {
@@ -245,7 +266,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
         console.log("[UploadArea] Tauri listeners cleaned up.");
       });
     };
-  }, [isActive, onUploadSuccess, onUploadError]);
+  }, [isActive, onUploadSuccess, onUploadError, uploadData]);
 
 
 
@@ -265,7 +286,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
 
       const filename = `clipboard-${Date.now()}.png`;
       console.log("[UploadArea] Uploading clipboard image as:", filename);
-      const result = await ImageHostingAPI.uploadImage(clipboardData, filename);
+      const result = await uploadData(clipboardData, filename, 'clipboard');
       console.log("[UploadArea] Clipboard upload result:", result);
 
       if (result.success) {
@@ -282,7 +303,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
       setIsUploading(false);
       console.log("[UploadArea] Clipboard upload process completed");
     }
-  }, [onUploadSuccess, onUploadError, t]);
+  }, [onUploadSuccess, onUploadError, uploadData, t]);
 
   return (
     <div className="w-full space-y-6">
@@ -352,7 +373,7 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
       </div>
 
       {/* 剪贴板粘贴按钮 */}
-      <div className="flex justify-center">
+      <div className="flex items-center justify-center gap-4">
         <button
           onClick={handlePasteFromClipboard}
           disabled={isUploading}
@@ -361,6 +382,29 @@ export const UploadArea: React.FC<UploadAreaProps> = ({
           <ImageIcon className="w-4 h-4" />
           <span>{t.upload.pasteFromClipboard}</span>
         </button>
+        <label className="flex items-center gap-2 cursor-pointer text-sm text-gray-700 dark:text-gray-300" title={t.upload.encryptHint}>
+          <input
+            type="checkbox"
+            className="checkbox checkbox-sm checkbox-primary"
+            checked={encrypt}
+            onChange={(e) => setEncrypt(e.target.checked)}
+            disabled={isUploading}
+          />
+          <span>{t.upload.encryptUpload}</span>
+        </label>
+        <select
+          className="select select-bordered select-sm"
+          value={ttlSecs === undefined ? "" : String(ttlSecs)}
+          onChange={(e) => setTtlSecs(e.target.value === "" ? undefined : Number(e.target.value))}
+          disabled={isUploading}
+          title={t.upload.expiresAfter}
+        >
+          <option value="">{t.upload.expiryDefault}</option>
+          <option value="0">{t.upload.expiryNever}</option>
+          <option value={String(24 * 60 * 60)}>{t.upload.expiryOneDay}</option>
+          <option value={String(7 * 24 * 60 * 60)}>{t.upload.expiryOneWeek}</option>
+          <option value={String(30 * 24 * 60 * 60)}>{t.upload.expiryOneMonth}</option>
+        </select>
       </div>
 
       <div className="px-6 pb-4 pt-2">
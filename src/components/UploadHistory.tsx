@@ -1,6 +1,6 @@
-import React, { useState, useEffect } from "react";
-import { Copy, Trash2, RefreshCw, ChevronLeft, ChevronRight, Image as ImageIcon, Download, X, ExternalLink } from "lucide-react";
-import { UploadRecord } from "../types";
+import React, { useState, useEffect, useRef } from "react";
+import { Copy, Trash2, RefreshCw, ChevronLeft, ChevronRight, Image as ImageIcon, Download, Upload, X, ExternalLink } from "lucide-react";
+import { ExternalHistoryApp, HistoryExportFormat, HistorySort, UploadRecord } from "../types";
 import { ImageHostingAPI } from "../api";
 import { useLanguage } from "../contexts/LanguageContext";
 
@@ -15,17 +15,37 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
   const [searchTerm, setSearchTerm] = useState("");
   const [currentPage, setCurrentPage] = useState(1);
   const [previewRecord, setPreviewRecord] = useState<UploadRecord | null>(null);
+  const [sort, setSort] = useState<HistorySort>('newest');
+  const [nextCursor, setNextCursor] = useState<string | null>(null);
+  const [totalRecords, setTotalRecords] = useState(0);
+  const [isLoadingMore, setIsLoadingMore] = useState(false);
+  const [altText, setAltText] = useState("");
+  const [caption, setCaption] = useState("");
+  const [isSavingDetails, setIsSavingDetails] = useState(false);
+  const [exportFormat, setExportFormat] = useState<HistoryExportFormat>('json');
+  // 'export' 表示本应用导出的 JSON/CSV 文件
+  const [importSource, setImportSource] = useState<'export' | ExternalHistoryApp>('export');
+  const importInputRef = useRef<HTMLInputElement>(null);
   const itemsPerPage = 10;
+  // 每次从后端加载的记录数
+  const fetchSize = 100;
 
   useEffect(() => {
-    loadHistory();
-  }, [refreshTrigger]);
+    // 输入搜索词时稍作延迟再查询
+    const timer = setTimeout(loadHistory, searchTerm ? 300 : 0);
+    return () => clearTimeout(timer);
+  }, [refreshTrigger, sort, searchTerm]);
+
+  const filter = () => (searchTerm.trim() ? { text: searchTerm.trim() } : undefined);
 
   const loadHistory = async () => {
     try {
       setIsLoading(true);
-      const history = await ImageHostingAPI.getUploadHistory();
-      setRecords(history);
+      const page = await ImageHostingAPI.getUploadHistoryPage({ filter: filter(), sort, limit: fetchSize });
+      setRecords(page.records);
+      setNextCursor(page.next_cursor);
+      setTotalRecords(page.total);
+      setCurrentPage(1);
     } catch (error) {
       console.error("Failed to load history:", error);
     } finally {
@@ -33,6 +53,21 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
     }
   };
 
+  const handleLoadMore = async () => {
+    if (!nextCursor) return;
+    try {
+      setIsLoadingMore(true);
+      const page = await ImageHostingAPI.getUploadHistoryPage({ filter: filter(), sort, cursor: nextCursor, limit: fetchSize });
+      setRecords(prev => [...prev, ...page.records]);
+      setNextCursor(page.next_cursor);
+      setTotalRecords(page.total);
+    } catch (error) {
+      console.error("Failed to load more history:", error);
+    } finally {
+      setIsLoadingMore(false);
+    }
+  };
+
   const handleRefresh = () => {
     loadHistory();
   };
@@ -48,6 +83,47 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
     }
   };
 
+  const handleExport = async () => {
+    try {
+      // 导出当前搜索条件下的全部记录
+      const result = await ImageHostingAPI.exportUploadHistory(exportFormat, filter());
+      const type = exportFormat === 'json' ? 'application/json' : exportFormat === 'csv' ? 'text/csv' : 'text/markdown';
+      const blobUrl = URL.createObjectURL(new Blob([result.content], { type }));
+      const link = document.createElement('a');
+      link.href = blobUrl;
+      link.download = result.filename;
+      document.body.appendChild(link);
+      link.click();
+      document.body.removeChild(link);
+      setTimeout(() => URL.revokeObjectURL(blobUrl), 100);
+    } catch (error) {
+      console.error("Failed to export history:", error);
+      window.alert(`${t.history.exportFailed}: ${error}`);
+    }
+  };
+
+  const handleImport = async (e: React.ChangeEvent<HTMLInputElement>) => {
+    const file = e.target.files?.[0];
+    e.target.value = '';
+    if (!file) return;
+    try {
+      const content = await file.text();
+      const format: HistoryExportFormat = file.name.toLowerCase().endsWith('.csv') ? 'csv' : 'json';
+      const report = importSource === 'export'
+        ? await ImageHostingAPI.importUploadHistory(content, format)
+        : await ImageHostingAPI.importExternalHistory(importSource, content);
+      const summary = t.history.importSummary
+        .replace('{added}', String(report.added))
+        .replace('{merged}', String(report.merged))
+        .replace('{unchanged}', String(report.unchanged));
+      window.alert(report.errors.length ? `${summary}\n\n${report.errors.join('\n')}` : summary);
+      loadHistory();
+    } catch (error) {
+      console.error("Failed to import history:", error);
+      window.alert(`${t.history.importFailed}: ${error}`);
+    }
+  };
+
   const handleCopyUrl = async (url: string) => {
     try {
       await navigator.clipboard.writeText(url);
@@ -70,6 +146,40 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
 
   const handlePreview = (record: UploadRecord) => {
     setPreviewRecord(record);
+    setAltText(record.alt_text ?? "");
+    setCaption(record.caption ?? "");
+  };
+
+  const handleSaveDetails = async () => {
+    if (!previewRecord) return;
+    try {
+      setIsSavingDetails(true);
+      await ImageHostingAPI.setUploadAltText(previewRecord.id, altText);
+      await ImageHostingAPI.setUploadCaption(previewRecord.id, caption);
+      const updated = {
+        ...previewRecord,
+        alt_text: altText.trim() || null,
+        caption: caption.trim() || null,
+      };
+      setPreviewRecord(updated);
+      setRecords(prev => prev.map(record => (record.id === updated.id ? updated : record)));
+    } catch (error) {
+      console.error("Failed to save image details:", error);
+    } finally {
+      setIsSavingDetails(false);
+    }
+  };
+
+  const handleCopySnippet = async (format: 'markdown' | 'html') => {
+    if (!previewRecord) return;
+    try {
+      // 先保存未提交的修改，复制的内容与输入框一致
+      await handleSaveDetails();
+      const snippets = await ImageHostingAPI.getUploadSnippets(previewRecord.id);
+      await navigator.clipboard.writeText(snippets[format]);
+    } catch (error) {
+      console.error("Failed to copy snippet:", error);
+    }
   };
 
   const handleDownload = async (url: string, filename: string) => {
@@ -116,10 +226,8 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
     });
   };
 
-  // Filter records based on search term
-  const filteredHistory = records.filter(record =>
-    record.original_filename.toLowerCase().includes(searchTerm.toLowerCase())
-  );
+  // 搜索由后端完成
+  const filteredHistory = records;
 
   // Pagination
   const totalPages = Math.ceil(filteredHistory.length / itemsPerPage);
@@ -147,6 +255,64 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
               <RefreshCw className={`w-4 h-4 ${isLoading ? 'animate-spin' : ''}`} />
             </button>
           </div>
+          <select
+            value={sort}
+            onChange={(e) => setSort(e.target.value as HistorySort)}
+            className="h-10 px-2 rounded-lg border border-gray-300 dark:border-gray-700 bg-gray-50 dark:bg-gray-900/50 text-sm text-gray-900 dark:text-white outline-none"
+            title={t.history.sortBy}
+          >
+            <option value="newest">{t.history.sortNewest}</option>
+            <option value="oldest">{t.history.sortOldest}</option>
+            <option value="largest">{t.history.sortLargest}</option>
+            <option value="smallest">{t.history.sortSmallest}</option>
+            <option value="name">{t.history.sortName}</option>
+          </select>
+        </div>
+
+        <div className="flex items-center gap-2">
+          <select
+            value={exportFormat}
+            onChange={(e) => setExportFormat(e.target.value as HistoryExportFormat)}
+            className="h-9 px-2 rounded-lg border border-gray-300 dark:border-gray-700 bg-gray-50 dark:bg-gray-900/50 text-sm text-gray-900 dark:text-white outline-none"
+            title={t.history.exportFormat}
+          >
+            <option value="json">JSON</option>
+            <option value="csv">CSV</option>
+            <option value="markdown">Markdown</option>
+          </select>
+          <button
+            onClick={handleExport}
+            className="flex items-center gap-2 px-3 py-2 rounded-lg text-gray-700 hover:bg-gray-100 dark:text-gray-300 dark:hover:bg-gray-700 text-sm font-medium transition-colors"
+          >
+            <Download className="w-4 h-4" />
+            <span>{t.history.exportHistory}</span>
+          </button>
+          <select
+            value={importSource}
+            onChange={(e) => setImportSource(e.target.value as 'export' | ExternalHistoryApp)}
+            className="h-9 px-2 rounded-lg border border-gray-300 dark:border-gray-700 bg-gray-50 dark:bg-gray-900/50 text-sm text-gray-900 dark:text-white outline-none"
+            title={t.history.importSource}
+          >
+            <option value="export">{t.history.importFromExport}</option>
+            <option value="picgo">PicGo</option>
+            <option value="piclist">PicList</option>
+            <option value="upic">uPic</option>
+          </select>
+          <button
+            onClick={() => importInputRef.current?.click()}
+            className="flex items-center gap-2 px-3 py-2 rounded-lg text-gray-700 hover:bg-gray-100 dark:text-gray-300 dark:hover:bg-gray-700 text-sm font-medium transition-colors"
+            title={importSource === 'export' ? t.history.importHint : t.history.importExternalHint}
+          >
+            <Upload className="w-4 h-4" />
+            <span>{t.history.importHistory}</span>
+          </button>
+          <input
+            ref={importInputRef}
+            type="file"
+            accept={importSource === 'export' ? ".json,.csv" : ".json,.db"}
+            onChange={handleImport}
+            className="hidden"
+          />
         </div>
 
         <button
@@ -227,6 +393,18 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
                             Cached
                           </span>
                         )}
+                        {item.tags && item.tags.length > 0 && (
+                          <div className="flex flex-wrap gap-1 mt-1 max-w-[200px]">
+                            {item.tags.map((tag) => (
+                              <span
+                                key={tag}
+                                className="inline-flex items-center px-1.5 py-0.5 rounded text-xs bg-gray-100 text-gray-600 dark:bg-gray-700 dark:text-gray-300"
+                              >
+                                #{tag}
+                              </span>
+                            ))}
+                          </div>
+                        )}
                       </div>
                     </td>
                     <td className="px-4 py-3 text-right text-gray-500 dark:text-gray-400">
@@ -300,6 +478,22 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
         </div>
       )}
 
+      {/* 加载更多 */}
+      {nextCursor && (
+        <div className="flex flex-col items-center gap-1">
+          <button
+            onClick={handleLoadMore}
+            disabled={isLoadingMore}
+            className="px-4 py-2 rounded-lg border border-gray-200 dark:border-gray-700 text-sm text-gray-600 dark:text-gray-300 hover:bg-gray-50 dark:hover:bg-gray-800 disabled:opacity-50 transition-colors"
+          >
+            {isLoadingMore ? t.common.loading : t.history.loadMore}
+          </button>
+          <span className="text-xs text-gray-400">
+            {records.length} / {totalRecords}
+          </span>
+        </div>
+      )}
+
       {/* 图片预览模态框 */}
       {previewRecord && (
         <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/70 backdrop-blur-sm">
@@ -311,7 +505,11 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
                   {previewRecord.original_filename}
                 </h3>
                 <p className="text-sm text-gray-500 dark:text-gray-400">
-                  {formatFileSize(previewRecord.file_size)} • {formatDate(previewRecord.upload_time)}
+                  {formatFileSize(previewRecord.file_size)}
+                  {previewRecord.width && previewRecord.height
+                    ? ` • ${previewRecord.width} × ${previewRecord.height}`
+                    : ''}
+                  {' • '}{formatDate(previewRecord.upload_time)}
                 </p>
               </div>
               <button
@@ -326,7 +524,7 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
             <div className="relative bg-gray-100 dark:bg-gray-900 flex items-center justify-center" style={{ minHeight: '400px', maxHeight: '60vh' }}>
               <img
                 src={previewRecord.url}
-                alt={previewRecord.original_filename}
+                alt={previewRecord.alt_text || previewRecord.original_filename}
                 className="max-w-full max-h-[60vh] object-contain"
                 onError={(e) => {
                   const target = e.target as HTMLImageElement;
@@ -346,6 +544,55 @@ export const UploadHistory: React.FC<UploadHistoryProps> = ({ refreshTrigger })
               />
             </div>
 
+            {/* 替代文本与图片说明 */}
+            <div className="grid grid-cols-2 gap-3 p-4 border-t border-gray-200 dark:border-gray-700">
+              <label className="flex flex-col gap-1 text-sm text-gray-600 dark:text-gray-400">
+                {t.history.altText}
+                <input
+                  type="text"
+                  value={altText}
+                  onChange={(e) => setAltText(e.target.value)}
+                  placeholder={t.history.altTextPlaceholder}
+                  className="h-9 px-3 rounded-lg border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-sm text-gray-700 dark:text-gray-300"
+                />
+              </label>
+              <label className="flex flex-col gap-1 text-sm text-gray-600 dark:text-gray-400">
+                {t.history.caption}
+                <input
+                  type="text"
+                  value={caption}
+                  onChange={(e) => setCaption(e.target.value)}
+                  placeholder={t.history.captionPlaceholder}
+                  className="h-9 px-3 rounded-lg border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-sm text-gray-700 dark:text-gray-300"
+                />
+              </label>
+              <div className="col-span-2 flex items-center justify-end gap-2">
+                <button
+                  onClick={() => handleCopySnippet('markdown')}
+                  disabled={isSavingDetails}
+                  className="flex items-center gap-1.5 h-9 px-3 rounded-lg bg-gray-200 dark:bg-gray-700 text-gray-700 dark:text-gray-300 hover:bg-gray-300 dark:hover:bg-gray-600 text-sm font-medium transition-colors disabled:opacity-50"
+                >
+                  <Copy className="w-4 h-4" />
+                  {t.history.copyMarkdown}
+                </button>
+                <button
+                  onClick={() => handleCopySnippet('html')}
+                  disabled={isSavingDetails}
+                  className="flex items-center gap-1.5 h-9 px-3 rounded-lg bg-gray-200 dark:bg-gray-700 text-gray-700 dark:text-gray-300 hover:bg-gray-300 dark:hover:bg-gray-600 text-sm font-medium transition-colors disabled:opacity-50"
+                >
+                  <Copy className="w-4 h-4" />
+                  {t.history.copyHtml}
+                </button>
+                <button
+                  onClick={handleSaveDetails}
+                  disabled={isSavingDetails}
+                  className="h-9 px-4 rounded-lg bg-primary text-white hover:bg-primary/90 text-sm font-medium transition-colors disabled:opacity-50"
+                >
+                  {t.history.saveDetails}
+                </button>
+              </div>
+            </div>
+
             {/* 底部操作栏 */}
             <div className="flex items-center justify-between p-4 border-t border-gray-200 dark:border-gray-700 bg-gray-50 dark:bg-gray-800/50">
               <div className="flex items-center gap-2">
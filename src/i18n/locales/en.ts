@@ -59,11 +59,19 @@ export const en = {
     fetchUrlFailed: 'Failed to fetch URL',
     urlUploadSuccess: 'URL upload successful',
     processingUrlError: 'Error processing URL',
+    encryptUpload: 'Encrypt before upload',
+    encryptHint: 'Encrypts the image locally; the link carries the key after #, and the bucket only stores ciphertext.',
+    expiresAfter: 'Expires after',
+    expiryDefault: 'Default expiry',
+    expiryNever: 'Never expires',
+    expiryOneDay: '1 day',
+    expiryOneWeek: '7 days',
+    expiryOneMonth: '30 days',
   },
 
   // Upload History
   history: {
-    searchPlaceholder: 'Search filenames...',
+    searchPlaceholder: 'Search filenames, URLs, tags...',
     clearHistory: 'Clear History',
     noMatchingRecords: 'No matching records found',
     noUploadHistory: 'No upload history',
@@ -79,6 +87,30 @@ export const en = {
     confirmDeleteRecord: 'Are you sure you want to delete this record?',
     linkCopied: 'Link copied to clipboard',
     openInNewTab: 'Open in new tab',
+    sortBy: 'Sort by',
+    sortNewest: 'Newest first',
+    sortOldest: 'Oldest first',
+    sortLargest: 'Largest first',
+    sortSmallest: 'Smallest first',
+    sortName: 'Filename',
+    loadMore: 'Load more',
+    altText: 'Alt text',
+    altTextPlaceholder: 'Describe the image for screen readers',
+    caption: 'Caption',
+    captionPlaceholder: 'Shown below the image',
+    saveDetails: 'Save',
+    copyMarkdown: 'Copy Markdown',
+    copyHtml: 'Copy HTML',
+    exportFormat: 'Export format',
+    exportHistory: 'Export',
+    exportFailed: 'Export failed',
+    importHistory: 'Import',
+    importHint: 'Import a JSON or CSV history export',
+    importSummary: 'Imported history: {added} added, {merged} merged, {unchanged} already present',
+    importFailed: 'Import failed',
+    importSource: 'Import from',
+    importFromExport: 'History export',
+    importExternalHint: 'Pick the data.json (or picgo.db) of PicGo/PicList, or a uPic history export',
   },
 
   // Config Dialog
@@ -101,11 +133,50 @@ export const en = {
     configSaved: 'Configuration saved successfully!',
     configureR2: 'Please configure your Cloudflare R2 settings to enable image uploading.',
     configWarning: 'Note: Ensure your configuration is correct. Incorrect settings will cause upload failures.',
+    credentialSource: 'Credential Source',
+    credentialSourceInline: 'Access keys',
+    credentialSourceEnvironment: 'Environment variables',
+    credentialSourceAwsProfile: 'AWS profile',
+    credentialSourceProcess: 'credential_process command',
+    sessionToken: 'Session Token',
+    enterSessionToken: 'Only needed for temporary credentials',
+    awsProfileName: 'Profile Name',
+    enterAwsProfileName: 'Profile name from ~/.aws/credentials',
+    credentialProcessCommand: 'Command',
+    enterCredentialProcessCommand: 'Command that prints credential_process JSON',
+    environmentCredentialsHint: 'Reads AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and AWS_SESSION_TOKEN.',
+    serverSideEncryption: 'Server-Side Encryption',
+    sseNone: 'Bucket default',
+    sseS3: 'Provider-managed keys (SSE-S3)',
+    sseCustomerKey: 'Customer-provided key (SSE-C)',
+    sseCustomerKeyValue: 'Encryption Key',
+    enterSseCustomerKey: 'Base64-encoded 256-bit key',
+    generateKey: 'Generate',
+    sseCustomerKeyHint: 'Keep a copy of this key. Objects can't be read without it and their public URLs won't work.',
+    defaultTtlDays: 'Default Expiry (days)',
+    enterDefaultTtlDays: 'Empty keeps uploads forever',
+    expiryPrefix: 'Expiry Prefix',
+    enterExpiryPrefix: 'e.g. expiring/ for a lifecycle rule',
+    monthlyBudget: 'Monthly Budget (USD)',
+    enterMonthlyBudget: 'Warn when the estimated monthly cost reaches this amount',
+    networkSettings: 'Network',
+    networkSettingsHint: 'Optional proxy, trusted certificates and timeouts for reaching the storage endpoint.',
+    proxyUrl: 'Proxy URL',
+    enterProxyUrl: 'http://proxy:8080 or socks5://proxy:1080',
+    proxyUsername: 'Proxy Username',
+    proxyPassword: 'Proxy Password',
+    noProxy: 'Bypass Proxy For',
+    enterNoProxy: 'localhost, .internal.example.com, 10.0.0.0/8',
+    caCertificates: 'Extra CA Certificates',
+    enterCaCertificates: 'One PEM file path per line',
+    connectTimeout: 'Connect Timeout (s)',
+    readTimeout: 'Read Timeout (s)',
     apiProxyTitle: 'Local API Proxy',
     apiProxyDescription: 'Enable a local HTTP proxy so you can upload to R2 without opening the app.',
     apiProxyPort: 'Proxy Port',
     apiProxyEndpoint: 'Upload endpoint',
-    apiProxyHint: 'Send a multipart/form-data POST with a file field named "file" to /upload.',
+    apiProxyToken: 'Session token',
+    apiProxyHint: 'Send a multipart/form-data POST with a file field named "file" to /upload, with the header "Authorization: Bearer <session token>". The token changes every time the proxy starts.',
     apiProxyStatusFailed: 'Failed to load API proxy status.',
     apiProxyStarted: 'API proxy started.',
     apiProxyStopped: 'API proxy stopped.',
@@ -163,6 +234,7 @@ export const en = {
   // Compress History
   compressHistory: {
     title: 'Compression History',
+    uploaded: 'Uploaded',
     noRecords: 'No compression records',
     recordsWillAppear: 'Compressed images will appear here',
     totalCompressions: 'Total Compressions',
@@ -198,10 +270,22 @@ export const en = {
     restoreFailed: 'Restore failed',
   },
 
+  // Security
+  security: {
+    unlockTitle: 'Unlock Moli TuTu',
+    unlockDescription: 'Your configuration and upload history are encrypted. Enter your master passphrase to continue.',
+    passphrase: 'Master Passphrase',
+    enterPassphrase: 'Enter your master passphrase',
+    unlock: 'Unlock',
+    unlocking: 'Unlocking...',
+    unlockFailed: 'Unlock failed',
+  },
+
   // Notifications
   notification: {
     uploadSuccess: 'Image uploaded successfully!',
     uploadFailed: 'Upload failed',
     configSaved: 'Configuration saved successfully!',
+    budgetExceeded: 'Estimated storage cost exceeded the monthly budget',
   },
 };
@@ -59,11 +59,19 @@ export const zh = {
     fetchUrlFailed: '获取 URL 失败',
     urlUploadSuccess: 'URL 上传成功',
     processingUrlError: '处理 URL 时出错',
+    encryptUpload: '上传前加密',
+    encryptHint: '在本地加密图片；密钥位于链接 # 之后，存储桶中只保存密文。',
+    expiresAfter: '有效期',
+    expiryDefault: '默认有效期',
+    expiryNever: '永久保存',
+    expiryOneDay: '1 天',
+    expiryOneWeek: '7 天',
+    expiryOneMonth: '30 天',
   },
 
   // Upload History
   history: {
-    searchPlaceholder: '搜索文件名...',
+    searchPlaceholder: '搜索文件名、链接、标签...',
     clearHistory: '清空记录',
     noMatchingRecords: '没有找到匹配的记录',
     noUploadHistory: '暂无上传记录',
@@ -79,6 +87,30 @@ export const zh = {
     confirmDeleteRecord: '确定要删除这条记录吗？',
     linkCopied: '链接已复制到剪贴板',
     openInNewTab: '在新标签页打开',
+    sortBy: '排序方式',
+    sortNewest: '最新优先',
+    sortOldest: '最早优先',
+    sortLargest: '最大优先',
+    sortSmallest: '最小优先',
+    sortName: '文件名',
+    loadMore: '加载更多',
+    altText: '替代文本',
+    altTextPlaceholder: '为屏幕阅读器描述图片',
+    caption: '图片说明',
+    captionPlaceholder: '显示在图片下方',
+    saveDetails: '保存',
+    copyMarkdown: '复制 Markdown',
+    copyHtml: '复制 HTML',
+    exportFormat: '导出格式',
+    exportHistory: '导出',
+    exportFailed: '导出失败',
+    importHistory: '导入',
+    importHint: '导入 JSON 或 CSV 格式的历史记录',
+    importSummary: '导入完成：新增 {added} 条，合并 {merged} 条，已存在 {unchanged} 条',
+    importFailed: '导入失败',
+    importSource: '导入来源',
+    importFromExport: '历史记录导出文件',
+    importExternalHint: '选择 PicGo/PicList 的 data.json（或 picgo.db），或 uPic 导出的历史记录',
   },
 
   // Config Dialog
@@ -101,11 +133,50 @@ export const zh = {
     configSaved: '配置保存成功！',
     configureR2: '请配置您的 Cloudflare R2 设置以启用图片上传功能。',
     configWarning: '注意：请确保配置正确。错误的设置将导致上传失败。',
+    credentialSource: '凭证来源',
+    credentialSourceInline: '访问密钥',
+    credentialSourceEnvironment: '环境变量',
+    credentialSourceAwsProfile: 'AWS 配置档案',
+    credentialSourceProcess: 'credential_process 命令',
+    sessionToken: 'Session Token',
+    enterSessionToken: '仅临时凭证需要填写',
+    awsProfileName: '配置档案名称',
+    enterAwsProfileName: '~/.aws/credentials 中的配置档案名称',
+    credentialProcessCommand: '命令',
+    enterCredentialProcessCommand: '输出 credential_process JSON 的命令',
+    environmentCredentialsHint: '读取 AWS_ACCESS_KEY_ID、AWS_SECRET_ACCESS_KEY 和 AWS_SESSION_TOKEN。',
+    serverSideEncryption: '服务端加密',
+    sseNone: '存储桶默认设置',
+    sseS3: '服务商托管密钥 (SSE-S3)',
+    sseCustomerKey: '客户提供的密钥 (SSE-C)',
+    sseCustomerKeyValue: '加密密钥',
+    enterSseCustomerKey: 'Base64 编码的 256 位密钥',
+    generateKey: '生成',
+    sseCustomerKeyHint: '请妥善保存此密钥。没有它将无法读取对象，且公开链接无法访问。',
+    defaultTtlDays: '默认有效期（天）',
+    enterDefaultTtlDays: '留空表示永久保存',
+    expiryPrefix: '过期对象前缀',
+    enterExpiryPrefix: '例如 expiring/，用于生命周期规则',
+    monthlyBudget: '每月预算（美元）',
+    enterMonthlyBudget: '本月预估费用达到该金额时提醒',
+    networkSettings: '网络',
+    networkSettingsHint: '可选的代理、受信任证书和超时设置，用于访问存储端点。',
+    proxyUrl: '代理地址',
+    enterProxyUrl: 'http://proxy:8080 或 socks5://proxy:1080',
+    proxyUsername: '代理用户名',
+    proxyPassword: '代理密码',
+    noProxy: '不使用代理的地址',
+    enterNoProxy: 'localhost, .internal.example.com, 10.0.0.0/8',
+    caCertificates: '额外的 CA 证书',
+    enterCaCertificates: '每行一个 PEM 文件路径',
+    connectTimeout: '连接超时（秒）',
+    readTimeout: '读取超时（秒）',
     apiProxyTitle: '本地 API 代理',
     apiProxyDescription: '开启本地 HTTP 代理，避免打开应用也能上传到 R2。',
     apiProxyPort: '代理端口',
     apiProxyEndpoint: '上传地址',
-    apiProxyHint: '使用 multipart/form-data POST，文件字段名为 "file"。',
+    apiProxyToken: '会话令牌',
+    apiProxyHint: '使用 multipart/form-data POST，文件字段名为 "file"，并带上请求头 "Authorization: Bearer <会话令牌>"。每次启动代理都会生成新的令牌。',
     apiProxyStatusFailed: '获取 API 代理状态失败。',
     apiProxyStarted: 'API 代理已启动。',
     apiProxyStopped: 'API 代理已停止。',
@@ -163,6 +234,7 @@ export const zh = {
   // Compress History
   compressHistory: {
     title: '压缩记录',
+    uploaded: '已上传',
     noRecords: '暂无压缩记录',
     recordsWillAppear: '压缩图片后记录将显示在这里',
     totalCompressions: '总压缩次数',
@@ -198,10 +270,22 @@ export const zh = {
     restoreFailed: '恢复失败',
   },
 
+  // Security
+  security: {
+    unlockTitle: '解锁 Moli TuTu',
+    unlockDescription: '您的配置和上传历史已加密，请输入主密码继续。',
+    passphrase: '主密码',
+    enterPassphrase: '输入您的主密码',
+    unlock: '解锁',
+    unlocking: '解锁中...',
+    unlockFailed: '解锁失败',
+  },
+
   // Notifications
   notification: {
     uploadSuccess: '图片上传成功！',
     uploadFailed: '上传失败',
     configSaved: '配置保存成功！',
+    budgetExceeded: '本月预估存储费用已超出预算',
   },
 };
@@ -15,18 +15,42 @@ export interface CompressConfig {
   dithering: number;
 }
 
-// 压缩记录接口
+// 压缩来源类型
+export type CompressSource = 'file' | 'clipboard' | 'drag';
+
+// 压缩记录接口（保存在 Rust 端数据库，文件保存在应用数据目录）
 export interface CompressRecord {
   id: string;
-  originalName: string;
-  originalSize: number;
-  compressedSize: number;
-  compressionRatio: number;
-  compressTime: number;
+  original_name: string;
+  original_size: number;
+  compressed_size: number;
+  compression_ratio: number;
+  /** Unix 时间（秒） */
+  compressed_at: number;
   config: CompressConfig; // 完整的压缩配置
-  originalImagePath: string; // 原始图片文件路径
-  compressedImagePath: string; // 压缩后图片文件路径
-  sourceType: 'file' | 'clipboard' | 'drag'; // 图片来源类型
+  source: CompressSource; // 图片来源类型
+  content_type?: string | null; // 压缩后文件的类型
+  has_original: boolean; // 从浏览器存储迁移的旧记录可能没有原图
+  original_hash?: string | null;
+  compressed_hash: string;
+  /** 上传压缩后的图片时自动关联 */
+  upload_id?: string | null;
+}
+
+export interface SaveCompressionRequest {
+  original_name: string;
+  compression_ratio: number;
+  config: CompressConfig;
+  source: CompressSource;
+  content_type?: string;
+  original_size?: number;
+  compressed_at?: number;
+}
+
+export interface CompressionFiles {
+  record: CompressRecord;
+  original_data: number[] | null;
+  compressed_data: number[];
 }
 
 // 压缩结果接口
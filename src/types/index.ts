@@ -1,15 +1,172 @@
+export type CredentialSource =
+  | { type: 'inline' }
+  | { type: 'environment' }
+  | { type: 'aws_profile'; profile_name: string }
+  | { type: 'credential_process'; command: string };
+
+export type SseConfig =
+  | { type: 'none' }
+  | { type: 's3' }
+  | { type: 'customer_key'; key: string };
+
+export interface ExpiryConfig {
+  default_ttl_secs?: number | null;
+  prefix?: string | null;
+}
+
+/** USD price table used to estimate monthly cost; defaults to R2 pricing. */
+export interface BillingConfig {
+  class_a_price_per_million?: number;
+  class_b_price_per_million?: number;
+  class_a_free_ops?: number;
+  class_b_free_ops?: number;
+  storage_price_per_gb_month?: number;
+  storage_free_gb?: number;
+  monthly_budget?: number | null;
+}
+
+export interface OutboundProxy {
+  url: string;
+  username?: string | null;
+  password?: string | null;
+  no_proxy: string[];
+}
+
+export interface NetworkConfig {
+  proxy?: OutboundProxy | null;
+  ca_certificates: string[];
+  connect_timeout_secs?: number | null;
+  read_timeout_secs?: number | null;
+}
+
 export interface R2Config {
   access_key_id: string;
   secret_access_key: string;
+  session_token?: string | null;
+  credential_source?: CredentialSource;
+  network?: NetworkConfig;
+  server_side_encryption?: SseConfig;
+  expiry?: ExpiryConfig;
+  billing?: BillingConfig;
   endpoint: string;
   bucket_name: string;
   public_url_base: string;
 }
 
+export type UploaderStatus =
+  | { state: 'unconfigured' }
+  | { state: 'initializing' }
+  | { state: 'ready' }
+  | { state: 'error'; message: string; attempts: number; retry_in_secs: number | null };
+
+export interface AppSettings {
+  api_proxy_enabled: boolean;
+  api_proxy_port: number;
+  verify_cache_with_head: boolean;
+  history_retention?: RetentionPolicy;
+}
+
+export type RetentionPolicy =
+  | { type: 'unlimited' }
+  | { type: 'count'; max_records: number }
+  | { type: 'age'; max_age_days: number };
+
+export type HistorySort = 'newest' | 'oldest' | 'largest' | 'smallest' | 'name';
+
+export interface HistoryFilter {
+  /** Full-text query over filename, URL, tags and notes (word prefixes). */
+  text?: string;
+  /** Unix seconds, inclusive. */
+  uploaded_after?: number;
+  /** Unix seconds, exclusive. */
+  uploaded_before?: number;
+  min_size?: number;
+  max_size?: number;
+  /** Exact type, or a prefix ending in "/" such as "image/". */
+  content_type?: string;
+  profile?: string;
+  source?: UploadSource;
+  status?: UploadStatus;
+  /** Records carrying every one of these tags. */
+  tags?: string[];
+  /** Album id. */
+  album?: string;
+}
+
+export interface HistoryPageRequest {
+  filter?: HistoryFilter;
+  sort?: HistorySort;
+  /** next_cursor of the previous page, used with the same sort. */
+  cursor?: string | null;
+  limit?: number;
+}
+
+export interface HistoryPage {
+  records: UploadRecord[];
+  next_cursor: string | null;
+  total: number;
+}
+
+export interface TagCount {
+  name: string;
+  count: number;
+}
+
+export type HistoryExportFormat = 'json' | 'csv' | 'markdown';
+
+/** Other uploaders whose history can be imported. */
+export type ExternalHistoryApp = 'picgo' | 'piclist' | 'upic';
+
+export interface HistoryExport {
+  content: string;
+  /** Suggested name of the exported file. */
+  filename: string;
+  record_count: number;
+}
+
+export interface HistoryImportReport {
+  added: number;
+  /** Existing records that gained tags, notes, alt text or a caption. */
+  merged: number;
+  unchanged: number;
+  errors: string[];
+}
+
+export interface UploadSnippets {
+  url: string;
+  markdown: string;
+  html: string;
+}
+
+/** Records of an album are listed with HistoryFilter.album. */
+export interface Album {
+  id: string;
+  name: string;
+  description: string | null;
+  created_at: number;
+  upload_count: number;
+}
+
+export interface EncryptedUploadResult {
+  success: boolean;
+  share_url: string | null;
+  object_url: string | null;
+  view_url: string | null;
+  error: string | null;
+  record?: UploadRecord | null;
+}
+
+export interface SecurityStatus {
+  encryption_enabled: boolean;
+  locked: boolean;
+}
+
 export interface ApiProxyStatus {
   enabled: boolean;
   running: boolean;
   port: number;
+  // 代理运行时的会话令牌，每次启动都会重新生成
+  token: string | null;
 }
 
 export interface UploadRecord {
@@ -19,12 +176,194 @@ export interface UploadRecord {
   file_size: number;
   url: string;
   upload_time: number;
+  status: UploadStatus;
+  expires_at?: number | null;
+  content_type?: string | null;
+  /** Endpoint and bucket of the profile the file was uploaded with. */
+  profile?: string | null;
+  source?: UploadSource;
+  bucket?: string | null;
+  object_key?: string | null;
+  width?: number | null;
+  height?: number | null;
+  /** Program that sent the upload, e.g. the User-Agent of an API client. */
+  client?: string | null;
+  /** Hash of the file before processing; file_hash is of the stored bytes. */
+  original_hash?: string | null;
+  tags?: string[];
+  notes?: string | null;
+  /** Used in generated snippets; falls back to the caption. */
+  alt_text?: string | null;
+  caption?: string | null;
   from_cache?: boolean;
 }
 
+/** 'app' marks uploads from the window saved before the origin was recorded. */
+export type UploadSource =
+  | 'app'
+  | 'clipboard'
+  | 'drag'
+  | 'file'
+  | 'proxy'
+  | 'bucket_sync'
+  | 'picgo'
+  | 'piclist'
+  | 'upic';
+
+export type UploadStatus = 'active' | 'missing' | 'expired';
+
+export interface ExpiryReport {
+  expired: number;
+  already_missing: number;
+  errors: string[];
+}
+
 export interface UploadResult {
   success: boolean;
   url?: string;
   error?: string;
   from_cache: boolean;
+  record?: UploadRecord | null;
+}
+
+export interface PresignRequest {
+  key?: string;
+  filename?: string;
+  content_type: string;
+  expires_in_secs?: number;
+}
+
+export interface PresignedUpload {
+  method: string;
+  upload_url: string;
+  headers: Record<string, string>;
+  key: string;
+  public_url: string;
+  expires_at: number;
+}
+
+export interface BucketListRequest {
+  prefix?: string;
+  delimiter?: string;
+  continuation_token?: string;
+  max_keys?: number;
+}
+
+export interface RemoteObject {
+  key: string;
+  size: number;
+  last_modified?: number;
+  etag?: string;
+  content_type: string;
+  url: string;
+}
+
+export interface BucketListing {
+  prefix: string;
+  objects: RemoteObject[];
+  folders: string[];
+  next_continuation_token?: string;
+  is_truncated: boolean;
+}
+
+export interface UsageGroup {
+  name: string;
+  object_count: number;
+  total_bytes: number;
+}
+
+export interface BucketStats {
+  profile: string;
+  bucket_name: string;
+  object_count: number;
+  total_bytes: number;
+  free_tier_storage_bytes: number;
+  by_prefix: UsageGroup[];
+  by_month: UsageGroup[];
+  by_content_type: UsageGroup[];
+  largest_objects: RemoteObject[];
+  refreshed_at: number;
+}
+
+export type OperationClass = 'class_a' | 'class_b' | 'free';
+
+export interface OperationCount {
+  operation: string;
+  class: OperationClass;
+  count: number;
+}
+
+export interface OperationUsage {
+  profile: string;
+  month: string;
+  operations: OperationCount[];
+  class_a_ops: number;
+  class_b_ops: number;
+  free_ops: number;
+  storage_bytes?: number | null;
+  class_a_cost: number;
+  class_b_cost: number;
+  storage_cost: number;
+  estimated_cost: number;
+  monthly_budget?: number | null;
+  budget_exceeded: boolean;
+}
+
+export interface CorsRuleConfig {
+  id?: string | null;
+  allowed_origins: string[];
+  allowed_methods: string[];
+  allowed_headers?: string[];
+  expose_headers?: string[];
+  max_age_seconds?: number | null;
+}
+
+export interface LifecycleRuleConfig {
+  id: string;
+  enabled?: boolean;
+  /** Empty applies the rule to the whole bucket. */
+  prefix?: string | null;
+  expiration_days?: number | null;
+  abort_incomplete_multipart_days?: number | null;
+  /** Rule also has actions the app can't edit (transitions, tag filters, ...). */
+  has_other_actions?: boolean;
+}
+
+export type CorsPreset = 'public_read' | 'browser_upload';
+
+export type LifecyclePreset = 'abort_incomplete_uploads' | 'cleanup_probe_objects';
+
+export interface SyncOptions {
+  import_untracked?: boolean;
+  hash_mode?: 'etag' | 'download';
+  prefix?: string;
+  dry_run?: boolean;
+}
+
+export interface SyncReport {
+  scanned_objects: number;
+  tracked_records: number;
+  matched: number;
+  missing: UploadRecord[];
+  restored: UploadRecord[];
+  untracked: RemoteObject[];
+  imported: UploadRecord[];
+  skipped_records: number;
+  errors: string[];
+  started_at: number;
+  finished_at: number;
+}
+
+export type CheckStatus = 'passed' | 'failed' | 'skipped';
+
+export interface ConnectionCheck {
+  name: 'endpoint' | 'auth' | 'bucket' | 'list' | 'put' | 'get' | 'public_url' | 'delete';
+  status: CheckStatus;
+  message: string;
+  duration_ms: number;
+}
+
+export interface ConnectionTestReport {
+  success: boolean;
+  checks: ConnectionCheck[];
 }